@@ -1,9 +1,31 @@
+use crate::database::Database;
+use crate::files::patch::{self, PatchResult};
+use crate::files::read::{self, FileRange, FileRangeContent, ReadMode};
+use crate::files::search::{self, SearchFilesResult, SearchOptions};
+use crate::files::tree::{self, TreeListing, TreeOptions};
+use crate::files::write::{self, WritePrecondition, WriteResult};
+use crate::indexer::commands::IndexerState;
+use crate::rbac::{self, Permission, RbacError};
+use crate::sandbox::SandboxRoot;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::ipc::Channel;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tauri::State;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::Command;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+#[cfg(unix)]
+use libc::{SIGCONT, SIGHUP, SIGINT, SIGKILL, SIGQUIT, SIGSTOP, SIGTERM, SIGUSR1, SIGUSR2};
+#[cfg(windows)]
+use windows_signals::*;
 
 /// Error type for command operations
 #[derive(Debug, thiserror::Error)]
@@ -14,6 +36,16 @@ pub enum CommandError {
     ShellFailed(String),
     #[error("Path error: {0}")]
     PathError(String),
+    #[error("Process not found: {0}")]
+    ProcessNotFound(String),
+    #[error("Environment error: {0}")]
+    Env(String),
+    #[error("{0}")]
+    Rbac(#[from] RbacError),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("Write conflict: {0}")]
+    Conflict(String),
 }
 
 impl Serialize for CommandError {
@@ -25,27 +57,220 @@ impl Serialize for CommandError {
     }
 }
 
+/// A shell process tracked by the registry
+struct RunningProcess {
+    pid: u32,
+    /// Shell hex the execution was started for, whose permissions cover signalling it
+    shell_hex_id: Option<String>,
+    command: String,
+    cwd: String,
+    started_at: String,
+    killed: Arc<AtomicBool>,
+}
+
+/// Registry of running shell processes managed by Tauri, keyed by execution id
+#[derive(Default)]
+pub struct ShellState {
+    processes: Mutex<HashMap<String, RunningProcess>>,
+}
+
+impl ShellState {
+    fn register(&self, execution_id: &str, process: RunningProcess) {
+        self.processes
+            .lock()
+            .unwrap()
+            .insert(execution_id.to_string(), process);
+    }
+
+    fn unregister(&self, execution_id: &str) {
+        self.processes.lock().unwrap().remove(execution_id);
+    }
+
+    /// Send a signal to the process group of a running execution, checking the
+    /// calling agent's execute permission on its shell hex
+    fn signal(
+        &self,
+        db: &Database,
+        execution_id: &str,
+        agent_hex_id: Option<&str>,
+        signal: i32,
+    ) -> Result<(), CommandError> {
+        // Copied out so the registry isn't locked while the permission check queries the database
+        let (pid, shell_hex_id, killed) = {
+            let processes = self.processes.lock().unwrap();
+            let process = processes
+                .get(execution_id)
+                .ok_or_else(|| CommandError::ProcessNotFound(execution_id.to_string()))?;
+            (process.pid, process.shell_hex_id.clone(), process.killed.clone())
+        };
+
+        if agent_hex_id.is_some() {
+            let shell_hex_id = shell_hex_id.as_deref().ok_or_else(|| {
+                RbacError::Denied("agents can only signal executions of a shell hex".to_string())
+            })?;
+            rbac::authorize(db, agent_hex_id, shell_hex_id, Permission::Execute)?;
+        }
+
+        if is_terminating_signal(signal) {
+            killed.store(true, Ordering::SeqCst);
+        }
+        signal_process_group(pid, signal)
+    }
+}
+
+/// Removes an execution from the registry when it goes out of scope
+struct RegistryGuard<'a> {
+    state: &'a ShellState,
+    execution_id: String,
+}
+
+impl Drop for RegistryGuard<'_> {
+    fn drop(&mut self) {
+        self.state.unregister(&self.execution_id);
+    }
+}
+
+/// Information about a running shell process
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunningShell {
+    pub execution_id: String,
+    pub pid: u32,
+    pub command: String,
+    pub cwd: String,
+    pub started_at: String,
+}
+
+/// Default cap on combined stdout/stderr bytes captured per execution
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 10 * 1024 * 1024;
+
+/// Time allowed after the shell exits, on its own or killed, for the output readers to finish
+///
+/// A process that left the group, such as a `setsid` daemon, can keep the
+/// pipes open after everything else is dead.
+const KILL_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default cap on the length of a single output line
+const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// Secrets shorter than this are not masked, to avoid mangling ordinary output
+const MIN_MASKED_SECRET_LEN: usize = 4;
+
+/// Replacement for secret values in shell output
+const SECRET_MASK: &str = "********";
+
+/// Start of a reference to a setting in an env value
+const SETTING_REF_PREFIX: &str = "${setting:";
+
+/// Environment configuration for a shell execution
+///
+/// Values in a shell hex's `set` may reference settings as `${setting:key}`;
+/// resolved values are treated as secrets and masked in the command's output.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ShellEnv {
+    /// Variables to set
+    #[serde(default)]
+    pub set: HashMap<String, String>,
+    /// Inherited variables to remove
+    #[serde(default)]
+    pub unset: Vec<String>,
+    /// Start from an empty environment instead of inheriting the app's
+    #[serde(default)]
+    pub clear: bool,
+    /// Inherited variables kept when `clear` is set
+    #[serde(default)]
+    pub allowlist: Vec<String>,
+}
+
+impl ShellEnv {
+    /// Layer `other` on top of this configuration; `other` wins on conflicts
+    fn merge(mut self, other: ShellEnv) -> ShellEnv {
+        for key in other.set.keys() {
+            self.unset.retain(|k| k != key);
+        }
+        for key in &other.unset {
+            self.set.remove(key);
+        }
+        self.set.extend(other.set);
+        self.unset.extend(other.unset);
+        self.clear |= other.clear;
+        self.allowlist.extend(other.allowlist);
+        self
+    }
+
+    /// Refuse setting references, which only a shell hex's saved config may use
+    ///
+    /// Otherwise the caller could load any setting into the shell and read it
+    /// back in a form masking doesn't catch.
+    fn reject_setting_refs(&self) -> Result<(), CommandError> {
+        match self.set.iter().find(|(_, value)| value.contains(SETTING_REF_PREFIX)) {
+            Some((key, _)) => Err(CommandError::Env(format!(
+                "{} references a setting; only the shell hex's env config may reference settings",
+                key
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Per-call options for shell execution
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct ShellOptions {
+    /// Shell hex whose `env` config applies to this call
+    pub shell_hex_id: Option<String>,
+    /// Agent hex making the call; it needs execute permission on `shell_hex_id`
+    pub agent_hex_id: Option<String>,
+    /// Environment changes for this call, applied on top of the shell hex's;
+    /// these may not reference settings
+    pub env: Option<ShellEnv>,
+    /// Kill the process group if it runs longer than this
+    pub timeout_ms: Option<u64>,
+    /// Maximum combined stdout/stderr bytes to capture and stream
+    pub max_output_bytes: Option<usize>,
+    /// Maximum bytes kept from a single line; the rest of the line is dropped
+    pub max_line_length: Option<usize>,
+}
+
 /// Result of shell command execution
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ShellResult {
+    pub execution_id: String,
     pub exit_code: i32,
     pub stdout: String,
     pub stderr: String,
+    /// Whether the process was stopped via `shell_kill` or `shell_signal`
+    pub killed: bool,
+    /// Whether the process was killed for exceeding `timeout_ms`
+    pub timed_out: bool,
+    /// Whether output was cut short by `max_output_bytes` or `max_line_length`
+    pub truncated: bool,
 }
 
 /// Event sent during shell execution for streaming output
 #[derive(Debug, Clone, Serialize)]
 pub enum ShellEvent {
+    Started { execution_id: String, pid: u32 },
     Stdout(String),
     Stderr(String),
+    /// Output capture stopped after reaching `max_output_bytes`
+    Truncated { max_output_bytes: usize },
+    /// The process group was killed after running for `timeout_ms`
+    TimedOut { timeout_ms: u64 },
     Exit(i32),
 }
 
 /// Execute a shell command with optional streaming output
+///
+/// The process runs in its own process group and is tracked in the registry
+/// under `execution_id` (generated if not provided) until it exits, so it can
+/// be stopped with `shell_kill` or `shell_signal`.
 #[tauri::command]
 pub async fn execute_shell(
+    state: State<'_, ShellState>,
+    db: State<'_, Database>,
     command: String,
     cwd: Option<String>,
+    execution_id: Option<String>,
+    options: Option<ShellOptions>,
     on_event: Channel<ShellEvent>,
 ) -> Result<ShellResult, CommandError> {
     let shell = if cfg!(target_os = "windows") {
@@ -59,81 +284,589 @@ pub async fn execute_shell(
         "-c"
     };
 
+    let options = options.unwrap_or_default();
+    if options.agent_hex_id.is_some() {
+        let shell_hex_id = options.shell_hex_id.as_deref().ok_or_else(|| {
+            RbacError::Denied("agent shell calls must name a shell hex".to_string())
+        })?;
+        rbac::authorize(&db, options.agent_hex_id.as_deref(), shell_hex_id, Permission::Execute)?;
+    }
+
     let working_dir = cwd.unwrap_or_else(|| ".".to_string());
+    let execution_id = execution_id.unwrap_or_else(|| Uuid::new_v4().to_string());
 
-    let mut child = Command::new(shell)
-        .arg(shell_arg)
+    let mut cmd = Command::new(shell);
+    cmd.arg(shell_arg)
         .arg(&command)
         .current_dir(&working_dir)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .spawn()?;
+        .kill_on_drop(true);
+
+    let secrets = apply_env(&mut cmd, &db, &options)?;
+
+    // Put the shell in its own process group so the whole tree can be signalled
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let mut child = cmd.spawn()?;
+    let pid = child.id().unwrap_or_default();
+
+    let killed = Arc::new(AtomicBool::new(false));
+    state.register(
+        &execution_id,
+        RunningProcess {
+            pid,
+            shell_hex_id: options.shell_hex_id.clone(),
+            command: command.clone(),
+            cwd: working_dir.clone(),
+            started_at: Utc::now().to_rfc3339(),
+            killed: killed.clone(),
+        },
+    );
+    let _guard = RegistryGuard {
+        state: &state,
+        execution_id: execution_id.clone(),
+    };
+
+    let _ = on_event.send(ShellEvent::Started {
+        execution_id: execution_id.clone(),
+        pid,
+    });
 
     let stdout = child.stdout.take().expect("Failed to capture stdout");
     let stderr = child.stderr.take().expect("Failed to capture stderr");
+    let (stop_readers, readers_stopped) = watch::channel(false);
 
-    let mut stdout_reader = BufReader::new(stdout).lines();
-    let mut stderr_reader = BufReader::new(stderr).lines();
-
-    let mut stdout_buf = String::new();
-    let mut stderr_buf = String::new();
+    // Both streams draw from one output budget
+    let limits = OutputLimits {
+        secrets: Arc::new(secrets),
+        max_output_bytes: options.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+        max_line_length: options.max_line_length.unwrap_or(DEFAULT_MAX_LINE_LENGTH),
+        remaining: Arc::new(AtomicUsize::new(
+            options.max_output_bytes.unwrap_or(DEFAULT_MAX_OUTPUT_BYTES),
+        )),
+        exhausted: Arc::new(AtomicBool::new(false)),
+    };
 
     // Stream stdout
-    let on_event_clone = on_event.clone();
-    let stdout_handle = tokio::spawn(async move {
-        let mut buf = String::new();
-        while let Ok(Some(line)) = stdout_reader.next_line().await {
-            let _ = on_event_clone.send(ShellEvent::Stdout(line.clone()));
-            buf.push_str(&line);
-            buf.push('\n');
-        }
-        buf
-    });
+    let stdout_handle = tokio::spawn(capture_stream(
+        stdout,
+        ShellEvent::Stdout,
+        limits.clone(),
+        on_event.clone(),
+        readers_stopped.clone(),
+    ));
 
     // Stream stderr
-    let on_event_clone = on_event.clone();
-    let stderr_handle = tokio::spawn(async move {
-        let mut buf = String::new();
-        while let Ok(Some(line)) = stderr_reader.next_line().await {
-            let _ = on_event_clone.send(ShellEvent::Stderr(line.clone()));
-            buf.push_str(&line);
-            buf.push('\n');
+    let stderr_handle = tokio::spawn(capture_stream(
+        stderr,
+        ShellEvent::Stderr,
+        limits.clone(),
+        on_event.clone(),
+        readers_stopped,
+    ));
+
+    // Wait for the process, bounded by the timeout if one was given
+    let mut timed_out = false;
+    let status = match options.timeout_ms {
+        Some(timeout_ms) => {
+            match tokio::time::timeout(Duration::from_millis(timeout_ms), child.wait()).await {
+                Ok(status) => status?,
+                Err(_) => {
+                    timed_out = true;
+                    let _ = on_event.send(ShellEvent::TimedOut { timeout_ms });
+                    let _ = signal_process_group(pid, SIGKILL);
+                    child.wait().await?
+                }
+            }
         }
-        buf
-    });
+        None => child.wait().await?,
+    };
 
-    // Wait for all streams and the process
-    stdout_buf = stdout_handle.await.unwrap_or_default();
-    stderr_buf = stderr_handle.await.unwrap_or_default();
+    // The shell has exited, on its own or killed; a stray process that left
+    // its group can still hold the pipes open, so only drain them for a while
+    let drain = async {
+        let stdout = stdout_handle.await.unwrap_or_default();
+        let stderr = stderr_handle.await.unwrap_or_default();
+        (stdout, stderr)
+    };
+    tokio::pin!(drain);
+    let (stdout, stderr) = match tokio::time::timeout(KILL_DRAIN_TIMEOUT, &mut drain).await {
+        Ok(output) => output,
+        Err(_) => {
+            // Keep what was read so far rather than wait on a stray process
+            let _ = stop_readers.send(true);
+            drain.await
+        }
+    };
 
-    let status = child.wait().await?;
     let exit_code = status.code().unwrap_or(-1);
 
     let _ = on_event.send(ShellEvent::Exit(exit_code));
 
     Ok(ShellResult {
+        execution_id,
         exit_code,
-        stdout: stdout_buf,
-        stderr: stderr_buf,
+        stdout: stdout.text,
+        stderr: stderr.text,
+        killed: killed.load(Ordering::SeqCst),
+        timed_out,
+        truncated: stdout.truncated || stderr.truncated || limits.exhausted.load(Ordering::SeqCst),
     })
 }
 
+/// Output limits and masking shared by the stdout and stderr readers of one execution
+#[derive(Clone)]
+struct OutputLimits {
+    /// Secret values masked before output is streamed or buffered
+    secrets: Arc<Vec<String>>,
+    max_output_bytes: usize,
+    max_line_length: usize,
+    /// Bytes left in the combined output budget
+    remaining: Arc<AtomicUsize>,
+    /// Set once the budget runs out, so `Truncated` is only sent once
+    exhausted: Arc<AtomicBool>,
+}
+
+/// Output captured from one stream
+#[derive(Debug, Default)]
+struct CapturedOutput {
+    text: String,
+    /// Whether any line from this stream was shortened
+    truncated: bool,
+}
+
+/// Read a stream line by line, streaming and buffering each line within the limits
+///
+/// Lines are assembled from the reader's buffer rather than with `lines()` so a
+/// single huge line never has to be held in memory. Once the output budget is
+/// spent the stream is still drained (and discarded) so the process never
+/// blocks on a full pipe. Reading stops early once `stop` is set.
+async fn capture_stream<R>(
+    reader: R,
+    to_event: fn(String) -> ShellEvent,
+    limits: OutputLimits,
+    on_event: Channel<ShellEvent>,
+    mut stop: watch::Receiver<bool>,
+) -> CapturedOutput
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut output = CapturedOutput::default();
+    let mut line: Vec<u8> = Vec::new();
+    let mut line_truncated = false;
+
+    // Keep enough past the line limit to recognise a secret that straddles it
+    let secret_slack = limits.secrets.iter().map(String::len).max().unwrap_or(0);
+    let max_buffered = limits.max_line_length.saturating_add(secret_slack);
+
+    loop {
+        let read = tokio::select! {
+            read = reader.fill_buf() => read,
+            _ = stop.wait_for(|stopped| *stopped) => break,
+        };
+        let (consumed, line_complete) = match read {
+            Ok(available) if !available.is_empty() => {
+                let newline = available.iter().position(|b| *b == b'\n');
+                let segment = &available[..newline.unwrap_or(available.len())];
+
+                let room = max_buffered.saturating_sub(line.len());
+                if segment.len() > room {
+                    line.extend_from_slice(&segment[..room]);
+                    line_truncated = true;
+                } else {
+                    line.extend_from_slice(segment);
+                }
+
+                (segment.len() + newline.map_or(0, |_| 1), newline.is_some())
+            }
+            _ => break,
+        };
+        reader.consume(consumed);
+
+        if line_complete {
+            emit_line(&line, line_truncated, to_event, &limits, &on_event, &mut output);
+            line.clear();
+            line_truncated = false;
+        }
+    }
+
+    if !line.is_empty() {
+        emit_line(&line, line_truncated, to_event, &limits, &on_event, &mut output);
+    }
+
+    output
+}
+
+/// Stream and buffer a single line if the output budget allows it
+fn emit_line(
+    line: &[u8],
+    line_truncated: bool,
+    to_event: fn(String) -> ShellEvent,
+    limits: &OutputLimits,
+    on_event: &Channel<ShellEvent>,
+    output: &mut CapturedOutput,
+) {
+    let (text, truncated) = format_line(line, line_truncated, limits);
+    output.truncated |= truncated;
+
+    // Once a line doesn't fit, later lines are dropped too so none are skipped
+    if limits.exhausted.load(Ordering::SeqCst) {
+        return;
+    }
+
+    // Reserve space for the line and its newline in the shared budget
+    let needed = text.len() + 1;
+    let reserved = limits
+        .remaining
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |r| r.checked_sub(needed))
+        .is_ok();
+
+    if !reserved {
+        // Also stop a shorter line racing in from the other stream
+        limits.remaining.store(0, Ordering::SeqCst);
+        if !limits.exhausted.swap(true, Ordering::SeqCst) {
+            let _ = on_event.send(ShellEvent::Truncated {
+                max_output_bytes: limits.max_output_bytes,
+            });
+        }
+        return;
+    }
+
+    let _ = on_event.send(to_event(text.clone()));
+    output.text.push_str(&text);
+    output.text.push('\n');
+}
+
+/// Suffix added to lines cut at `max_line_length`
+const LINE_TRUNCATED_MARKER: &str = " [line truncated]";
+
+/// Mask a captured line's secrets, then cut it to `max_line_length`
+///
+/// `line` may run past the limit by up to the longest secret, so a secret
+/// that straddles the limit is masked as a whole before the cut. `dropped`
+/// says whether bytes beyond that were already discarded. Returns the line
+/// and whether it was cut.
+fn format_line(line: &[u8], dropped: bool, limits: &OutputLimits) -> (String, bool) {
+    let mut text = mask_secrets(&String::from_utf8_lossy(line), &limits.secrets);
+    if text.ends_with('\r') {
+        text.pop();
+    }
+    let truncated = dropped || text.len() > limits.max_line_length;
+    if truncated {
+        let mut end = limits.max_line_length.min(text.len());
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+        text.push_str(LINE_TRUNCATED_MARKER);
+    }
+    (text, truncated)
+}
+
+/// Apply the shell hex's and the call's environment config to `cmd`
+///
+/// Returns the secret values resolved from settings so they can be masked.
+fn apply_env(
+    cmd: &mut Command,
+    db: &Database,
+    options: &ShellOptions,
+) -> Result<Vec<String>, CommandError> {
+    let mut env = match &options.shell_hex_id {
+        Some(shell_hex_id) => shell_hex_env(db, shell_hex_id)?,
+        None => ShellEnv::default(),
+    };
+    if let Some(call_env) = &options.env {
+        call_env.reject_setting_refs()?;
+        env = env.merge(call_env.clone());
+    }
+
+    if env.clear {
+        let kept: Vec<(String, std::ffi::OsString)> = env
+            .allowlist
+            .iter()
+            .filter_map(|key| std::env::var_os(key).map(|value| (key.clone(), value)))
+            .collect();
+        cmd.env_clear();
+        cmd.envs(kept);
+    }
+
+    for key in &env.unset {
+        cmd.env_remove(key);
+    }
+
+    let mut secrets = Vec::new();
+    for (key, value) in &env.set {
+        let resolved = expand_setting_refs(value, &mut secrets, |setting_key| {
+            db.get_setting(setting_key)
+                .map_err(|e| CommandError::Env(e.to_string()))
+        })?;
+        cmd.env(key, resolved);
+    }
+
+    Ok(secrets)
+}
+
+/// Read the `env` section of a shell hex's tool config
+fn shell_hex_env(db: &Database, shell_hex_id: &str) -> Result<ShellEnv, CommandError> {
+    let hex = db
+        .get_hex(shell_hex_id)
+        .map_err(|e| CommandError::Env(e.to_string()))?
+        .ok_or_else(|| CommandError::Env(format!("Shell hex not found: {}", shell_hex_id)))?;
+
+    let config: serde_json::Value = serde_json::from_str(&hex.config)
+        .map_err(|e| CommandError::Env(format!("Invalid hex config: {}", e)))?;
+
+    match config.get("config").and_then(|c| c.get("env")) {
+        Some(env) => serde_json::from_value(env.clone())
+            .map_err(|e| CommandError::Env(format!("Invalid env config: {}", e))),
+        None => Ok(ShellEnv::default()),
+    }
+}
+
+/// Replace `${setting:key}` references with values from `lookup`
+///
+/// Every substituted value is appended to `secrets`.
+fn expand_setting_refs<F>(
+    value: &str,
+    secrets: &mut Vec<String>,
+    mut lookup: F,
+) -> Result<String, CommandError>
+where
+    F: FnMut(&str) -> Result<Option<String>, CommandError>,
+{
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find(SETTING_REF_PREFIX) {
+        let after_prefix = &rest[start + SETTING_REF_PREFIX.len()..];
+        let Some(end) = after_prefix.find('}') else {
+            break;
+        };
+
+        let key = &after_prefix[..end];
+        let setting = lookup(key)?
+            .ok_or_else(|| CommandError::Env(format!("Setting not found: {}", key)))?;
+
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&setting);
+        secrets.push(setting);
+        rest = &after_prefix[end + 1..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Mask every occurrence of a known secret value in `text`
+fn mask_secrets(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| secret.len() >= MIN_MASKED_SECRET_LEN)
+        .fold(text.to_string(), |masked, secret| masked.replace(secret.as_str(), SECRET_MASK))
+}
+
+/// Kill a running shell execution and every process in its process group
+#[tauri::command]
+pub fn shell_kill(
+    state: State<'_, ShellState>,
+    db: State<'_, Database>,
+    execution_id: String,
+    agent_hex_id: Option<String>,
+) -> Result<(), CommandError> {
+    state.signal(&db, &execution_id, agent_hex_id.as_deref(), SIGKILL)
+}
+
+/// Send a signal (e.g. "SIGINT", "TERM", "9") to a running shell execution's process group
+#[tauri::command]
+pub fn shell_signal(
+    state: State<'_, ShellState>,
+    db: State<'_, Database>,
+    execution_id: String,
+    signal: String,
+    agent_hex_id: Option<String>,
+) -> Result<(), CommandError> {
+    let signal_number = parse_signal(&signal)
+        .ok_or_else(|| CommandError::ShellFailed(format!("Unknown signal: {}", signal)))?;
+    state.signal(&db, &execution_id, agent_hex_id.as_deref(), signal_number)
+}
+
+/// List shell executions that are still running
+#[tauri::command]
+pub fn shell_list_running(state: State<'_, ShellState>) -> Vec<RunningShell> {
+    let processes = state.processes.lock().unwrap();
+    let mut running: Vec<RunningShell> = processes
+        .iter()
+        .map(|(id, p)| RunningShell {
+            execution_id: id.clone(),
+            pid: p.pid,
+            command: p.command.clone(),
+            cwd: p.cwd.clone(),
+            started_at: p.started_at.clone(),
+        })
+        .collect();
+    running.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    running
+}
+
+/// Windows has no signals; these only name them, and `signal_process_group`
+/// refuses the ones that don't terminate
+#[cfg(windows)]
+mod windows_signals {
+    pub const SIGHUP: i32 = 1;
+    pub const SIGINT: i32 = 2;
+    pub const SIGQUIT: i32 = 3;
+    pub const SIGKILL: i32 = 9;
+    pub const SIGUSR1: i32 = 10;
+    pub const SIGUSR2: i32 = 12;
+    pub const SIGTERM: i32 = 15;
+    pub const SIGCONT: i32 = 18;
+    pub const SIGSTOP: i32 = 19;
+}
+
+/// Parse a signal name or number into a signal number
+fn parse_signal(signal: &str) -> Option<i32> {
+    let name = signal.trim().to_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    match name {
+        "HUP" => Some(SIGHUP),
+        "INT" => Some(SIGINT),
+        "QUIT" => Some(SIGQUIT),
+        "KILL" => Some(SIGKILL),
+        "TERM" => Some(SIGTERM),
+        "CONT" => Some(SIGCONT),
+        "STOP" => Some(SIGSTOP),
+        "USR1" => Some(SIGUSR1),
+        "USR2" => Some(SIGUSR2),
+        _ => name.parse().ok().filter(|n| *n > 0),
+    }
+}
+
+/// Whether a signal ends the process by default (used to mark results as killed)
+fn is_terminating_signal(signal: i32) -> bool {
+    matches!(signal, SIGHUP | SIGINT | SIGQUIT | SIGKILL | SIGTERM)
+}
+
+/// Send a signal to every process in the group led by `pid`
+#[cfg(unix)]
+fn signal_process_group(pid: u32, signal: i32) -> Result<(), CommandError> {
+    // A negative pid addresses the whole process group
+    let result = unsafe { libc::kill(-(pid as i32), signal) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().into())
+    }
+}
+
+/// Windows has no process groups or signals; terminate the process tree instead
+#[cfg(windows)]
+fn signal_process_group(pid: u32, signal: i32) -> Result<(), CommandError> {
+    if !is_terminating_signal(signal) {
+        return Err(CommandError::ShellFailed(format!(
+            "Signal {} is not supported on Windows",
+            signal
+        )));
+    }
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(CommandError::ShellFailed(format!("taskkill failed for pid {}", pid)))
+    }
+}
+
+/// Authorize the calling agent on a filesystem hex and load the hex's sandbox
+fn filesystem_sandbox(
+    db: &Database,
+    filesystem_hex_id: &str,
+    agent_hex_id: Option<String>,
+    permission: Permission,
+) -> Result<SandboxRoot, CommandError> {
+    rbac::authorize(db, agent_hex_id.as_deref(), filesystem_hex_id, permission)?;
+    SandboxRoot::for_hex(db, filesystem_hex_id)
+}
+
 /// Read a file's contents
 #[tauri::command]
-pub async fn read_file(path: String) -> Result<String, CommandError> {
+pub async fn read_file(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+) -> Result<String, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path = sandbox.resolve(&path)?;
     let contents = tokio::fs::read_to_string(&path).await?;
     Ok(contents)
 }
 
-/// Write contents to a file
+/// Read a byte or line range of a file, or a binary file as base64
 #[tauri::command]
-pub async fn write_file(path: String, contents: String) -> Result<(), CommandError> {
-    // Create parent directories if they don't exist
-    if let Some(parent) = PathBuf::from(&path).parent() {
-        tokio::fs::create_dir_all(parent).await?;
-    }
-    tokio::fs::write(&path, contents).await?;
-    Ok(())
+pub async fn read_file_range(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+    range: Option<FileRange>,
+    mode: Option<ReadMode>,
+) -> Result<FileRangeContent, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path = sandbox.resolve(&path)?;
+    tokio::task::spawn_blocking(move || read::read_range(&path, range, mode.unwrap_or_default()))
+        .await
+        .map_err(|e| CommandError::Io(std::io::Error::other(e)))?
+}
+
+/// Write contents to a file atomically
+///
+/// With a precondition, the write fails with a conflict if the file changed
+/// since it was read.
+#[tauri::command]
+pub async fn write_file(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+    contents: String,
+    precondition: Option<WritePrecondition>,
+) -> Result<WriteResult, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let path = sandbox.resolve_write_target(&path)?;
+    let precondition = precondition.unwrap_or_default();
+    tokio::task::spawn_blocking(move || write::write_atomic(&path, contents.as_bytes(), &precondition))
+        .await
+        .map_err(|e| CommandError::Io(std::io::Error::other(e)))?
+}
+
+/// Apply a unified diff or search/replace blocks to a file
+///
+/// Nothing is written unless every hunk applies, or at least one does with
+/// `allow_partial`. Per-hunk results are returned either way.
+#[tauri::command]
+pub async fn apply_patch(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+    patch: String,
+    precondition: Option<WritePrecondition>,
+    allow_partial: Option<bool>,
+) -> Result<PatchResult, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let path = sandbox.resolve_write_target(&path)?;
+    let precondition = precondition.unwrap_or_default();
+    tokio::task::spawn_blocking(move || {
+        patch::patch_file(&path, &patch, &precondition, allow_partial.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| CommandError::Io(std::io::Error::other(e)))?
 }
 
 /// Directory entry information
@@ -148,7 +881,14 @@ pub struct DirEntry {
 
 /// List directory contents
 #[tauri::command]
-pub async fn list_directory(path: String) -> Result<Vec<DirEntry>, CommandError> {
+pub async fn list_directory(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+) -> Result<Vec<DirEntry>, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path = sandbox.resolve(&path)?;
     let mut entries = Vec::new();
     let mut dir = tokio::fs::read_dir(&path).await?;
 
@@ -166,31 +906,108 @@ pub async fn list_directory(path: String) -> Result<Vec<DirEntry>, CommandError>
     Ok(entries)
 }
 
+/// List a directory recursively, honouring .gitignore and glob filters
+#[tauri::command]
+pub async fn list_tree(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+    options: Option<TreeOptions>,
+) -> Result<TreeListing, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path = sandbox.resolve(&path)?;
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || tree::list_tree(&path, &options))
+        .await
+        .map_err(|e| CommandError::Io(std::io::Error::other(e)))?
+}
+
+/// Search file contents for a regex or literal string
+///
+/// Skips the same directories as the indexer, so results line up with
+/// `indexer_search`.
+#[tauri::command]
+pub async fn search_files(
+    db: State<'_, Database>,
+    indexer: State<'_, IndexerState>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+    query: String,
+    options: Option<SearchOptions>,
+) -> Result<SearchFilesResult, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path = sandbox.resolve(&path)?;
+    let options = options.unwrap_or_default();
+    let ignore_dirs = indexer.config.ignore_dirs.clone();
+    tokio::task::spawn_blocking(move || search::search_files(&path, &query, &options, &ignore_dirs))
+        .await
+        .map_err(|e| CommandError::Io(std::io::Error::other(e)))?
+}
+
 /// Check if a file or directory exists
 #[tauri::command]
-pub async fn file_exists(path: String) -> Result<bool, CommandError> {
+pub async fn file_exists(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+) -> Result<bool, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path = sandbox.resolve(&path)?;
     Ok(tokio::fs::try_exists(&path).await?)
 }
 
 /// Delete a file
 #[tauri::command]
-pub async fn delete_file(path: String) -> Result<(), CommandError> {
+pub async fn delete_file(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+) -> Result<(), CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let path = sandbox.resolve(&path)?;
     tokio::fs::remove_file(&path).await?;
     Ok(())
 }
 
 /// Delete a directory (recursive)
 #[tauri::command]
-pub async fn delete_directory(path: String) -> Result<(), CommandError> {
+pub async fn delete_directory(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+) -> Result<(), CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let path = sandbox.resolve(&path)?;
+    if path == sandbox.root() {
+        return Err(CommandError::PathError(
+            "Refusing to delete the filesystem root".to_string(),
+        ));
+    }
     tokio::fs::remove_dir_all(&path).await?;
     Ok(())
 }
 
 /// Copy a file
 #[tauri::command]
-pub async fn copy_file(source: String, destination: String) -> Result<u64, CommandError> {
+pub async fn copy_file(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    source: String,
+    destination: String,
+) -> Result<u64, CommandError> {
+    // Copying reads the source as well as writing the destination
+    rbac::authorize(&db, agent_hex_id.as_deref(), &filesystem_hex_id, Permission::Read)?;
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let source = sandbox.resolve(&source)?;
+    let destination = sandbox.resolve_write_target(&destination)?;
     // Create parent directories if they don't exist
-    if let Some(parent) = PathBuf::from(&destination).parent() {
+    if let Some(parent) = destination.parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
     let bytes_copied = tokio::fs::copy(&source, &destination).await?;
@@ -199,9 +1016,23 @@ pub async fn copy_file(source: String, destination: String) -> Result<u64, Comma
 
 /// Move/rename a file or directory
 #[tauri::command]
-pub async fn move_file(source: String, destination: String) -> Result<(), CommandError> {
+pub async fn move_file(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    source: String,
+    destination: String,
+) -> Result<(), CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let source = sandbox.resolve(&source)?;
+    let destination = sandbox.resolve_write_target(&destination)?;
+    if source == sandbox.root() {
+        return Err(CommandError::PathError(
+            "Refusing to move the filesystem root".to_string(),
+        ));
+    }
     // Create parent directories if they don't exist
-    if let Some(parent) = PathBuf::from(&destination).parent() {
+    if let Some(parent) = destination.parent() {
         tokio::fs::create_dir_all(parent).await?;
     }
     tokio::fs::rename(&source, &destination).await?;
@@ -210,7 +1041,14 @@ pub async fn move_file(source: String, destination: String) -> Result<(), Comman
 
 /// Create a directory (with parents)
 #[tauri::command]
-pub async fn create_directory(path: String) -> Result<(), CommandError> {
+pub async fn create_directory(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+) -> Result<(), CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Write)?;
+    let path = sandbox.resolve(&path)?;
     tokio::fs::create_dir_all(&path).await?;
     Ok(())
 }
@@ -224,16 +1062,28 @@ pub struct FileInfo {
     pub is_dir: bool,
     pub size: u64,
     pub modified: Option<u64>,
+    /// Modification time in milliseconds, for a write's `expected_mtime_ms`
+    pub modified_ms: Option<u64>,
     pub created: Option<u64>,
     pub readonly: bool,
+    /// SHA-256 of the contents, when requested with `include_hash`
+    pub hash: Option<String>,
 }
 
 /// Get file/directory metadata
 #[tauri::command]
-pub async fn get_file_info(path: String) -> Result<FileInfo, CommandError> {
-    let path_buf = PathBuf::from(&path);
+pub async fn get_file_info(
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+    path: String,
+    include_hash: Option<bool>,
+) -> Result<FileInfo, CommandError> {
+    let sandbox = filesystem_sandbox(&db, &filesystem_hex_id, agent_hex_id, Permission::Read)?;
+    let path_buf = sandbox.resolve(&path)?;
+    let path = path_buf.to_string_lossy().to_string();
 
-    if !tokio::fs::try_exists(&path).await? {
+    if !tokio::fs::try_exists(&path_buf).await? {
         return Ok(FileInfo {
             path,
             exists: false,
@@ -241,8 +1091,10 @@ pub async fn get_file_info(path: String) -> Result<FileInfo, CommandError> {
             is_dir: false,
             size: 0,
             modified: None,
+            modified_ms: None,
             created: None,
             readonly: false,
+            hash: None,
         });
     }
 
@@ -252,10 +1104,18 @@ pub async fn get_file_info(path: String) -> Result<FileInfo, CommandError> {
         t.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
     });
 
+    let modified_ms = write::modified_ms(&metadata);
+
     let created = metadata.created().ok().and_then(|t| {
         t.duration_since(std::time::UNIX_EPOCH).ok().map(|d| d.as_secs())
     });
 
+    let hash = if include_hash.unwrap_or(false) && metadata.is_file() {
+        Some(write::hash_bytes(&tokio::fs::read(&path_buf).await?))
+    } else {
+        None
+    };
+
     Ok(FileInfo {
         path,
         exists: true,
@@ -263,8 +1123,10 @@ pub async fn get_file_info(path: String) -> Result<FileInfo, CommandError> {
         is_dir: metadata.is_dir(),
         size: metadata.len(),
         modified,
+        modified_ms,
         created,
         readonly: metadata.permissions().readonly(),
+        hash,
     })
 }
 
@@ -296,3 +1158,120 @@ fn home_dir() -> Option<PathBuf> {
         .or_else(|| std::env::var_os("USERPROFILE"))
         .map(PathBuf::from)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(key: &str) -> Result<Option<String>, CommandError> {
+        Ok(match key {
+            "api.github_token" => Some("ghp_secret123".to_string()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn test_expand_setting_refs() {
+        let mut secrets = Vec::new();
+        let value = expand_setting_refs("token ${setting:api.github_token}!", &mut secrets, lookup).unwrap();
+
+        assert_eq!(value, "token ghp_secret123!");
+        assert_eq!(secrets, vec!["ghp_secret123".to_string()]);
+    }
+
+    #[test]
+    fn test_expand_setting_refs_missing_setting() {
+        let mut secrets = Vec::new();
+        let result = expand_setting_refs("${setting:missing}", &mut secrets, lookup);
+
+        assert!(matches!(result, Err(CommandError::Env(_))));
+    }
+
+    #[test]
+    fn test_expand_setting_refs_leaves_plain_values() {
+        let mut secrets = Vec::new();
+        let value = expand_setting_refs("$HOME/${setting:unterminated", &mut secrets, lookup).unwrap();
+
+        assert_eq!(value, "$HOME/${setting:unterminated");
+        assert!(secrets.is_empty());
+    }
+
+    #[test]
+    fn test_call_env_unsets_hex_variables() {
+        let hex_env = ShellEnv {
+            set: [("API_TOKEN".to_string(), "${setting:api.token}".to_string())].into(),
+            unset: vec!["AWS_PROFILE".to_string()],
+            ..Default::default()
+        };
+        let call_env = ShellEnv {
+            set: [("AWS_PROFILE".to_string(), "dev".to_string())].into(),
+            unset: vec!["API_TOKEN".to_string()],
+            ..Default::default()
+        };
+        let env = hex_env.merge(call_env);
+
+        assert!(!env.set.contains_key("API_TOKEN"));
+        assert_eq!(env.set.get("AWS_PROFILE").map(String::as_str), Some("dev"));
+        assert_eq!(env.unset, vec!["API_TOKEN".to_string()]);
+    }
+
+    #[test]
+    fn test_call_env_cannot_reference_settings() {
+        let call_env = ShellEnv {
+            set: [("K".to_string(), "${setting:indexer:http-api-key}".to_string())].into(),
+            ..Default::default()
+        };
+        assert!(matches!(call_env.reject_setting_refs(), Err(CommandError::Env(_))));
+
+        let call_env = ShellEnv {
+            set: [("AWS_PROFILE".to_string(), "dev".to_string())].into(),
+            ..Default::default()
+        };
+        assert!(call_env.reject_setting_refs().is_ok());
+    }
+
+    #[test]
+    fn test_format_line_masks_secrets_cut_by_the_limit() {
+        let limits = OutputLimits {
+            secrets: Arc::new(vec!["ghp_secret123".to_string()]),
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            max_line_length: 10,
+            remaining: Arc::new(AtomicUsize::new(DEFAULT_MAX_OUTPUT_BYTES)),
+            exhausted: Arc::new(AtomicBool::new(false)),
+        };
+
+        // The limit falls inside the secret
+        let (text, truncated) = format_line(b"token ghp_secret123", false, &limits);
+        assert_eq!(text, "token **** [line truncated]");
+        assert!(truncated);
+
+        let (text, truncated) = format_line(b"ok\r", false, &limits);
+        assert_eq!(text, "ok");
+        assert!(!truncated);
+    }
+
+    #[test]
+    fn test_mask_secrets() {
+        let secrets = vec!["ghp_secret123".to_string(), "abc".to_string()];
+        let masked = mask_secrets("auth ghp_secret123 for abc", &secrets);
+
+        assert_eq!(masked, "auth ******** for abc");
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("SIGINT"), Some(SIGINT));
+        assert_eq!(parse_signal("term"), Some(SIGTERM));
+        assert_eq!(parse_signal(" sigusr1 "), Some(SIGUSR1));
+        assert_eq!(parse_signal("STOP"), Some(SIGSTOP));
+        assert_eq!(parse_signal("9"), Some(SIGKILL));
+        assert_eq!(parse_signal("0"), None);
+        assert_eq!(parse_signal("-9"), None);
+        assert_eq!(parse_signal("SIGBOGUS"), None);
+
+        assert!(is_terminating_signal(SIGKILL));
+        assert!(is_terminating_signal(parse_signal("HUP").unwrap()));
+        assert!(!is_terminating_signal(SIGCONT));
+        assert!(!is_terminating_signal(SIGUSR2));
+    }
+}
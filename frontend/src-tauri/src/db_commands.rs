@@ -1,5 +1,10 @@
 use tauri::State;
 use crate::database::{Database, Board, CreateBoardRequest, HexEntity, CreateHexRequest, Connection_};
+use crate::indexer::http_embedder;
+
+/// Secrets the frontend can set but only reads back masked
+const WRITE_ONLY_SETTINGS: &[&str] = &[http_embedder::API_KEY_SETTING];
+const SECRET_MASK: &str = "********";
 
 /// Error type for database command operations
 #[derive(Debug, thiserror::Error)]
@@ -123,9 +128,18 @@ pub fn db_delete_connection(db: State<Database>, id: String) -> Result<bool, DbE
 
 // === Settings Commands ===
 
+/// The value of a setting as the frontend sees it
+fn masked(key: &str, value: String) -> String {
+    if WRITE_ONLY_SETTINGS.contains(&key) {
+        SECRET_MASK.to_string()
+    } else {
+        value
+    }
+}
+
 #[tauri::command]
 pub fn db_get_setting(db: State<Database>, key: String) -> Result<Option<String>, DbError> {
-    Ok(db.get_setting(&key)?)
+    Ok(db.get_setting(&key)?.map(|value| masked(&key, value)))
 }
 
 #[tauri::command]
@@ -140,5 +154,9 @@ pub fn db_delete_setting(db: State<Database>, key: String) -> Result<bool, DbErr
 
 #[tauri::command]
 pub fn db_list_settings(db: State<Database>, prefix: Option<String>) -> Result<Vec<(String, String)>, DbError> {
-    Ok(db.list_settings(prefix.as_deref())?)
+    let settings = db.list_settings(prefix.as_deref())?;
+    Ok(settings.into_iter().map(|(key, value)| {
+        let value = masked(&key, value);
+        (key, value)
+    }).collect())
 }
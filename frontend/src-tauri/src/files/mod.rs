@@ -0,0 +1,10 @@
+//! File operations behind the filesystem commands
+//!
+//! The Tauri commands live in `commands.rs`; this module holds the parts that
+//! are more than a single `tokio::fs` call.
+
+pub mod patch;
+pub mod read;
+pub mod search;
+pub mod tree;
+pub mod write;
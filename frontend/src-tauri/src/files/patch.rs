@@ -0,0 +1,530 @@
+//! Patch application for `apply_patch`
+//!
+//! Accepts either a unified diff for a single file or a series of
+//! search/replace blocks:
+//!
+//! ```text
+//! <<<<<<< SEARCH
+//! old text
+//! =======
+//! new text
+//! >>>>>>> REPLACE
+//! ```
+//!
+//! Each hunk or block is applied independently and reported on its own.
+
+use super::write::{hash_bytes, write_atomic, WritePrecondition};
+use crate::commands::CommandError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const SEARCH_MARKER: &str = "<<<<<<< SEARCH";
+const DIVIDER_MARKER: &str = "=======";
+const REPLACE_MARKER: &str = ">>>>>>> REPLACE";
+
+/// Furthest a unified diff hunk may land from the line its header names
+const MAX_HUNK_OFFSET: usize = 50;
+
+/// Outcome of a single hunk or search/replace block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HunkResult {
+    /// 1-based position of the hunk in the patch
+    pub hunk: usize,
+    pub applied: bool,
+    /// 1-based line where the hunk was applied
+    pub line: Option<usize>,
+    /// The hunk's context only matched after ignoring trailing whitespace
+    pub fuzzy: bool,
+    pub error: Option<String>,
+}
+
+/// Result of `apply_patch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchResult {
+    pub path: String,
+    /// Whether the patched contents were written
+    pub written: bool,
+    pub hunks: Vec<HunkResult>,
+    /// Hash of the new contents when written
+    pub hash: Option<String>,
+}
+
+/// Patched text and per-hunk results
+#[derive(Debug)]
+pub struct PatchOutcome {
+    pub content: String,
+    pub hunks: Vec<HunkResult>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Hunk {
+    Unified {
+        /// 1-based start line in the original file
+        old_start: usize,
+        old_lines: Vec<String>,
+        new_lines: Vec<String>,
+    },
+    SearchReplace {
+        search: String,
+        replace: String,
+    },
+}
+
+/// Apply a patch to text
+pub fn apply_patch(original: &str, patch: &str) -> Result<PatchOutcome, CommandError> {
+    let hunks = parse_patch(patch)?;
+    if hunks.is_empty() {
+        return Err(CommandError::InvalidRequest(
+            "Patch contains no hunks or search/replace blocks".to_string(),
+        ));
+    }
+
+    match hunks[0] {
+        Hunk::Unified { .. } => Ok(apply_unified(original, &hunks)),
+        Hunk::SearchReplace { .. } => Ok(apply_search_replace(original, &hunks)),
+    }
+}
+
+/// Apply a patch to a file, writing it only if every hunk applies (or some do, with `allow_partial`)
+pub fn patch_file(
+    path: &Path,
+    patch: &str,
+    precondition: &WritePrecondition,
+    allow_partial: bool,
+) -> Result<PatchResult, CommandError> {
+    precondition.check(path)?;
+    let original_bytes = std::fs::read(path)?;
+    let original = std::str::from_utf8(&original_bytes).map_err(|_| {
+        CommandError::InvalidRequest("Patches can only be applied to UTF-8 text files".to_string())
+    })?;
+
+    let outcome = apply_patch(original, patch)?;
+    let all_applied = outcome.hunks.iter().all(|h| h.applied);
+    let any_applied = outcome.hunks.iter().any(|h| h.applied);
+
+    if !(all_applied || (allow_partial && any_applied)) {
+        return Ok(PatchResult {
+            path: path.to_string_lossy().to_string(),
+            written: false,
+            hunks: outcome.hunks,
+            hash: None,
+        });
+    }
+
+    // Refuse to write if another writer changed the file since it was read
+    let unchanged = WritePrecondition {
+        expected_hash: Some(hash_bytes(&original_bytes)),
+        expected_mtime_ms: None,
+    };
+    let written = write_atomic(path, outcome.content.as_bytes(), &unchanged)?;
+
+    Ok(PatchResult {
+        path: written.path,
+        written: true,
+        hunks: outcome.hunks,
+        hash: Some(written.hash),
+    })
+}
+
+fn parse_patch(patch: &str) -> Result<Vec<Hunk>, CommandError> {
+    if patch.lines().any(|l| l.trim_end() == SEARCH_MARKER) {
+        parse_search_replace(patch)
+    } else {
+        parse_unified(patch)
+    }
+}
+
+fn parse_search_replace(patch: &str) -> Result<Vec<Hunk>, CommandError> {
+    enum State {
+        Outside,
+        Search(Vec<String>),
+        Replace(Vec<String>, Vec<String>),
+    }
+
+    let mut hunks = Vec::new();
+    let mut state = State::Outside;
+
+    for line in patch.lines() {
+        let marker = line.trim_end();
+        state = match state {
+            State::Outside if marker == SEARCH_MARKER => State::Search(Vec::new()),
+            State::Outside => State::Outside,
+            State::Search(search) if marker == DIVIDER_MARKER => State::Replace(search, Vec::new()),
+            State::Search(mut search) => {
+                search.push(line.to_string());
+                State::Search(search)
+            }
+            State::Replace(search, replace) if marker == REPLACE_MARKER => {
+                hunks.push(Hunk::SearchReplace {
+                    search: search.join("\n"),
+                    replace: replace.join("\n"),
+                });
+                State::Outside
+            }
+            State::Replace(search, mut replace) => {
+                replace.push(line.to_string());
+                State::Replace(search, replace)
+            }
+        };
+    }
+
+    if !matches!(state, State::Outside) {
+        return Err(CommandError::InvalidRequest(
+            "Unterminated search/replace block".to_string(),
+        ));
+    }
+
+    Ok(hunks)
+}
+
+/// Parse `@@ -start[,count] +start[,count] @@`
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize)> {
+    let mut parts = line.split_whitespace().skip(1);
+    let parse_range = |range: &str| -> Option<(usize, usize)> {
+        let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+        Some((start.parse().ok()?, count.parse().ok()?))
+    };
+
+    let (old_start, old_count) = parse_range(parts.next()?.strip_prefix('-')?)?;
+    let (_, new_count) = parse_range(parts.next()?.strip_prefix('+')?)?;
+    Some((old_start, old_count, new_count))
+}
+
+fn parse_unified(patch: &str) -> Result<Vec<Hunk>, CommandError> {
+    let mut hunks = Vec::new();
+    let mut files = 0;
+    let mut lines = patch.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if line.starts_with("+++ ") {
+            files += 1;
+            if files > 1 {
+                return Err(CommandError::InvalidRequest(
+                    "Patch changes more than one file".to_string(),
+                ));
+            }
+            continue;
+        }
+        if !line.starts_with("@@") {
+            continue;
+        }
+
+        let (old_start, mut old_remaining, mut new_remaining) = parse_hunk_header(line)
+            .ok_or_else(|| CommandError::InvalidRequest(format!("Invalid hunk header: {}", line)))?;
+        let mut old_lines = Vec::new();
+        let mut new_lines = Vec::new();
+
+        while old_remaining > 0 || new_remaining > 0 {
+            let Some(body) = lines.next() else {
+                return Err(CommandError::InvalidRequest(format!(
+                    "Hunk is shorter than its header: {}",
+                    line
+                )));
+            };
+            let body = body.strip_suffix('\r').unwrap_or(body);
+
+            if let Some(removed) = body.strip_prefix('-') {
+                old_lines.push(removed.to_string());
+                old_remaining = old_remaining.saturating_sub(1);
+            } else if let Some(added) = body.strip_prefix('+') {
+                new_lines.push(added.to_string());
+                new_remaining = new_remaining.saturating_sub(1);
+            } else if body.starts_with('\\') {
+                // "\ No newline at end of file"
+            } else {
+                // Context; some tools strip the leading space from blank lines
+                let context = body.strip_prefix(' ').unwrap_or(body);
+                old_lines.push(context.to_string());
+                new_lines.push(context.to_string());
+                old_remaining = old_remaining.saturating_sub(1);
+                new_remaining = new_remaining.saturating_sub(1);
+            }
+        }
+
+        // A trailing marker belongs to this hunk
+        if lines.peek().is_some_and(|l| l.starts_with('\\')) {
+            lines.next();
+        }
+
+        hunks.push(Hunk::Unified {
+            old_start,
+            old_lines,
+            new_lines,
+        });
+    }
+
+    Ok(hunks)
+}
+
+/// Find `block` in `lines` at or after `min_start` and within `MAX_HUNK_OFFSET`
+/// lines of `expected`, preferring positions close to `expected`
+///
+/// Exact matches win; otherwise lines are compared ignoring trailing
+/// whitespace, and the match is reported as fuzzy. Indentation always has to
+/// match. Returns (position, fuzzy).
+fn find_block(
+    lines: &[String],
+    block: &[String],
+    expected: usize,
+    min_start: usize,
+) -> Option<(usize, bool)> {
+    if block.is_empty() {
+        return Some((expected.max(min_start).min(lines.len()), false));
+    }
+    if block.len() > lines.len() {
+        return None;
+    }
+
+    let first = min_start.max(expected.saturating_sub(MAX_HUNK_OFFSET));
+    let last = (lines.len() - block.len()).min(expected.saturating_add(MAX_HUNK_OFFSET));
+    if first > last {
+        return None;
+    }
+    let mut candidates: Vec<usize> = (first..=last).collect();
+    candidates.sort_by_key(|pos| pos.abs_diff(expected));
+
+    let matches_at = |pos: usize, loose: bool| {
+        block.iter().zip(&lines[pos..]).all(|(want, have)| {
+            if loose {
+                want.trim_end() == have.trim_end()
+            } else {
+                want == have
+            }
+        })
+    };
+
+    let exact = candidates.iter().copied().find(|pos| matches_at(*pos, false));
+    match exact {
+        Some(pos) => Some((pos, false)),
+        None => candidates
+            .iter()
+            .copied()
+            .find(|pos| matches_at(*pos, true))
+            .map(|pos| (pos, true)),
+    }
+}
+
+fn apply_unified(original: &str, hunks: &[Hunk]) -> PatchOutcome {
+    let newline = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = original.lines().map(String::from).collect();
+    let mut results = Vec::new();
+    let mut delta: isize = 0;
+    let mut min_start = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let Hunk::Unified {
+            old_start,
+            old_lines,
+            new_lines,
+        } = hunk
+        else {
+            continue;
+        };
+
+        // An empty old range inserts after `old_start` rather than at it
+        let start = if old_lines.is_empty() {
+            *old_start
+        } else {
+            old_start.saturating_sub(1)
+        };
+        let expected = (start as isize + delta).max(0) as usize;
+
+        match find_block(&lines, old_lines, expected, min_start) {
+            Some((pos, fuzzy)) => {
+                lines.splice(pos..pos + old_lines.len(), new_lines.iter().cloned());
+                delta += new_lines.len() as isize - old_lines.len() as isize;
+                min_start = pos + new_lines.len();
+                results.push(HunkResult {
+                    hunk: index + 1,
+                    applied: true,
+                    line: Some(pos + 1),
+                    fuzzy,
+                    error: None,
+                });
+            }
+            None => results.push(HunkResult {
+                hunk: index + 1,
+                applied: false,
+                line: None,
+                fuzzy: false,
+                error: Some(format!(
+                    "Context not found within {} lines of line {}",
+                    MAX_HUNK_OFFSET, old_start
+                )),
+            }),
+        }
+    }
+
+    let mut content = lines.join(newline);
+    if !lines.is_empty() && (original.ends_with('\n') || original.is_empty()) {
+        content.push_str(newline);
+    }
+
+    PatchOutcome {
+        content,
+        hunks: results,
+    }
+}
+
+fn apply_search_replace(original: &str, hunks: &[Hunk]) -> PatchOutcome {
+    let crlf = original.contains("\r\n");
+    let mut content = original.to_string();
+    let mut results = Vec::new();
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let Hunk::SearchReplace { search, replace } = hunk else {
+            continue;
+        };
+        let (search, replace) = if crlf {
+            (search.replace('\n', "\r\n"), replace.replace('\n', "\r\n"))
+        } else {
+            (search.clone(), replace.clone())
+        };
+
+        let failed = |error: String| HunkResult {
+            hunk: index + 1,
+            applied: false,
+            line: None,
+            fuzzy: false,
+            error: Some(error),
+        };
+
+        if search.is_empty() {
+            results.push(failed("Search text is empty".to_string()));
+            continue;
+        }
+
+        match content.matches(search.as_str()).count() {
+            0 => results.push(failed("Search text not found".to_string())),
+            1 => {
+                let pos = content.find(search.as_str()).unwrap_or_default();
+                let line = content[..pos].matches('\n').count() + 1;
+                content.replace_range(pos..pos + search.len(), &replace);
+                results.push(HunkResult {
+                    hunk: index + 1,
+                    applied: true,
+                    line: Some(line),
+                    fuzzy: false,
+                    error: None,
+                });
+            }
+            n => results.push(failed(format!(
+                "Search text matches {} places; include more context",
+                n
+            ))),
+        }
+    }
+
+    PatchOutcome {
+        content,
+        hunks: results,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n\nfn helper() {\n    todo!()\n}\n";
+
+    #[test]
+    fn test_apply_unified_diff() {
+        let patch = "--- a/main.rs\n+++ b/main.rs\n@@ -1,3 +1,3 @@\n fn main() {\n-    let x = 1;\n+    let x = 2;\n     println!(\"{}\", x);\n@@ -6,3 +6,4 @@\n fn helper() {\n-    todo!()\n+    // TODO\n+    unimplemented!()\n }\n";
+        let outcome = apply_patch(ORIGINAL, patch).unwrap();
+
+        assert!(outcome.hunks.iter().all(|h| h.applied));
+        assert_eq!(outcome.hunks[1].line, Some(6));
+        assert_eq!(
+            outcome.content,
+            "fn main() {\n    let x = 2;\n    println!(\"{}\", x);\n}\n\nfn helper() {\n    // TODO\n    unimplemented!()\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_tolerates_shifted_lines() {
+        let shifted = format!("// header\n// comment\n{}", ORIGINAL);
+        let patch = "@@ -2,1 +2,1 @@\n-    let x = 1;\n+    let x = 3;\n";
+        let outcome = apply_patch(&shifted, patch).unwrap();
+
+        assert_eq!(outcome.hunks[0].line, Some(4));
+        assert!(outcome.content.contains("let x = 3;"));
+    }
+
+    #[test]
+    fn test_unified_diff_reports_failed_hunks() {
+        let patch = "@@ -2,1 +2,1 @@\n-    let y = 1;\n+    let y = 2;\n@@ -7,1 +7,1 @@\n-    todo!()\n+    panic!()\n";
+        let outcome = apply_patch(ORIGINAL, patch).unwrap();
+
+        assert!(!outcome.hunks[0].applied);
+        assert!(outcome.hunks[0].error.is_some());
+        assert!(outcome.hunks[1].applied);
+        assert!(outcome.content.contains("panic!()"));
+    }
+
+    #[test]
+    fn test_unified_diff_context_must_be_near_and_indented_alike() {
+        // Only trailing whitespace may differ, and the match says so
+        let patch = "@@ -2,1 +2,1 @@\n-    let x = 1;   \n+    let x = 3;\n";
+        let outcome = apply_patch(ORIGINAL, patch).unwrap();
+        assert!(outcome.hunks[0].applied);
+        assert!(outcome.hunks[0].fuzzy);
+
+        // Context at another indentation doesn't match
+        let patch = "@@ -2,1 +2,1 @@\n-let x = 1;\n+let x = 3;\n";
+        let outcome = apply_patch(ORIGINAL, patch).unwrap();
+        assert!(!outcome.hunks[0].applied);
+        assert_eq!(outcome.content, ORIGINAL);
+
+        // Nor does context far from the line in the header
+        let padded = format!("{}{}", "\n".repeat(MAX_HUNK_OFFSET + 1), ORIGINAL);
+        let patch = "@@ -2,1 +2,1 @@\n-    let x = 1;\n+    let x = 3;\n";
+        let outcome = apply_patch(&padded, patch).unwrap();
+        assert!(!outcome.hunks[0].applied);
+        assert!(outcome.hunks[0].error.is_some());
+    }
+
+    #[test]
+    fn test_apply_search_replace_blocks() {
+        let patch = "<<<<<<< SEARCH\n    todo!()\n=======\n    42\n>>>>>>> REPLACE\n<<<<<<< SEARCH\n    let\n=======\n    const\n>>>>>>> REPLACE\n<<<<<<< SEARCH\nmissing\n=======\nx\n>>>>>>> REPLACE\n";
+        let outcome = apply_patch(ORIGINAL, patch).unwrap();
+
+        assert!(outcome.hunks[0].applied);
+        assert_eq!(outcome.hunks[0].line, Some(7));
+        assert!(outcome.content.contains("    42\n"));
+        // "    let" only occurs once
+        assert!(outcome.hunks[1].applied);
+        assert!(!outcome.hunks[2].applied);
+    }
+
+    #[test]
+    fn test_search_replace_rejects_ambiguous_match() {
+        let patch = "<<<<<<< SEARCH\nfn \n=======\npub fn \n>>>>>>> REPLACE\n";
+        let outcome = apply_patch(ORIGINAL, patch).unwrap();
+
+        assert!(!outcome.hunks[0].applied);
+        assert_eq!(outcome.content, ORIGINAL);
+    }
+
+    #[test]
+    fn test_preserves_crlf_line_endings() {
+        let original = "a\r\nb\r\nc\r\n";
+        let outcome = apply_patch(original, "@@ -2 +2 @@\n-b\n+B\n").unwrap();
+        assert_eq!(outcome.content, "a\r\nB\r\nc\r\n");
+
+        let outcome = apply_patch(original, "<<<<<<< SEARCH\nb\nc\n=======\nc\n>>>>>>> REPLACE\n").unwrap();
+        assert_eq!(outcome.content, "a\r\nc\r\n");
+    }
+
+    #[test]
+    fn test_rejects_invalid_patches() {
+        assert!(matches!(apply_patch(ORIGINAL, "just text"), Err(CommandError::InvalidRequest(_))));
+        assert!(matches!(
+            apply_patch(ORIGINAL, "<<<<<<< SEARCH\nfoo\n=======\n"),
+            Err(CommandError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            apply_patch(ORIGINAL, "+++ b/a.rs\n@@ -1 +1 @@\n-a\n+b\n+++ b/b.rs\n@@ -1 +1 @@\n-a\n+b\n"),
+            Err(CommandError::InvalidRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,577 @@
+//! Ranged and binary-safe file reads
+//!
+//! Reads a byte or line range of a file, detects its encoding and returns the
+//! content either as decoded text or as base64 of the raw bytes.
+
+use crate::commands::CommandError;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Bytes inspected when detecting a file's encoding
+const SAMPLE_BYTES: usize = 8 * 1024;
+
+/// Maximum bytes returned by a single read
+const MAX_READ_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Text encoding detected from the start of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileEncoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin-1")]
+    Latin1,
+    #[serde(rename = "binary")]
+    Binary,
+}
+
+/// How content is returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadMode {
+    /// Decoded text; binary files fall back to base64
+    #[default]
+    Text,
+    /// Base64 of the raw bytes
+    Base64,
+}
+
+/// Part of a file to read
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileRange {
+    /// `length` bytes starting at byte `offset` (to the end if no length)
+    Bytes { offset: u64, length: Option<u64> },
+    /// Lines `start` through `end`, 1-based and inclusive (to the end if no end)
+    Lines { start: usize, end: Option<usize> },
+}
+
+/// Content returned by `read_file_range`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRangeContent {
+    pub path: String,
+    pub encoding: FileEncoding,
+    /// How `content` is encoded
+    pub mode: ReadMode,
+    pub content: String,
+    /// Byte offset of the first returned byte
+    pub offset: u64,
+    /// Number of bytes returned
+    pub length: u64,
+    /// First line returned, for line ranges
+    pub start_line: Option<usize>,
+    /// Last line returned, for line ranges
+    pub end_line: Option<usize>,
+    pub total_bytes: u64,
+    /// Whether the read reached the end of the file
+    pub eof: bool,
+    /// Whether the size limit cut the requested range short
+    pub truncated: bool,
+}
+
+/// Detect the encoding of a file from a sample of its first bytes
+pub fn detect_encoding(sample: &[u8]) -> FileEncoding {
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return FileEncoding::Utf8;
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return FileEncoding::Utf16Le;
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return FileEncoding::Utf16Be;
+    }
+    if let Some(encoding) = detect_utf16_without_bom(sample) {
+        return encoding;
+    }
+    if sample.contains(&0) {
+        return FileEncoding::Binary;
+    }
+
+    match std::str::from_utf8(sample) {
+        Ok(_) => return FileEncoding::Utf8,
+        // The sample may end in the middle of a character
+        Err(e) if e.error_len().is_none() => return FileEncoding::Utf8,
+        Err(_) => {}
+    }
+
+    let control_bytes = sample.iter().filter(|b| is_binary_control(**b)).count();
+    if control_bytes * 10 > sample.len() {
+        FileEncoding::Binary
+    } else {
+        FileEncoding::Latin1
+    }
+}
+
+/// Mostly-ASCII UTF-16 has a zero in every other byte
+fn detect_utf16_without_bom(sample: &[u8]) -> Option<FileEncoding> {
+    let units = sample.len() / 2;
+    if units < 2 {
+        return None;
+    }
+
+    let zeros_at = |start: usize| {
+        sample
+            .iter()
+            .skip(start)
+            .step_by(2)
+            .take(units)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let even_zeros = zeros_at(0);
+    let odd_zeros = zeros_at(1);
+
+    if odd_zeros * 10 >= units * 9 && even_zeros * 10 < units {
+        Some(FileEncoding::Utf16Le)
+    } else if even_zeros * 10 >= units * 9 && odd_zeros * 10 < units {
+        Some(FileEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+fn is_binary_control(byte: u8) -> bool {
+    (byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0C)) || byte == 0x7F
+}
+
+/// Length of the byte order mark at the start of `bytes`, if any
+fn bom_len(bytes: &[u8], encoding: FileEncoding) -> usize {
+    match encoding {
+        FileEncoding::Utf8 if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) => 3,
+        FileEncoding::Utf16Le if bytes.starts_with(&[0xFF, 0xFE]) => 2,
+        FileEncoding::Utf16Be if bytes.starts_with(&[0xFE, 0xFF]) => 2,
+        _ => 0,
+    }
+}
+
+/// Decode bytes as text; `None` for binary content
+pub fn decode(bytes: &[u8], encoding: FileEncoding) -> Option<String> {
+    match encoding {
+        FileEncoding::Utf8 => Some(String::from_utf8_lossy(bytes).into_owned()),
+        FileEncoding::Utf16Le | FileEncoding::Utf16Be => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| match encoding {
+                    FileEncoding::Utf16Le => u16::from_le_bytes([pair[0], pair[1]]),
+                    _ => u16::from_be_bytes([pair[0], pair[1]]),
+                })
+                .collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        FileEncoding::Latin1 => Some(bytes.iter().map(|b| *b as char).collect()),
+        FileEncoding::Binary => None,
+    }
+}
+
+/// Lines selected from a file
+struct LineSlice {
+    bytes: Vec<u8>,
+    offset: u64,
+    first: Option<usize>,
+    last: Option<usize>,
+    /// Lines in the range were left unread because of the size limit
+    truncated: bool,
+}
+
+/// Collects lines `start..=end` as they are read
+struct LineCollector {
+    start: usize,
+    end: Option<usize>,
+    line_no: usize,
+    position: u64,
+    slice: LineSlice,
+}
+
+impl LineCollector {
+    fn new(start: usize, end: Option<usize>) -> Self {
+        Self {
+            start,
+            end,
+            line_no: 0,
+            position: 0,
+            slice: LineSlice {
+                bytes: Vec::new(),
+                offset: 0,
+                first: None,
+                last: None,
+                truncated: false,
+            },
+        }
+    }
+
+    /// Count a line before the range without keeping it
+    fn skip(&mut self, len: u64) {
+        self.line_no += 1;
+        self.position += len;
+    }
+
+    /// Add the next line (with its terminator), of which `line` may hold only
+    /// the first bytes of `len`; returns false once the range is complete
+    fn push(&mut self, line: &[u8], len: u64) -> bool {
+        self.line_no += 1;
+        if self.line_no >= self.start {
+            if self.slice.first.is_none() {
+                self.slice.first = Some(self.line_no);
+                self.slice.offset = self.position;
+            }
+            self.slice.bytes.extend_from_slice(line);
+            self.slice.last = Some(self.line_no);
+        }
+        self.position += len;
+
+        if (line.len() as u64) < len {
+            // The rest of the line didn't fit in the limit
+            self.slice.truncated = true;
+            return false;
+        }
+        let reached_end = self.end.is_some_and(|end| self.line_no >= end);
+        if reached_end {
+            return false;
+        }
+        self.slice.truncated = self.slice.bytes.len() as u64 >= MAX_READ_BYTES;
+        !self.slice.truncated
+    }
+}
+
+/// Read the next line, made of `newline.len()`-byte code units and ending in
+/// `newline`, appending up to `max_len` bytes of it to `line` if given. The
+/// rest of a longer line is read past without buffering. Returns the length
+/// of the whole line in bytes, 0 at the end of the file.
+fn next_line<R: BufRead>(
+    reader: &mut R,
+    newline: &[u8],
+    mut line: Option<&mut Vec<u8>>,
+    max_len: usize,
+) -> std::io::Result<u64> {
+    let mut len = 0;
+    let mut unit = [0u8; 2];
+    let mut unit_len = 0;
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(len);
+        }
+
+        let mut used = 0;
+        let mut found = false;
+        for &byte in buf {
+            used += 1;
+            unit[unit_len] = byte;
+            unit_len += 1;
+            if unit_len == newline.len() {
+                unit_len = 0;
+                if unit[..newline.len()] == *newline {
+                    found = true;
+                    break;
+                }
+            }
+        }
+        if let Some(line) = line.as_deref_mut() {
+            let keep = used.min(max_len.saturating_sub(line.len()));
+            line.extend_from_slice(&buf[..keep]);
+        }
+        reader.consume(used);
+        len += used as u64;
+        if found {
+            return Ok(len);
+        }
+    }
+}
+
+/// Read lines `start..=end` from the start of `file`
+fn read_lines(
+    file: File,
+    encoding: FileEncoding,
+    start: usize,
+    end: Option<usize>,
+) -> std::io::Result<LineSlice> {
+    let newline: &[u8] = match encoding {
+        FileEncoding::Utf16Le => &[b'\n', 0],
+        FileEncoding::Utf16Be => &[0, b'\n'],
+        _ => b"\n",
+    };
+    let mut reader = BufReader::new(file);
+    let mut collector = LineCollector::new(start, end);
+
+    // Lines before the range are only counted
+    while collector.line_no + 1 < start {
+        match next_line(&mut reader, newline, None, 0)? {
+            0 => return Ok(collector.slice),
+            len => collector.skip(len),
+        }
+    }
+
+    let mut line = Vec::new();
+    let mut cut = false;
+    loop {
+        line.clear();
+        let budget = (MAX_READ_BYTES as usize).saturating_sub(collector.slice.bytes.len());
+        let len = next_line(&mut reader, newline, Some(&mut line), budget)?;
+        if len == 0 {
+            break;
+        }
+        cut = (line.len() as u64) < len;
+        if !collector.push(&line, len) {
+            break;
+        }
+    }
+    // Stopping at the limit between lines only truncates the range if more lines follow
+    if collector.slice.truncated && !cut && reader.fill_buf()?.is_empty() {
+        collector.slice.truncated = false;
+    }
+
+    Ok(collector.slice)
+}
+
+/// Read a range of a file
+pub fn read_range(
+    path: &Path,
+    range: Option<FileRange>,
+    mode: ReadMode,
+) -> Result<FileRangeContent, CommandError> {
+    let mut file = File::open(path)?;
+    let total_bytes = file.metadata()?.len();
+
+    let mut sample = Vec::with_capacity(SAMPLE_BYTES);
+    (&mut file).take(SAMPLE_BYTES as u64).read_to_end(&mut sample)?;
+    let encoding = detect_encoding(&sample);
+    file.seek(SeekFrom::Start(0))?;
+
+    let range = range.unwrap_or(FileRange::Bytes {
+        offset: 0,
+        length: None,
+    });
+
+    let (bytes, offset, start_line, end_line, truncated) = match range {
+        FileRange::Bytes { offset, length } => {
+            let requested = length.unwrap_or(u64::MAX);
+            file.seek(SeekFrom::Start(offset))?;
+            let mut bytes = Vec::new();
+            file.take(requested.min(MAX_READ_BYTES)).read_to_end(&mut bytes)?;
+            let requested_end = offset.saturating_add(requested).min(total_bytes);
+            let truncated = offset + (bytes.len() as u64) < requested_end;
+            (bytes, offset, None, None, truncated)
+        }
+        FileRange::Lines { start, end } => {
+            if encoding == FileEncoding::Binary {
+                return Err(CommandError::InvalidRequest(
+                    "Line ranges are not supported for binary files".to_string(),
+                ));
+            }
+            if start == 0 || end.is_some_and(|end| end < start) {
+                return Err(CommandError::InvalidRequest(format!(
+                    "Invalid line range: {}..{}",
+                    start,
+                    end.map(|e| e.to_string()).unwrap_or_default()
+                )));
+            }
+            let lines = read_lines(file, encoding, start, end)?;
+            let offset = if lines.first.is_some() { lines.offset } else { total_bytes };
+            (lines.bytes, offset, lines.first, lines.last, lines.truncated)
+        }
+    };
+
+    let text = match mode {
+        ReadMode::Text => {
+            // Drop the byte order mark when reading from the start
+            let skip = if offset == 0 { bom_len(&bytes, encoding) } else { 0 };
+            decode(&bytes[skip..], encoding)
+        }
+        ReadMode::Base64 => None,
+    };
+    let (mode, content) = match text {
+        Some(text) => (ReadMode::Text, text),
+        None => (
+            ReadMode::Base64,
+            base64::engine::general_purpose::STANDARD.encode(&bytes),
+        ),
+    };
+
+    let length = bytes.len() as u64;
+    Ok(FileRangeContent {
+        path: path.to_string_lossy().to_string(),
+        encoding,
+        mode,
+        content,
+        offset,
+        length,
+        start_line,
+        end_line,
+        total_bytes,
+        eof: offset + length >= total_bytes,
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn temp_file(contents: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(contents).unwrap();
+        file
+    }
+
+    fn lines(start: usize, end: Option<usize>) -> Option<FileRange> {
+        Some(FileRange::Lines { start, end })
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        assert_eq!(detect_encoding(b"fn main() {}\n"), FileEncoding::Utf8);
+        assert_eq!(detect_encoding("héllo wörld".as_bytes()), FileEncoding::Utf8);
+        assert_eq!(detect_encoding(b"h\xe9llo w\xf6rld"), FileEncoding::Latin1);
+        assert_eq!(detect_encoding(b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR"), FileEncoding::Binary);
+        assert_eq!(detect_encoding(b"\xff\xfeh\x00i\x00"), FileEncoding::Utf16Le);
+        assert_eq!(detect_encoding(b"\x00h\x00e\x00l\x00l\x00o"), FileEncoding::Utf16Be);
+        // Cut off in the middle of a multi-byte character
+        assert_eq!(detect_encoding(&"héllo".as_bytes()[..2]), FileEncoding::Utf8);
+    }
+
+    #[test]
+    fn test_read_line_range() {
+        let file = temp_file(b"one\ntwo\nthree\nfour\nfive");
+        let path = file.path();
+
+        let result = read_range(path, lines(2, Some(4)), ReadMode::Text).unwrap();
+        assert_eq!(result.content, "two\nthree\nfour\n");
+        assert_eq!(result.offset, 4);
+        assert_eq!((result.start_line, result.end_line), (Some(2), Some(4)));
+        assert!(!result.eof);
+
+        let result = read_range(path, lines(4, None), ReadMode::Text).unwrap();
+        assert_eq!(result.content, "four\nfive");
+        assert_eq!(result.end_line, Some(5));
+        assert!(result.eof);
+
+        let result = read_range(path, lines(10, Some(12)), ReadMode::Text).unwrap();
+        assert!(result.content.is_empty());
+        assert_eq!(result.start_line, None);
+
+        assert!(matches!(
+            read_range(path, lines(3, Some(2)), ReadMode::Text),
+            Err(CommandError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_byte_range() {
+        let file = temp_file(b"0123456789");
+        let path = file.path();
+
+        let range = Some(FileRange::Bytes { offset: 3, length: Some(4) });
+        let result = read_range(path, range, ReadMode::Text).unwrap();
+        assert_eq!(result.content, "3456");
+        assert_eq!((result.offset, result.length, result.total_bytes), (3, 4, 10));
+        assert!(!result.eof);
+
+        let range = Some(FileRange::Bytes { offset: 8, length: None });
+        let result = read_range(path, range, ReadMode::Base64).unwrap();
+        assert_eq!(result.mode, ReadMode::Base64);
+        assert_eq!(result.content, "ODk=");
+        assert!(result.eof);
+    }
+
+    #[test]
+    fn test_binary_falls_back_to_base64() {
+        let file = temp_file(&[0x00, 0x01, 0x02, 0xFF]);
+        let path = file.path();
+
+        let result = read_range(path, None, ReadMode::Text).unwrap();
+        assert_eq!(result.encoding, FileEncoding::Binary);
+        assert_eq!(result.mode, ReadMode::Base64);
+        assert_eq!(result.content, "AAEC/w==");
+
+        assert!(matches!(
+            read_range(path, lines(1, None), ReadMode::Text),
+            Err(CommandError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_utf16_lines() {
+        let mut contents = vec![0xFF, 0xFE];
+        for unit in "alpha\nbeta\ngamma".encode_utf16() {
+            contents.extend_from_slice(&unit.to_le_bytes());
+        }
+        let file = temp_file(&contents);
+        let path = file.path();
+
+        let result = read_range(path, lines(1, Some(2)), ReadMode::Text).unwrap();
+        assert_eq!(result.encoding, FileEncoding::Utf16Le);
+        assert_eq!(result.content, "alpha\nbeta\n");
+
+        let result = read_range(path, lines(3, None), ReadMode::Text).unwrap();
+        assert_eq!(result.content, "gamma");
+    }
+
+    #[test]
+    fn test_read_lines_past_the_limit() {
+        // 1 KiB per line, so the file is just over the read limit
+        let line_count = (MAX_READ_BYTES / 1024) as usize + 4;
+        let mut contents = vec![0xFF, 0xFE];
+        for i in 1..=line_count {
+            let line = format!("{:<511}\n", i);
+            for unit in line.encode_utf16() {
+                contents.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+        let file = temp_file(&contents);
+        let path = file.path();
+
+        let range = lines(line_count - 1, Some(line_count));
+        let result = read_range(path, range, ReadMode::Text).unwrap();
+        let numbers: Vec<_> = result.content.split_whitespace().collect();
+        assert_eq!(numbers, [(line_count - 1).to_string(), line_count.to_string()]);
+        assert_eq!(result.offset, 2 + (line_count as u64 - 2) * 1024);
+        assert!(result.eof);
+        assert!(!result.truncated);
+
+        let result = read_range(path, lines(1, None), ReadMode::Text).unwrap();
+        // The last line is cut off at the limit
+        assert_eq!(result.length, MAX_READ_BYTES);
+        assert_eq!(result.end_line, Some(line_count - 4));
+        assert!(!result.eof);
+        assert!(result.truncated);
+
+        let range = Some(FileRange::Bytes { offset: 0, length: None });
+        assert!(read_range(path, range, ReadMode::Base64).unwrap().truncated);
+        let range = Some(FileRange::Bytes { offset: 2, length: Some(1024) });
+        assert!(!read_range(path, range, ReadMode::Base64).unwrap().truncated);
+    }
+
+    #[test]
+    fn test_read_line_longer_than_the_limit() {
+        let mut contents = vec![b'a'; MAX_READ_BYTES as usize + 10];
+        contents.extend_from_slice(b"\nnext\n");
+        let file = temp_file(&contents);
+        let path = file.path();
+
+        let result = read_range(path, lines(1, Some(2)), ReadMode::Text).unwrap();
+        assert_eq!(result.length, MAX_READ_BYTES);
+        assert_eq!((result.start_line, result.end_line), (Some(1), Some(1)));
+        assert!(result.truncated);
+        assert!(!result.eof);
+
+        // Lines after it are still found
+        let result = read_range(path, lines(2, None), ReadMode::Text).unwrap();
+        assert_eq!(result.content, "next\n");
+        assert_eq!(result.offset, MAX_READ_BYTES + 11);
+        assert!(!result.truncated);
+
+        // A single line making up the whole file is still cut short
+        let file = temp_file(&contents[..MAX_READ_BYTES as usize + 10]);
+        let result = read_range(file.path(), lines(1, None), ReadMode::Text).unwrap();
+        assert_eq!(result.length, MAX_READ_BYTES);
+        assert!(result.truncated);
+    }
+}
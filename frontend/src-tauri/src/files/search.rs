@@ -0,0 +1,287 @@
+//! Content search for `search_files`
+//!
+//! Exact-match counterpart to the semantic `indexer_search`: finds a regex or
+//! literal across the files under a directory, skipping the same directories
+//! as the indexer, and returns each matching line with surrounding context.
+
+use super::read::{decode, detect_encoding, FileEncoding};
+use super::tree::glob_overrides;
+use crate::commands::CommandError;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Matches returned when no limit is given
+const DEFAULT_MAX_RESULTS: usize = 200;
+/// Files larger than this are skipped
+const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+/// Bytes inspected to tell text from binary
+const SAMPLE_BYTES: usize = 8 * 1024;
+
+/// Options for `search_files`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression instead of a literal string
+    pub regex: bool,
+    pub case_sensitive: bool,
+    /// Only match whole words
+    pub whole_word: bool,
+    /// Lines of context before and after each match
+    pub context_lines: usize,
+    /// Only search files matching one of these globs
+    pub include: Vec<String>,
+    /// Skip files and directories matching any of these globs
+    pub exclude: Vec<String>,
+    /// Maximum number of matching lines to return
+    pub max_results: Option<usize>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            regex: false,
+            case_sensitive: true,
+            whole_word: false,
+            context_lines: 2,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_results: None,
+        }
+    }
+}
+
+/// A matching line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub path: String,
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column (in characters) of the first match on the line
+    pub column: usize,
+    pub text: String,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+/// Result of `search_files`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchFilesResult {
+    pub matches: Vec<SearchMatch>,
+    pub files_searched: usize,
+    /// The result cap was hit; more matches exist
+    pub truncated: bool,
+}
+
+/// Search the files under `root` for `query`, skipping `ignore_dirs`
+pub fn search_files(
+    root: &Path,
+    query: &str,
+    options: &SearchOptions,
+    ignore_dirs: &[String],
+) -> Result<SearchFilesResult, CommandError> {
+    if query.is_empty() {
+        return Err(CommandError::InvalidRequest("Search query is empty".to_string()));
+    }
+    let matcher = build_matcher(query, options)?;
+    let max_results = options.max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let ignore_dirs = ignore_dirs.to_vec();
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .standard_filters(false)
+        .follow_links(false)
+        .overrides(glob_overrides(root, &options.include, &options.exclude)?)
+        .filter_entry(move |e| {
+            let name = e.file_name().to_string_lossy();
+            !ignore_dirs.iter().any(|d| name == *d)
+        })
+        .sort_by_file_name(|a, b| a.cmp(b));
+
+    let mut result = SearchFilesResult {
+        matches: Vec::new(),
+        files_searched: 0,
+        truncated: false,
+    };
+
+    for entry in builder.build().flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if !entry.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES) {
+            continue;
+        }
+        let Some(text) = read_text(entry.path()) else {
+            continue;
+        };
+
+        result.files_searched += 1;
+        let path = entry.path().to_string_lossy().to_string();
+        if search_text(&text, &path, &matcher, options.context_lines, max_results, &mut result.matches) {
+            result.truncated = true;
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+fn build_matcher(query: &str, options: &SearchOptions) -> Result<Regex, CommandError> {
+    let mut pattern = if options.regex {
+        query.to_string()
+    } else {
+        regex::escape(query)
+    };
+    if options.whole_word {
+        pattern = format!(r"\b(?:{})\b", pattern);
+    }
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| CommandError::InvalidRequest(format!("Invalid regex: {}", e)))
+}
+
+/// Read a file as text; `None` for binary or unreadable files
+fn read_text(path: &Path) -> Option<String> {
+    let bytes = std::fs::read(path).ok()?;
+    let encoding = detect_encoding(&bytes[..bytes.len().min(SAMPLE_BYTES)]);
+    if encoding == FileEncoding::Binary {
+        return None;
+    }
+    let text = decode(&bytes, encoding)?;
+    Some(text.strip_prefix('\u{feff}').map(str::to_string).unwrap_or(text))
+}
+
+/// Collect matching lines; returns true once `max_results` is exceeded
+fn search_text(
+    text: &str,
+    path: &str,
+    matcher: &Regex,
+    context_lines: usize,
+    max_results: usize,
+    matches: &mut Vec<SearchMatch>,
+) -> bool {
+    let lines: Vec<&str> = text.lines().collect();
+
+    for (index, line) in lines.iter().enumerate() {
+        let Some(found) = matcher.find(line) else {
+            continue;
+        };
+        if matches.len() >= max_results {
+            return true;
+        }
+
+        let before = index.saturating_sub(context_lines);
+        let after = (index + 1 + context_lines).min(lines.len());
+        matches.push(SearchMatch {
+            path: path.to_string(),
+            line: index + 1,
+            column: line[..found.start()].chars().count() + 1,
+            text: line.to_string(),
+            context_before: lines[before..index].iter().map(|l| l.to_string()).collect(),
+            context_after: lines[index + 1..after].iter().map(|l| l.to_string()).collect(),
+        });
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn temp_tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/dep")).unwrap();
+        std::fs::write(
+            dir.path().join("src/main.rs"),
+            "use std::io;\n\nfn main() {\n    let total = compute_total(1, 2);\n    println!(\"{}\", total);\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/lib.ts"), "export const Total = 3; // total\n").unwrap();
+        std::fs::write(dir.path().join("node_modules/dep/index.js"), "const total = 0;\n").unwrap();
+        std::fs::write(dir.path().join("src/blob.bin"), b"total\0\0\0\x01\x02").unwrap();
+        dir
+    }
+
+    fn ignore_dirs() -> Vec<String> {
+        vec!["node_modules".to_string()]
+    }
+
+    #[test]
+    fn test_literal_search_with_context() {
+        let dir = temp_tree();
+        let result = search_files(dir.path(), "total", &SearchOptions::default(), &ignore_dirs()).unwrap();
+
+        let found: Vec<(String, usize, usize)> = result
+            .matches
+            .iter()
+            .map(|m| {
+                let name = Path::new(&m.path).file_name().unwrap().to_string_lossy().to_string();
+                (name, m.line, m.column)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("lib.ts".to_string(), 1, 28),
+                ("main.rs".to_string(), 4, 9),
+                ("main.rs".to_string(), 5, 20),
+            ]
+        );
+
+        let first_main = &result.matches[1];
+        assert_eq!(first_main.context_before, vec!["", "fn main() {"]);
+        assert_eq!(first_main.context_after, vec!["    println!(\"{}\", total);", "}"]);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn test_regex_case_and_word_options() {
+        let dir = temp_tree();
+
+        let options = SearchOptions {
+            regex: true,
+            case_sensitive: false,
+            context_lines: 0,
+            include: vec!["*.ts".to_string()],
+            ..Default::default()
+        };
+        let result = search_files(dir.path(), r"^export\s+const\s+total", &options, &ignore_dirs()).unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.files_searched, 1);
+
+        let options = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        let result = search_files(dir.path(), "compute", &options, &ignore_dirs()).unwrap();
+        assert!(result.matches.is_empty());
+
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            search_files(dir.path(), "(", &options, &ignore_dirs()),
+            Err(CommandError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_result_cap() {
+        let dir = temp_tree();
+        let options = SearchOptions {
+            max_results: Some(2),
+            ..Default::default()
+        };
+        let result = search_files(dir.path(), "total", &options, &ignore_dirs()).unwrap();
+
+        assert_eq!(result.matches.len(), 2);
+        assert!(result.truncated);
+    }
+}
@@ -0,0 +1,284 @@
+//! Recursive directory listing for `list_tree`
+//!
+//! Walks a directory honouring `.gitignore`/`.ignore` files and glob filters.
+//! When the entry limit is hit, shallower entries are kept first so the
+//! overall shape of the tree survives, and directories whose children were
+//! cut off are marked as truncated.
+
+use super::write::modified_secs;
+use crate::commands::CommandError;
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Entries returned when no limit is given
+const DEFAULT_MAX_ENTRIES: usize = 1000;
+/// Hard cap on entries visited, whatever the requested limit
+const MAX_WALK_ENTRIES: usize = 100_000;
+
+/// Options for `list_tree`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TreeOptions {
+    /// Maximum depth below the listed directory (1 = direct children only)
+    pub max_depth: Option<usize>,
+    /// Only list files matching one of these globs (directories are always walked)
+    pub include: Vec<String>,
+    /// Skip files and directories matching any of these globs
+    pub exclude: Vec<String>,
+    /// Honour `.gitignore`, `.ignore` and `.git/info/exclude`
+    pub respect_gitignore: bool,
+    /// List dotfiles and dot-directories
+    pub include_hidden: bool,
+    /// Maximum number of entries to return
+    pub max_entries: Option<usize>,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            respect_gitignore: true,
+            include_hidden: false,
+            max_entries: None,
+        }
+    }
+}
+
+/// A file or directory in the tree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeEntry {
+    pub name: String,
+    pub path: String,
+    /// Depth below the listed directory, starting at 1
+    pub depth: usize,
+    pub is_dir: bool,
+    pub is_file: bool,
+    pub size: u64,
+    pub modified: Option<u64>,
+    /// Directory has children that were not listed because of the depth or entry limit
+    pub truncated: bool,
+}
+
+/// Result of `list_tree`, entries in depth-first order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeListing {
+    pub root: String,
+    pub entries: Vec<TreeEntry>,
+    /// Some entries were left out because of the entry limit
+    pub truncated: bool,
+}
+
+/// List the tree under `root`
+pub fn list_tree(root: &Path, options: &TreeOptions) -> Result<TreeListing, CommandError> {
+    if !root.is_dir() {
+        return Err(CommandError::PathError(format!(
+            "Not a directory: {}",
+            root.display()
+        )));
+    }
+    let max_entries = options.max_entries.unwrap_or(DEFAULT_MAX_ENTRIES);
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .max_depth(options.max_depth)
+        .hidden(!options.include_hidden)
+        .git_ignore(options.respect_gitignore)
+        .git_exclude(options.respect_gitignore)
+        .ignore(options.respect_gitignore)
+        .git_global(false)
+        .parents(options.respect_gitignore)
+        .require_git(false)
+        .follow_links(false)
+        .overrides(glob_overrides(root, &options.include, &options.exclude)?)
+        .sort_by_file_name(|a, b| a.cmp(b));
+
+    let mut walked = Vec::new();
+    let mut walk_truncated = false;
+    // Unreadable entries are skipped rather than failing the whole listing
+    for entry in builder.build().flatten() {
+        if entry.depth() == 0 {
+            continue;
+        }
+        if walked.len() >= MAX_WALK_ENTRIES {
+            walk_truncated = true;
+            break;
+        }
+        walked.push(entry);
+    }
+
+    // Keep the shallowest entries, then emit them in walk order
+    let mut by_depth: Vec<usize> = (0..walked.len()).collect();
+    by_depth.sort_by_key(|&i| walked[i].depth());
+    let kept: HashSet<usize> = by_depth.into_iter().take(max_entries).collect();
+    let truncated = walk_truncated || kept.len() < walked.len();
+
+    // Directories with at least one child that didn't make the cut
+    let cut_parents: HashSet<PathBuf> = walked
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !kept.contains(i))
+        .filter_map(|(_, e)| e.path().parent().map(Path::to_path_buf))
+        .collect();
+
+    let entries = walked
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| kept.contains(i))
+        .map(|(_, entry)| {
+            let metadata = entry.metadata().ok();
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            let at_depth_limit = options.max_depth.is_some_and(|d| entry.depth() >= d);
+            let truncated = is_dir
+                && (cut_parents.contains(entry.path())
+                    || (at_depth_limit && has_children(entry.path())));
+
+            TreeEntry {
+                name: entry.file_name().to_string_lossy().to_string(),
+                path: entry.path().to_string_lossy().to_string(),
+                depth: entry.depth(),
+                is_dir,
+                is_file: entry.file_type().is_some_and(|t| t.is_file()),
+                size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+                modified: metadata.as_ref().and_then(modified_secs),
+                truncated,
+            }
+        })
+        .collect();
+
+    Ok(TreeListing {
+        root: root.to_string_lossy().to_string(),
+        entries,
+        truncated,
+    })
+}
+
+fn invalid_glob(e: ignore::Error) -> CommandError {
+    CommandError::InvalidRequest(format!("Invalid glob: {}", e))
+}
+
+/// Build walker overrides: with any `include` globs only matching files are
+/// yielded, and anything matching an `exclude` glob is skipped
+pub fn glob_overrides(root: &Path, include: &[String], exclude: &[String]) -> Result<Override, CommandError> {
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in include {
+        overrides.add(glob).map_err(invalid_glob)?;
+    }
+    for glob in exclude {
+        overrides.add(&format!("!{}", glob)).map_err(invalid_glob)?;
+    }
+    overrides.build().map_err(invalid_glob)
+}
+
+fn has_children(dir: &Path) -> bool {
+    std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn temp_tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for path in ["src/bin", "target/debug", ".hidden"] {
+            std::fs::create_dir_all(dir.path().join(path)).unwrap();
+        }
+        for path in [
+            "Cargo.toml",
+            "README.md",
+            "src/lib.rs",
+            "src/main.rs",
+            "src/bin/tool.rs",
+            "target/debug/app",
+            ".hidden/secret",
+        ] {
+            std::fs::write(dir.path().join(path), "x").unwrap();
+        }
+        std::fs::write(dir.path().join(".gitignore"), "target/\n").unwrap();
+        dir
+    }
+
+    fn names(listing: &TreeListing) -> Vec<String> {
+        listing
+            .entries
+            .iter()
+            .map(|e| {
+                Path::new(&e.path)
+                    .strip_prefix(&listing.root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list_tree_respects_gitignore_and_hidden() {
+        let dir = temp_tree();
+        let listing = list_tree(dir.path(), &TreeOptions::default()).unwrap();
+
+        assert_eq!(
+            names(&listing),
+            vec!["Cargo.toml", "README.md", "src", "src/bin", "src/bin/tool.rs", "src/lib.rs", "src/main.rs"]
+        );
+        assert!(!listing.truncated);
+        assert!(listing.entries.iter().all(|e| e.modified.is_some()));
+
+        let options = TreeOptions {
+            respect_gitignore: false,
+            include_hidden: true,
+            ..Default::default()
+        };
+        let all = names(&list_tree(dir.path(), &options).unwrap());
+        assert!(all.contains(&"target/debug/app".to_string()));
+        assert!(all.contains(&".hidden/secret".to_string()));
+    }
+
+    #[test]
+    fn test_list_tree_depth_and_globs() {
+        let dir = temp_tree();
+
+        let options = TreeOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let listing = list_tree(dir.path(), &options).unwrap();
+        assert_eq!(names(&listing), vec!["Cargo.toml", "README.md", "src"]);
+        assert!(listing.entries[2].truncated);
+
+        let options = TreeOptions {
+            include: vec!["*.rs".to_string()],
+            exclude: vec!["bin".to_string()],
+            ..Default::default()
+        };
+        let listing = list_tree(dir.path(), &options).unwrap();
+        assert_eq!(names(&listing), vec!["src", "src/lib.rs", "src/main.rs"]);
+
+        let options = TreeOptions {
+            include: vec!["[".to_string()],
+            ..Default::default()
+        };
+        assert!(matches!(list_tree(dir.path(), &options), Err(CommandError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_list_tree_entry_limit_keeps_shallow_entries() {
+        let dir = temp_tree();
+        let options = TreeOptions {
+            max_entries: Some(4),
+            ..Default::default()
+        };
+        let listing = list_tree(dir.path(), &options).unwrap();
+
+        assert!(listing.truncated);
+        assert_eq!(names(&listing), vec!["Cargo.toml", "README.md", "src", "src/bin"]);
+        // src lost lib.rs and main.rs, src/bin lost tool.rs
+        assert!(listing.entries[2].truncated);
+        assert!(listing.entries[3].truncated);
+    }
+}
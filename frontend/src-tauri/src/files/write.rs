@@ -0,0 +1,254 @@
+//! Atomic file writes with optional conflict detection
+//!
+//! Contents are written to a temporary file next to the target and renamed
+//! over it, so a crash never leaves a half-written file. A precondition on the
+//! current file's hash or modification time rejects writes based on a stale read.
+
+use crate::commands::CommandError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use uuid::Uuid;
+
+/// Serializes the precondition check and rename so two writers can't both pass the check
+static COMMIT_LOCK: Mutex<()> = Mutex::new(());
+
+/// What the file must look like for a write to go ahead
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WritePrecondition {
+    /// SHA-256 (hex) of the current contents
+    pub expected_hash: Option<String>,
+    /// Current modification time in milliseconds since the Unix epoch, as in
+    /// `FileInfo::modified_ms`
+    pub expected_mtime_ms: Option<u64>,
+}
+
+impl WritePrecondition {
+    pub fn is_empty(&self) -> bool {
+        self.expected_hash.is_none() && self.expected_mtime_ms.is_none()
+    }
+
+    /// Check the file at `path` against the precondition
+    pub fn check(&self, path: &Path) -> Result<(), CommandError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let metadata = match std::fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(CommandError::Conflict(format!(
+                    "{} no longer exists",
+                    path.display()
+                )))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(expected) = self.expected_mtime_ms {
+            let actual = modified_ms(&metadata);
+            if actual != Some(expected) {
+                return Err(CommandError::Conflict(format!(
+                    "{} was modified (expected mtime {}, found {})",
+                    path.display(),
+                    expected,
+                    actual.map(|m| m.to_string()).unwrap_or_else(|| "unknown".to_string())
+                )));
+            }
+        }
+
+        if let Some(expected) = &self.expected_hash {
+            let actual = hash_bytes(&std::fs::read(path)?);
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(CommandError::Conflict(format!(
+                    "{} was modified (expected hash {}, found {})",
+                    path.display(),
+                    expected,
+                    actual
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Result of a successful write
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WriteResult {
+    pub path: String,
+    pub bytes_written: u64,
+    /// SHA-256 of the new contents, usable as the next write's `expected_hash`
+    pub hash: String,
+    /// Modification time in milliseconds, usable as the next write's `expected_mtime_ms`
+    pub modified_ms: Option<u64>,
+}
+
+/// SHA-256 of some bytes as lowercase hex
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn modified_secs(metadata: &std::fs::Metadata) -> Option<u64> {
+    modified_ms(metadata).map(|ms| ms / 1000)
+}
+
+/// Modification time in milliseconds, fine enough to tell apart writes within a second
+pub fn modified_ms(metadata: &std::fs::Metadata) -> Option<u64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+}
+
+/// Write `contents` to `path` atomically, if the file still matches `precondition`
+pub fn write_atomic(
+    path: &Path,
+    contents: &[u8],
+    precondition: &WritePrecondition,
+) -> Result<WriteResult, CommandError> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| CommandError::PathError(format!("No parent directory: {}", path.display())))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| CommandError::PathError(format!("Not a file path: {}", path.display())))?;
+    std::fs::create_dir_all(parent)?;
+
+    // Temp file in the same directory so the rename stays on one filesystem
+    let temp_path = parent.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        Uuid::new_v4().simple()
+    ));
+
+    let result = write_temp(&temp_path, path, contents).and_then(|()| {
+        let _guard = COMMIT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        precondition.check(path)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    });
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    let metadata = std::fs::metadata(path)?;
+    Ok(WriteResult {
+        path: path.to_string_lossy().to_string(),
+        bytes_written: contents.len() as u64,
+        hash: hash_bytes(contents),
+        modified_ms: modified_ms(&metadata),
+    })
+}
+
+/// Write and sync the temp file, keeping the permissions of the file it replaces
+fn write_temp(temp_path: &Path, target: &Path, contents: &[u8]) -> Result<(), CommandError> {
+    let mut file = std::fs::File::create(temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    if let Ok(metadata) = std::fs::metadata(target) {
+        std::fs::set_permissions(temp_path, metadata.permissions())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_atomic_creates_and_replaces() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("nested/file.txt");
+
+        let first = write_atomic(&path, b"one", &WritePrecondition::default()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"one");
+        assert_eq!(first.hash, hash_bytes(b"one"));
+
+        let precondition = WritePrecondition {
+            expected_hash: Some(first.hash.clone()),
+            expected_mtime_ms: None,
+        };
+        write_atomic(&path, b"two", &precondition).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"two");
+
+        // Only the target is left behind, no temp files
+        assert_eq!(std::fs::read_dir(dir.path().join("nested")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_atomic_rejects_stale_hash() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file.txt");
+        std::fs::write(&path, b"theirs").unwrap();
+
+        let precondition = WritePrecondition {
+            expected_hash: Some(hash_bytes(b"original")),
+            expected_mtime_ms: None,
+        };
+        let result = write_atomic(&path, b"mine", &precondition);
+
+        assert!(matches!(result, Err(CommandError::Conflict(_))));
+        assert_eq!(std::fs::read(&path).unwrap(), b"theirs");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_precondition_on_missing_file() {
+        let dir = TempDir::new().unwrap();
+        let precondition = WritePrecondition {
+            expected_hash: None,
+            expected_mtime_ms: Some(0),
+        };
+
+        assert!(matches!(
+            precondition.check(&dir.path().join("missing.txt")),
+            Err(CommandError::Conflict(_))
+        ));
+        assert!(WritePrecondition::default().check(&dir.path().join("missing.txt")).is_ok());
+    }
+
+    #[test]
+    fn test_write_atomic_rejects_change_within_the_same_second() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("file.txt");
+        let read_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        std::fs::write(&path, b"original").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(read_at)
+            .unwrap();
+        let precondition = WritePrecondition {
+            expected_hash: None,
+            expected_mtime_ms: modified_ms(&std::fs::metadata(&path).unwrap()),
+        };
+
+        // Another writer changes the file 300ms later
+        std::fs::write(&path, b"theirs").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(read_at + Duration::from_millis(300))
+            .unwrap();
+
+        let result = write_atomic(&path, b"mine", &precondition);
+        assert!(matches!(result, Err(CommandError::Conflict(_))));
+        assert_eq!(std::fs::read(&path).unwrap(), b"theirs");
+    }
+}
@@ -3,7 +3,7 @@
 //! Splits source files into semantic chunks suitable for embedding.
 //! Uses tree-sitter for syntax-aware chunking when available, falling back to line-based chunking.
 
-use super::tree_sitter_parser::{SupportedLanguage, parse_source, extract_semantic_units};
+use super::tree_sitter_parser::{SemanticUnit, SupportedLanguage, parse_source, extract_semantic_units};
 use super::{CodeChunk, IndexerConfig};
 use std::path::Path;
 use uuid::Uuid;
@@ -46,62 +46,192 @@ fn chunk_file_tree_sitter(
     let parsed = parse_source(content, language)?;
     let units = extract_semantic_units(&parsed);
 
-    if units.is_empty() {
-        return None;
+    let chunker = UnitChunker {
+        filesystem_hex_id,
+        file_path,
+        language,
+        config,
+    };
+    let mut chunks = Vec::new();
+    for unit in &units {
+        chunker.push_unit(unit, &mut chunks);
     }
+    let lines: Vec<&str> = content.lines().collect();
+    chunker.push_gaps(&lines, &units, &mut chunks);
 
-    let mut chunks = Vec::new();
-    let language_name = Some(language.name().to_string());
+    // Gap chunks were added last; restore file order, outlines before their members
+    chunks.sort_by_key(|chunk| chunk.start_line);
 
-    for unit in units {
+    Some(chunks)
+}
+
+/// Turns a file's semantic units into chunks
+struct UnitChunker<'a> {
+    filesystem_hex_id: &'a str,
+    file_path: &'a str,
+    language: SupportedLanguage,
+    config: &'a IndexerConfig,
+}
+
+impl UnitChunker<'_> {
+    /// Chunk a unit whole if it fits; otherwise chunk a container as an
+    /// outline plus its members, and anything else as overlapping line windows
+    ///
+    /// An outline that is itself too long, such as a class with hundreds of
+    /// methods, is split into line windows too.
+    fn push_unit(&self, unit: &SemanticUnit, chunks: &mut Vec<CodeChunk>) {
         let line_count = unit.end_line - unit.start_line + 1;
 
         // If unit is small enough, create a single chunk
-        if line_count <= config.max_chunk_lines {
-            chunks.push(CodeChunk {
-                id: Uuid::new_v4().to_string(),
-                filesystem_hex_id: filesystem_hex_id.to_string(),
-                file_path: file_path.to_string(),
-                start_line: (unit.start_line + 1) as u32, // Convert to 1-indexed
-                end_line: (unit.end_line + 1) as u32,
-                content: unit.content,
-                language: language_name.clone(),
-            });
-        } else {
-            // For very large units, split into overlapping chunks
-            let unit_lines: Vec<&str> = unit.content.lines().collect();
-            let step = config.max_chunk_lines.saturating_sub(config.overlap_lines).max(1);
-            let mut start = 0;
-
-            while start < unit_lines.len() {
-                let end = (start + config.max_chunk_lines).min(unit_lines.len());
-
-                if end - start < config.min_chunk_lines && !chunks.is_empty() {
-                    break;
-                }
+        if line_count <= self.config.max_chunk_lines {
+            chunks.push(self.unit_chunk(unit, unit.start_line, unit.end_line, &unit.content));
+            return;
+        }
+
+        if !unit.members.is_empty() {
+            let outline = unit.outline_lines();
+            for (start, end) in line_windows(outline.len(), self.config) {
+                let content: Vec<&str> = outline[start..end].iter().map(|(_, line)| line.as_str()).collect();
+                chunks.push(self.unit_chunk(unit, outline[start].0, outline[end - 1].0, &content.join("\n")));
+            }
+            for member in &unit.members {
+                self.push_unit(member, chunks);
+            }
+            return;
+        }
 
-                let chunk_content = unit_lines[start..end].join("\n");
+        // For very large units, split into overlapping chunks
+        let unit_lines: Vec<&str> = unit.content.lines().collect();
+        for (start, end) in line_windows(unit_lines.len(), self.config) {
+            let chunk_content = unit_lines[start..end].join("\n");
+            chunks.push(self.unit_chunk(unit, unit.start_line + start, unit.start_line + end - 1, &chunk_content));
+        }
+    }
 
-                chunks.push(CodeChunk {
-                    id: Uuid::new_v4().to_string(),
-                    filesystem_hex_id: filesystem_hex_id.to_string(),
-                    file_path: file_path.to_string(),
-                    start_line: (unit.start_line + start + 1) as u32,
-                    end_line: (unit.start_line + end) as u32,
-                    content: chunk_content,
-                    language: language_name.clone(),
-                });
+    /// Chunk the lines outside every top-level unit, such as imports and
+    /// module-level statements
+    ///
+    /// Blank lines at the edges of a gap are dropped. A gap shorter than
+    /// `min_chunk_lines` shares a chunk with the next gap, as long as the
+    /// lines from the first to the last fit in `max_chunk_lines`, so a chunk
+    /// never claims to span a large unit between them.
+    fn push_gaps(&self, lines: &[&str], units: &[SemanticUnit], chunks: &mut Vec<CodeChunk>) {
+        let mut covered = vec![false; lines.len()];
+        for unit in units {
+            for line in covered.iter_mut().take(unit.end_line + 1).skip(unit.start_line) {
+                *line = true;
+            }
+        }
 
-                if end >= unit_lines.len() {
-                    break;
+        // Runs of uncovered lines, trimmed of blank lines, as 0-indexed `start..end`
+        let mut gaps: Vec<(usize, usize)> = Vec::new();
+        let mut line = 0;
+        while line < lines.len() {
+            if covered[line] || lines[line].trim().is_empty() {
+                line += 1;
+                continue;
+            }
+            let start = line;
+            while line < lines.len() && !covered[line] {
+                line += 1;
+            }
+            let end = (start..line).rev().find(|&l| !lines[l].trim().is_empty()).unwrap_or(start) + 1;
+            gaps.push((start, end));
+        }
+
+        let mut groups: Vec<Vec<(usize, usize)>> = Vec::new();
+        for gap in gaps {
+            let gap_lines = gap.1 - gap.0;
+            if let Some(group) = groups.last_mut() {
+                let group_lines: usize = group.iter().map(|(start, end)| end - start).sum();
+                let tiny = group_lines < self.config.min_chunk_lines || gap_lines < self.config.min_chunk_lines;
+                if tiny && gap.1 - group[0].0 <= self.config.max_chunk_lines {
+                    group.push(gap);
+                    continue;
                 }
+            }
+            groups.push(vec![gap]);
+        }
 
-                start += step;
+        for group in groups {
+            let (first_start, _) = group[0];
+            let (_, last_end) = group[group.len() - 1];
+            if group.len() == 1 {
+                for (start, end) in line_windows(last_end - first_start, self.config) {
+                    let content = lines[first_start + start..first_start + end].join("\n");
+                    chunks.push(self.chunk(first_start + start, first_start + end - 1, content));
+                }
+            } else {
+                let content: Vec<String> = group.iter().map(|&(start, end)| lines[start..end].join("\n")).collect();
+                chunks.push(self.chunk(first_start, last_end - 1, content.join("\n")));
             }
         }
     }
 
-    Some(chunks)
+    /// Create a chunk of `unit` spanning 0-indexed lines `start_line..=end_line`,
+    /// prefixed with the header naming its container
+    fn unit_chunk(&self, unit: &SemanticUnit, start_line: usize, end_line: usize, content: &str) -> CodeChunk {
+        let content = match &unit.header {
+            Some(header) => format!("{}\n{}", header, content),
+            None => content.to_string(),
+        };
+
+        CodeChunk {
+            kind: unit.category.map(str::to_string),
+            symbol: unit.name.clone(),
+            node_kind: Some(unit.kind.clone()),
+            parent_symbol: unit.parent.clone(),
+            signature: unit.signature.clone(),
+            ..self.chunk(start_line, end_line, content)
+        }
+    }
+
+    /// Create a chunk with no symbol spanning 0-indexed lines `start_line..=end_line`
+    fn chunk(&self, start_line: usize, end_line: usize, content: String) -> CodeChunk {
+        CodeChunk {
+            id: Uuid::new_v4().to_string(),
+            filesystem_hex_id: self.filesystem_hex_id.to_string(),
+            file_path: self.file_path.to_string(),
+            start_line: (start_line + 1) as u32, // Convert to 1-indexed
+            end_line: (end_line + 1) as u32,
+            content,
+            language: Some(self.language.name().to_string()),
+            kind: None,
+            symbol: None,
+            node_kind: None,
+            parent_symbol: None,
+            signature: None,
+        }
+    }
+}
+
+/// Split `line_count` lines into overlapping windows of at most
+/// `max_chunk_lines`, as 0-indexed `start..end` ranges
+///
+/// A final window shorter than `min_chunk_lines` starts earlier instead,
+/// overlapping the previous window more, so every line is still covered.
+fn line_windows(line_count: usize, config: &IndexerConfig) -> Vec<(usize, usize)> {
+    let step = config.max_chunk_lines.saturating_sub(config.overlap_lines).max(1);
+    let mut windows = Vec::new();
+    let mut start = 0;
+
+    while start < line_count {
+        let end = (start + config.max_chunk_lines).min(line_count);
+
+        // A short final window takes more lines from the previous one (unless it's the first chunk)
+        if end - start < config.min_chunk_lines && start > 0 {
+            windows.push((line_count.saturating_sub(config.min_chunk_lines), end));
+            break;
+        }
+        windows.push((start, end));
+
+        if end >= line_count {
+            break;
+        }
+        start += step;
+    }
+
+    windows
 }
 
 /// Chunk a file using line-based splitting (fallback)
@@ -131,22 +261,17 @@ fn chunk_file_line_based(
             end_line: total_lines as u32,
             content: content.to_string(),
             language: language.clone(),
+            kind: None,
+            symbol: None,
+            node_kind: None,
+            parent_symbol: None,
+            signature: None,
         });
         return chunks;
     }
 
     // For larger files, create overlapping chunks
-    let step = config.max_chunk_lines.saturating_sub(config.overlap_lines).max(1);
-    let mut start = 0;
-
-    while start < total_lines {
-        let end = (start + config.max_chunk_lines).min(total_lines);
-
-        // Skip if chunk would be too small (unless it's the last chunk)
-        if end - start < config.min_chunk_lines && !chunks.is_empty() {
-            break;
-        }
-
+    for (start, end) in line_windows(total_lines, config) {
         let chunk_content = lines[start..end].join("\n");
 
         chunks.push(CodeChunk {
@@ -157,13 +282,12 @@ fn chunk_file_line_based(
             end_line: end as u32,           // 1-indexed, inclusive
             content: chunk_content,
             language: language.clone(),
+            kind: None,
+            symbol: None,
+            node_kind: None,
+            parent_symbol: None,
+            signature: None,
         });
-
-        if end >= total_lines {
-            break;
-        }
-
-        start += step;
     }
 
     chunks
@@ -189,8 +313,8 @@ fn detect_language(file_path: &str) -> Option<String> {
             "py" => "python",
             "go" => "go",
             "java" => "java",
-            "c" | "h" => "c",
-            "cpp" | "hpp" | "cc" | "cxx" => "cpp",
+            "c" => "c",
+            "cpp" | "hpp" | "cc" | "cxx" | "h" => "cpp",
             "cs" => "csharp",
             "rb" => "ruby",
             "php" => "php",
@@ -257,6 +381,12 @@ struct MyStruct {
         assert!(chunks[1].content.contains("bar"));
         assert!(chunks[2].content.contains("MyStruct"));
         assert_eq!(chunks[0].language, Some("rust".to_string()));
+        assert_eq!(chunks[0].kind, Some("function".to_string()));
+        assert_eq!(chunks[2].kind, Some("struct".to_string()));
+        assert_eq!(chunks[0].symbol, Some("foo".to_string()));
+        assert_eq!(chunks[0].node_kind, Some("function_item".to_string()));
+        assert_eq!(chunks[0].signature, Some("fn foo()".to_string()));
+        assert_eq!(chunks[2].symbol, Some("MyStruct".to_string()));
     }
 
     #[test]
@@ -300,6 +430,194 @@ class MyClass:
         assert_eq!(chunks[0].language, Some("python".to_string()));
     }
 
+    #[test]
+    fn test_chunk_large_impl_by_method() {
+        let config = IndexerConfig {
+            max_chunk_lines: 6,
+            min_chunk_lines: 2,
+            overlap_lines: 1,
+            ..IndexerConfig::default()
+        };
+        let content = r#"impl Database {
+    pub fn add_board_usage(&self, tokens: u64) {
+        self.usage += tokens;
+    }
+
+    pub fn reset_board_usage(&self) {
+        let a = 1;
+        let b = 2;
+        let c = 3;
+        let d = 4;
+        let e = 5;
+        let f = 6;
+    }
+}
+"#;
+        let chunks = chunk_file("hex-1", "db.rs", content, &config);
+
+        // Outline, one small method, and two windows of the oversized one
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0].symbol, Some("Database".to_string()));
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 14));
+        assert!(chunks[0].content.contains("pub fn reset_board_usage(&self)\n}"));
+
+        assert_eq!(chunks[1].symbol, Some("add_board_usage".to_string()));
+        assert_eq!(chunks[1].parent_symbol, Some("Database".to_string()));
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (2, 4));
+        assert!(chunks[1].content.starts_with("impl Database\npub fn add_board_usage"));
+
+        assert_eq!((chunks[2].start_line, chunks[2].end_line), (6, 11));
+        assert_eq!((chunks[3].start_line, chunks[3].end_line), (11, 13));
+        assert!(chunks[3].content.starts_with("impl Database\n"));
+        assert_eq!(chunks[3].symbol, Some("reset_board_usage".to_string()));
+    }
+
+    #[test]
+    fn test_chunk_long_outline_in_windows() {
+        let config = IndexerConfig::default();
+        let methods: String = (0..120)
+            .map(|i| format!("    fn method_{}(&self) {{\n        self.step();\n    }}\n", i))
+            .collect();
+        let content = format!("impl Big {{\n{}}}\n", methods);
+        let chunks = chunk_file("hex-1", "big.rs", &content, &config);
+
+        // The 122-line outline is split like any other long unit
+        let outlines: Vec<_> = chunks
+            .iter()
+            .filter(|c| c.symbol.as_deref() == Some("Big"))
+            .collect();
+        assert!(outlines.len() > 1);
+        for outline in &outlines {
+            assert!(outline.content.lines().count() <= config.max_chunk_lines);
+        }
+        assert_eq!(outlines[0].start_line, 1);
+        assert!(outlines[0].content.starts_with("impl Big {\n    fn method_0(&self)\n    fn method_1(&self)"));
+        assert_eq!(outlines[1].start_line, 2 + 3 * (config.max_chunk_lines - config.overlap_lines - 1) as u32);
+        assert_eq!(outlines.last().unwrap().end_line, 362);
+        assert_eq!(chunks.len(), outlines.len() + 120);
+    }
+
+    #[test]
+    fn test_line_windows() {
+        let config = IndexerConfig {
+            max_chunk_lines: 10,
+            min_chunk_lines: 5,
+            overlap_lines: 2,
+            ..IndexerConfig::default()
+        };
+
+        // A short first window is kept, since nothing else covers its lines
+        assert_eq!(line_windows(3, &config), vec![(0, 3)]);
+        assert_eq!(line_windows(10, &config), vec![(0, 10)]);
+        assert_eq!(line_windows(21, &config), vec![(0, 10), (8, 18), (16, 21)]);
+        // A short final window is pulled back to `min_chunk_lines`
+        assert_eq!(line_windows(19, &config), vec![(0, 10), (8, 18), (14, 19)]);
+    }
+
+    #[test]
+    fn test_chunk_gaps_between_units() {
+        let config = IndexerConfig::default();
+        let content = r#"import os
+import sys
+
+CONFIG = {"debug": True}
+
+
+def main():
+    print("main")
+
+
+x = 1
+
+if __name__ == "__main__":
+    main()
+"#;
+        let chunks = chunk_file("hex-1", "script.py", content, &config);
+        let ranges: Vec<_> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        // The tiny module-level gaps around `main` share one chunk
+        assert_eq!(ranges, vec![(1, 14), (7, 8)]);
+        assert_eq!(
+            chunks[0].content,
+            "import os\nimport sys\n\nCONFIG = {\"debug\": True}\nx = 1\n\nif __name__ == \"__main__\":\n    main()"
+        );
+        assert_eq!(chunks[0].symbol, None);
+        assert_eq!(chunks[0].language, Some("python".to_string()));
+        assert_eq!(chunks[1].symbol, Some("main".to_string()));
+
+        // Gaps that are both large enough stay apart
+        let config = IndexerConfig {
+            min_chunk_lines: 2,
+            ..IndexerConfig::default()
+        };
+        let chunks = chunk_file("hex-1", "script.py", content, &config);
+        let ranges: Vec<_> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 4), (7, 8), (11, 14)]);
+
+        // Gaps either side of a long unit stay apart
+        let content = format!("use a;\n\nfn long() {{\n{}}}\n\nuse b;\n", "    step();\n".repeat(2000));
+        let chunks = chunk_file("hex-1", "long.rs", &content, &IndexerConfig::default());
+        let gaps: Vec<_> = chunks
+            .iter()
+            .filter(|c| c.symbol.is_none())
+            .map(|c| (c.start_line, c.end_line, c.content.as_str()))
+            .collect();
+        assert_eq!(gaps, vec![(1, 1, "use a;"), (2006, 2006, "use b;")]);
+
+        // A script with no definitions is still chunked with its tree-sitter language
+        let chunks = chunk_file("hex-1", "view.tsx", "import React from 'react';\nrender(<App />);\n", &config);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].language, Some("tsx".to_string()));
+    }
+
+    #[test]
+    fn test_chunk_c_header() {
+        let config = IndexerConfig::default();
+
+        // One-line declarations share a chunk rather than getting one each
+        let content = "int a;\nint b;\nint c;\nint d;\nint e;\nint f;\n";
+        let chunks = chunk_file("hex-1", "globals.c", content, &config);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 6));
+
+        // Headers are parsed as C++, which also reads C
+        let chunks = chunk_file("hex-1", "foo.h", "class Foo { public: int x; };\n", &config);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].kind.as_deref(), Some("class"));
+        assert_eq!(chunks[0].symbol.as_deref(), Some("Foo"));
+        assert_eq!(chunks[0].language.as_deref(), Some("cpp"));
+    }
+
+    #[test]
+    fn test_chunks_cover_every_line() {
+        let config = IndexerConfig {
+            max_chunk_lines: 8,
+            min_chunk_lines: 4,
+            overlap_lines: 1,
+            ..IndexerConfig::default()
+        };
+        let body = "    x += 1;\n".repeat(14);
+        let content = format!(
+            "use a;\nuse b;\n\nconst A: u32 = 1;\n\nfn one() {{\n{body}}}\nstatic B: u32 = 2;\n\nimpl Big {{\n    fn two() {{\n{body}    }}\n\n    fn three() {{}}\n}}\n\n// trailing\nuse c;\n"
+        );
+        let chunks = chunk_file("hex-1", "cover.rs", &content, &config);
+
+        for (i, line) in content.lines().enumerate() {
+            let line_number = i as u32 + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            assert!(
+                chunks.iter().any(|c| c.start_line <= line_number && line_number <= c.end_line),
+                "line {} ({:?}) is in no chunk",
+                line_number,
+                line
+            );
+        }
+        for chunk in &chunks {
+            assert!(chunk.content.lines().count() <= config.max_chunk_lines + 1);
+        }
+    }
+
     #[test]
     fn test_chunk_unsupported_language_uses_line_based() {
         let config = IndexerConfig::default();
@@ -327,7 +645,8 @@ class MyClass:
         assert_eq!(detect_supported_language("test.go"), Some(SupportedLanguage::Go));
         assert_eq!(detect_supported_language("test.js"), Some(SupportedLanguage::JavaScript));
         assert_eq!(detect_supported_language("test.txt"), None);
-        assert_eq!(detect_supported_language("test.java"), None);
+        assert_eq!(detect_supported_language("test.java"), Some(SupportedLanguage::Java));
+        assert_eq!(detect_supported_language("test.kt"), None);
     }
 }
 
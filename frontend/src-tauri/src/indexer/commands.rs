@@ -2,21 +2,119 @@
 //!
 //! Exposes the indexer functionality to the frontend via Tauri IPC.
 
-use super::embedder::Embedder;
-use super::store::VectorStore;
+use super::jobs::{IndexEvent, IndexJobs, Job, JobInfo, JobKind, JobOutput, Listener};
+use super::pipeline::modified_ms;
+use super::provider::EmbeddingProvider;
+use super::store::{FileRecord, SearchFilters, SearchMode, SearchQuery, StoreBatch, VectorStore};
 use super::{chunker, IndexerConfig, SearchResult};
+use crate::database::Database;
+use crate::files::write::hash_bytes;
+use crate::rbac::{self, Permission};
+use crate::sandbox::SandboxRoot;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tauri::ipc::Channel;
 use tauri::State;
-use walkdir::WalkDir;
 
 /// Indexer state managed by Tauri
 pub struct IndexerState {
-    pub embedder: Arc<Embedder>,
+    pub embedder: Arc<dyn EmbeddingProvider>,
     pub store: Arc<VectorStore>,
     pub config: IndexerConfig,
+    pub jobs: IndexJobs,
+    /// Why the embedding settings couldn't be loaded at startup, if they couldn't
+    pub settings_error: Option<String>,
+}
+
+impl IndexerState {
+    /// Run queued indexing jobs, one at a time, forever
+    ///
+    /// Called once on a blocking thread at startup.
+    pub fn run_jobs(&self) {
+        loop {
+            self.jobs.run_next(|job| self.run_job(job));
+        }
+    }
+
+    fn run_job(&self, job: &Job) -> Result<JobOutput, String> {
+        let hex_id = &job.filesystem_hex_id;
+        match &job.kind {
+            JobKind::IndexFile { base_path, file_path } => {
+                self.index_file(hex_id, base_path, file_path).map(JobOutput::Chunks)
+            }
+            JobKind::RemoveFile { file_path } => self
+                .store
+                .remove_file(hex_id, file_path)
+                .map(JobOutput::Chunks)
+                .map_err(|e| e.to_string()),
+            JobKind::IndexDirectory { base_path, directory_path } => {
+                job.emit(IndexEvent::Started { job_id: job.id.clone() });
+                self.index_directory(hex_id, base_path, directory_path, &job.cancelled, |event| {
+                    job.emit(event)
+                })
+                .map(JobOutput::Directory)
+            }
+            JobKind::ClearFilesystem => self
+                .store
+                .clear_filesystem(hex_id)
+                .map(JobOutput::Chunks)
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Whether a file's extension is one the indexer covers
+    pub fn is_indexable(&self, file_path: &str) -> bool {
+        let extension = Path::new(file_path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+        self.config.extensions.is_empty() || self.config.extensions.iter().any(|ext| ext == extension)
+    }
+
+    /// Chunk, embed and store one file, replacing its previous chunks
+    pub fn index_file(
+        &self,
+        filesystem_hex_id: &str,
+        base_path: &Path,
+        file_path: &str,
+    ) -> Result<usize, String> {
+        // Read file content
+        let full_path = base_path.join(file_path);
+        let content = fs::read_to_string(&full_path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let modified_ms = fs::metadata(&full_path).ok().as_ref().and_then(modified_ms);
+
+        // Chunk the file
+        let chunks = chunker::chunk_file(filesystem_hex_id, file_path, &content, &self.config);
+
+        // Generate embeddings
+        let embeddings = if chunks.is_empty() {
+            Vec::new()
+        } else {
+            let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
+            self.embedder.embed(texts).map_err(|e| e.to_string())?
+        };
+
+        // Replace the old chunks and record the new hash in one transaction
+        let chunk_count = chunks.len();
+        let record = FileRecord {
+            content_hash: hash_bytes(content.as_bytes()),
+            modified_ms,
+            chunk_count,
+        };
+        let batch = StoreBatch {
+            replace_files: vec![file_path.to_string()],
+            chunks: chunks.into_iter().zip(embeddings).collect(),
+            records: vec![(file_path.to_string(), record)],
+        };
+        self.store
+            .write_batch(filesystem_hex_id, &batch)
+            .map_err(|e| e.to_string())?;
+
+        Ok(chunk_count)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,12 +122,18 @@ pub struct IndexFileRequest {
     pub filesystem_hex_id: String,
     pub base_path: String,
     pub file_path: String,
+    /// Agent hex making the request, checked against the board's RBAC
+    pub agent_hex_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IndexDirectoryRequest {
     pub filesystem_hex_id: String,
     pub directory_path: String,
+    /// Agent hex making the request, checked against the board's RBAC
+    pub agent_hex_id: Option<String>,
+    /// Id for `indexer_cancel`; generated if not provided
+    pub job_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,12 +141,61 @@ pub struct SearchRequest {
     pub query: String,
     pub filesystem_hex_ids: Vec<String>,
     pub limit: Option<usize>,
+    /// Ranking to use; defaults to `vector`
+    pub mode: Option<SearchMode>,
+    /// Share of a hybrid search's score taken from keyword matches; defaults to 0.5
+    pub keyword_weight: Option<f32>,
+    /// Languages, paths, unit kinds and similarity to restrict the search to
+    pub filters: Option<SearchFilters>,
+    /// Agent hex making the request, checked against the board's RBAC
+    pub agent_hex_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct IndexResult {
     pub chunks_indexed: usize,
+    /// Files (re-)indexed: `files_added + files_updated`
     pub files_processed: usize,
+    /// Files indexed for the first time
+    pub files_added: usize,
+    /// Previously indexed files whose contents changed
+    pub files_updated: usize,
+    /// Previously indexed files that no longer exist
+    pub files_removed: usize,
+    /// Files whose mtime or hash matched the last index
+    pub files_skipped: usize,
+    /// The job was stopped by `indexer_cancel` before every file was visited
+    pub cancelled: bool,
+}
+
+/// Check that the calling agent, if any, may run `permission` on a filesystem hex
+fn authorize(
+    db: &Database,
+    agent_hex_id: Option<&str>,
+    filesystem_hex_id: &str,
+    permission: Permission,
+) -> Result<(), String> {
+    rbac::authorize(db, agent_hex_id, filesystem_hex_id, permission).map_err(|e| e.to_string())
+}
+
+/// Resolve a path sent by the frontend inside a filesystem hex's root;
+/// returns (canonical root, resolved path)
+fn sandboxed_path(
+    db: &Database,
+    filesystem_hex_id: &str,
+    path: &str,
+) -> Result<(PathBuf, PathBuf), String> {
+    let sandbox = SandboxRoot::for_hex(db, filesystem_hex_id).map_err(|e| e.to_string())?;
+    let resolved = sandbox.resolve(path).map_err(|e| e.to_string())?;
+    Ok((sandbox.root().to_path_buf(), resolved))
+}
+
+/// Queue a job and wait for its chunk count
+async fn queue_and_wait(state: &IndexerState, filesystem_hex_id: &str, kind: JobKind) -> Result<usize, String> {
+    match state.jobs.submit(filesystem_hex_id, kind, None, None).wait().await? {
+        JobOutput::Chunks(count) => Ok(count),
+        JobOutput::Directory(_) => Err("Unexpected directory result for a file job".to_string()),
+    }
 }
 
 /// Initialize the indexer (downloads model on first run)
@@ -51,11 +204,21 @@ pub async fn indexer_initialize(
     state: State<'_, IndexerState>,
 ) -> Result<bool, String> {
     // Initialize embedder (downloads model if needed)
-    state.embedder.initialize().map_err(|e| e.to_string())?;
-    
+    let embedder = state.embedder.clone();
+    tokio::task::spawn_blocking(move || embedder.initialize())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
     Ok(true)
 }
 
+/// The error the embedding settings failed with at startup, for the settings UI
+#[tauri::command]
+pub fn indexer_settings_error(state: State<'_, IndexerState>) -> Option<String> {
+    state.settings_error.clone()
+}
+
 /// Check if the indexer is ready
 #[tauri::command]
 pub fn indexer_is_ready(state: State<'_, IndexerState>) -> bool {
@@ -66,81 +229,99 @@ pub fn indexer_is_ready(state: State<'_, IndexerState>) -> bool {
 #[tauri::command]
 pub async fn indexer_index_file(
     state: State<'_, IndexerState>,
+    db: State<'_, Database>,
     request: IndexFileRequest,
 ) -> Result<usize, String> {
-    // Read file content
-    let full_path = Path::new(&request.base_path).join(&request.file_path);
-    let content = fs::read_to_string(&full_path)
-        .map_err(|e| format!("Failed to read file: {}", e))?;
+    authorize(&db, request.agent_hex_id.as_deref(), &request.filesystem_hex_id, Permission::Execute)?;
 
-    // Chunk the file
-    let chunks = chunker::chunk_file(
+    let (root, base_path) = sandboxed_path(&db, &request.filesystem_hex_id, &request.base_path)?;
+    // The file path is relative to the base, and mustn't climb out of the root either
+    let (_, full_path) = sandboxed_path(
+        &db,
         &request.filesystem_hex_id,
-        &request.file_path,
-        &content,
-        &state.config,
-    );
-
-    if chunks.is_empty() {
-        return Ok(0);
-    }
-
-    // Generate embeddings
-    let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
-    let embeddings = state.embedder.embed(texts).map_err(|e| e.to_string())?;
-
-    // Remove old chunks for this file first
-    state.store
-        .remove_file(&request.filesystem_hex_id, &request.file_path)
-        .map_err(|e| e.to_string())?;
-
-    // Store new chunks with embeddings
-    for (chunk, embedding) in chunks.iter().zip(embeddings.iter()) {
-        state.store.insert(chunk, embedding).map_err(|e| e.to_string())?;
-    }
-
-    Ok(chunks.len())
+        &base_path.join(&request.file_path).to_string_lossy(),
+    )?;
+    // Stored relative to the root, like the watcher and directory indexing store it
+    let file_path = full_path
+        .strip_prefix(&root)
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|_| format!("Not a file path: {}", request.file_path))?;
+
+    let kind = JobKind::IndexFile {
+        base_path: root,
+        file_path,
+    };
+    queue_and_wait(&state, &request.filesystem_hex_id, kind).await
 }
 
 /// Search the codebase
 #[tauri::command]
 pub async fn indexer_search(
     state: State<'_, IndexerState>,
+    db: State<'_, Database>,
     request: SearchRequest,
 ) -> Result<Vec<SearchResult>, String> {
-    let limit = request.limit.unwrap_or(10);
-
-    // Generate query embedding
-    let query_embedding = state.embedder
-        .embed_one(&request.query)
-        .map_err(|e| e.to_string())?;
-
-    // Search the vector store
-    let results = state.store
-        .search(&query_embedding, &request.filesystem_hex_ids, limit)
-        .map_err(|e| e.to_string())?;
+    if let Some(agent_hex_id) = request.agent_hex_id.as_deref() {
+        // An empty list searches every filesystem, which no single grant covers
+        if request.filesystem_hex_ids.is_empty() {
+            return Err("Agent searches must name the filesystem hexes to search".to_string());
+        }
+        for filesystem_hex_id in &request.filesystem_hex_ids {
+            authorize(&db, Some(agent_hex_id), filesystem_hex_id, Permission::Read)?;
+        }
+    }
 
-    Ok(results)
+    let limit = request.limit.unwrap_or(10);
+    let mode = request.mode.unwrap_or_default();
+    let embedder = state.embedder.clone();
+    let store = state.store.clone();
+
+    // Searches skip the job queue so they never wait behind a rescan
+    tokio::task::spawn_blocking(move || {
+        // Keyword searches don't need the embedding model
+        let embedding = match mode {
+            SearchMode::Keyword => None,
+            _ => Some(embedder.embed_one(&request.query).map_err(|e| e.to_string())?),
+        };
+
+        let query = SearchQuery {
+            text: request.query,
+            embedding,
+            mode,
+            keyword_weight: request.keyword_weight.unwrap_or(0.5),
+            filters: request.filters.unwrap_or_default(),
+        };
+        store
+            .search(&query, &request.filesystem_hex_ids, limit)
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
 /// Remove a file from the index
 #[tauri::command]
 pub async fn indexer_remove_file(
     state: State<'_, IndexerState>,
+    db: State<'_, Database>,
     filesystem_hex_id: String,
     file_path: String,
+    agent_hex_id: Option<String>,
 ) -> Result<usize, String> {
-    state.store
-        .remove_file(&filesystem_hex_id, &file_path)
-        .map_err(|e| e.to_string())
+    authorize(&db, agent_hex_id.as_deref(), &filesystem_hex_id, Permission::Execute)?;
+    queue_and_wait(&state, &filesystem_hex_id, JobKind::RemoveFile { file_path }).await
 }
 
 /// Get indexing stats for a filesystem hex
 #[tauri::command]
 pub fn indexer_get_stats(
     state: State<'_, IndexerState>,
+    db: State<'_, Database>,
     filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
 ) -> Result<serde_json::Value, String> {
+    authorize(&db, agent_hex_id.as_deref(), &filesystem_hex_id, Permission::Read)?;
+
     let chunk_count = state.store
         .get_chunk_count(&filesystem_hex_id)
         .map_err(|e| e.to_string())?;
@@ -149,124 +330,107 @@ pub fn indexer_get_stats(
         .get_indexed_files(&filesystem_hex_id)
         .map_err(|e| e.to_string())?;
 
+    let indexed_model = state.store
+        .get_filesystem_model(&filesystem_hex_id)
+        .map_err(|e| e.to_string())?;
+
     Ok(serde_json::json!({
         "chunk_count": chunk_count,
         "file_count": files.len(),
-        "files": files
+        "files": files,
+        "embedding_model": indexed_model,
+        "current_model": state.store.model()
     }))
 }
 
 /// Index an entire directory recursively
+///
+/// Queued behind file updates; a request for a directory that is already
+/// queued joins that job.
 #[tauri::command]
 pub async fn indexer_index_directory(
     state: State<'_, IndexerState>,
+    db: State<'_, Database>,
     request: IndexDirectoryRequest,
+    on_event: Channel<IndexEvent>,
 ) -> Result<IndexResult, String> {
-    let base_path = Path::new(&request.directory_path);
+    authorize(&db, request.agent_hex_id.as_deref(), &request.filesystem_hex_id, Permission::Execute)?;
 
-    if !base_path.exists() {
+    let (base_path, directory_path) =
+        sandboxed_path(&db, &request.filesystem_hex_id, &request.directory_path)?;
+
+    if !directory_path.exists() {
         return Err(format!("Directory does not exist: {}", request.directory_path));
     }
 
-    if !base_path.is_dir() {
+    if !directory_path.is_dir() {
         return Err(format!("Path is not a directory: {}", request.directory_path));
     }
 
-    let mut files_processed = 0;
-    let mut chunks_indexed = 0;
-
-    // Walk the directory recursively
-    for entry in WalkDir::new(base_path)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| {
-            let name = e.file_name().to_string_lossy();
-            // Skip ignored directories
-            !state.config.ignore_dirs.iter().any(|d| name == *d)
-        })
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-
-        // Skip directories
-        if path.is_dir() {
-            continue;
-        }
-
-        // Check extension
-        let extension = path.extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("");
+    let kind = JobKind::IndexDirectory {
+        base_path,
+        directory_path,
+    };
+    let listener: Listener = Box::new(move |event| {
+        let _ = on_event.send(event);
+    });
+    let submitted = state
+        .jobs
+        .submit(&request.filesystem_hex_id, kind, request.job_id, Some(listener));
+
+    match submitted.wait().await? {
+        JobOutput::Directory(result) => Ok(result),
+        JobOutput::Chunks(_) => Err("Unexpected file result for a directory job".to_string()),
+    }
+}
 
-        if !state.config.extensions.is_empty()
-            && !state.config.extensions.iter().any(|ext| ext == extension)
-        {
-            continue;
-        }
+/// Cancel a queued or running indexing job
+///
+/// Queued jobs are dropped; a running directory job stops before its next
+/// file, and files already indexed stay indexed.
+#[tauri::command]
+pub fn indexer_cancel(
+    state: State<'_, IndexerState>,
+    db: State<'_, Database>,
+    job_id: String,
+    agent_hex_id: Option<String>,
+) -> Result<bool, String> {
+    let Some(job) = state.jobs.list().into_iter().find(|job| job.job_id == job_id) else {
+        return Ok(false);
+    };
+    authorize(&db, agent_hex_id.as_deref(), &job.filesystem_hex_id, Permission::Execute)?;
+    Ok(state.jobs.cancel(&job_id))
+}
 
-        // Get relative path from base
-        let relative_path = path.strip_prefix(base_path)
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|_| path.to_string_lossy().to_string());
-
-        // Read and index the file
-        match fs::read_to_string(path) {
-            Ok(content) => {
-                let file_chunks = chunker::chunk_file(
-                    &request.filesystem_hex_id,
-                    &relative_path,
-                    &content,
-                    &state.config,
-                );
-
-                if file_chunks.is_empty() {
-                    continue;
-                }
-
-                // Generate embeddings
-                let texts: Vec<String> = file_chunks.iter().map(|c| c.content.clone()).collect();
-                match state.embedder.embed(texts) {
-                    Ok(embeddings) => {
-                        // Remove old chunks for this file first
-                        let _ = state.store.remove_file(&request.filesystem_hex_id, &relative_path);
-
-                        // Store new chunks with embeddings
-                        for (chunk, embedding) in file_chunks.iter().zip(embeddings.iter()) {
-                            if let Err(e) = state.store.insert(chunk, embedding) {
-                                eprintln!("Failed to insert chunk: {}", e);
-                            }
-                        }
-
-                        chunks_indexed += file_chunks.len();
-                        files_processed += 1;
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to embed file {}: {}", relative_path, e);
-                    }
-                }
-            }
-            Err(e) => {
-                // Skip binary files or files that can't be read as text
-                if e.kind() != std::io::ErrorKind::InvalidData {
-                    eprintln!("Failed to read file {}: {}", relative_path, e);
-                }
-            }
-        }
+/// List the running indexing job and queued jobs in the order they will run
+///
+/// An agent only sees jobs for filesystem hexes it may read.
+#[tauri::command]
+pub fn indexer_list_jobs(
+    state: State<'_, IndexerState>,
+    db: State<'_, Database>,
+    agent_hex_id: Option<String>,
+) -> Vec<JobInfo> {
+    let mut jobs = state.jobs.list();
+    if agent_hex_id.is_some() {
+        jobs.retain(|job| {
+            authorize(&db, agent_hex_id.as_deref(), &job.filesystem_hex_id, Permission::Read).is_ok()
+        });
     }
-
-    Ok(IndexResult {
-        chunks_indexed,
-        files_processed,
-    })
+    jobs
 }
 
 /// Clear all indexed data for a filesystem hex
+///
+/// Cancels the hex's pending jobs first so nothing is re-added afterwards.
 #[tauri::command]
 pub async fn indexer_clear_filesystem(
     state: State<'_, IndexerState>,
+    db: State<'_, Database>,
     filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
 ) -> Result<usize, String> {
-    state.store
-        .clear_filesystem(&filesystem_hex_id)
-        .map_err(|e| e.to_string())
+    authorize(&db, agent_hex_id.as_deref(), &filesystem_hex_id, Permission::Execute)?;
+    state.jobs.cancel_filesystem(&filesystem_hex_id);
+    queue_and_wait(&state, &filesystem_hex_id, JobKind::ClearFilesystem).await
 }
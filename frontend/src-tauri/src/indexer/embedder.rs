@@ -1,13 +1,21 @@
 //! Embedding generation using fastembed
 //!
 //! Provides local embedding generation using the fastembed crate with ONNX Runtime.
+//! This is the default `EmbeddingProvider`.
 
-use fastembed::{EmbeddingModel, TextInitOptions, TextEmbedding};
-use std::path::PathBuf;
+use super::provider::{read_setting, EmbeddingProvider};
+use crate::database::Database;
+use fastembed::{
+    EmbeddingModel, InitOptionsUserDefined, Pooling, TextEmbedding, TextInitOptions, TokenizerFiles,
+    UserDefinedEmbeddingModel,
+};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use thiserror::Error;
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone)]
 pub enum EmbedderError {
     #[error("Failed to initialize embedding model: {0}")]
     InitError(String),
@@ -15,43 +23,199 @@ pub enum EmbedderError {
     EmbedError(String),
     #[error("Model not initialized")]
     NotInitialized,
+    #[error("No embedding model available: {0}")]
+    ModelNotFound(String),
 }
 
-/// Wrapper around fastembed's TextEmbedding model
+/// Settings key naming the embedding model, read at startup
+pub const MODEL_SETTING: &str = "indexer:embedding-model";
+/// Settings key for a local directory holding a user-defined ONNX model;
+/// takes precedence over `MODEL_SETTING`
+pub const MODEL_PATH_SETTING: &str = "indexer:model-path";
+/// Settings key for the local model's pooling, `mean` (default) or `cls`
+pub const MODEL_POOLING_SETTING: &str = "indexer:model-pooling";
+/// Settings key for the local model's dimension, if its `config.json` has no `hidden_size`
+pub const MODEL_DIM_SETTING: &str = "indexer:model-dim";
+
+/// A fastembed model the indexer can be configured with by name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupportedModel {
+    /// Name used in settings
+    pub name: &'static str,
+    pub model: EmbeddingModel,
+    pub dim: usize,
+}
+
+/// Models selectable through `MODEL_SETTING`; the first is the default
+pub const SUPPORTED_MODELS: &[SupportedModel] = &[
+    SupportedModel {
+        name: "all-minilm-l6-v2",
+        model: EmbeddingModel::AllMiniLML6V2,
+        dim: 384,
+    },
+    SupportedModel {
+        name: "bge-small-en-v1.5",
+        model: EmbeddingModel::BGESmallENV15,
+        dim: 384,
+    },
+    SupportedModel {
+        name: "bge-base-en-v1.5",
+        model: EmbeddingModel::BGEBaseENV15,
+        dim: 768,
+    },
+    SupportedModel {
+        name: "nomic-embed-text-v1.5",
+        model: EmbeddingModel::NomicEmbedTextV15,
+        dim: 768,
+    },
+    SupportedModel {
+        name: "jina-embeddings-v2-base-code",
+        model: EmbeddingModel::JinaEmbeddingsV2BaseCode,
+        dim: 768,
+    },
+];
+
+/// Files a local model directory must contain, as exported for Hugging Face
+const LOCAL_MODEL_FILES: [&str; 5] = [
+    "model.onnx",
+    "tokenizer.json",
+    "config.json",
+    "special_tokens_map.json",
+    "tokenizer_config.json",
+];
+
+/// Where an embedding model's files come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModelSource {
+    /// A fastembed model, downloaded to the cache on first use
+    Builtin(EmbeddingModel),
+    /// An ONNX model and tokenizer files in a local directory, never downloaded
+    Local { dir: PathBuf, pooling: Pooling },
+}
+
+/// The embedding model an `Embedder` loads
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelSpec {
+    /// Name recorded with each filesystem's index
+    pub name: String,
+    pub source: ModelSource,
+    pub dim: usize,
+}
+
+impl ModelSpec {
+    /// Look up a supported model by its settings name
+    pub fn from_name(name: &str) -> Option<Self> {
+        SUPPORTED_MODELS.iter().find(|m| m.name == name).map(|m| Self {
+            name: m.name.to_string(),
+            source: ModelSource::Builtin(m.model.clone()),
+            dim: m.dim,
+        })
+    }
+
+    /// A user-defined model in `dir`, with its dimension read from the
+    /// directory's `config.json` unless given
+    pub fn local(dir: PathBuf, pooling: Pooling, dim: Option<usize>) -> Result<Self, EmbedderError> {
+        if !dir.is_dir() {
+            return Err(EmbedderError::ModelNotFound(format!("{} is not a directory", dir.display())));
+        }
+        let dim = match dim {
+            Some(dim) => dim,
+            None => read_hidden_size(&dir)?,
+        };
+        Ok(Self {
+            name: format!("local:{}", dir.display()),
+            source: ModelSource::Local { dir, pooling },
+            dim,
+        })
+    }
+
+    /// The model configured in settings: the local model if a path is set,
+    /// otherwise the named model, or the default if neither is set
+    ///
+    /// A local model that can't be found or an unknown model name fails with
+    /// `ModelNotFound` rather than quietly loading another model.
+    pub fn from_settings(db: &Database) -> Result<Self, EmbedderError> {
+        if let Some(spec) = Self::local_from_settings(db)? {
+            return Ok(spec);
+        }
+
+        let Some(name) = read_setting(db, MODEL_SETTING) else {
+            return Ok(Self::default());
+        };
+        Self::from_name(&name).ok_or_else(|| {
+            let names: Vec<&str> = SUPPORTED_MODELS.iter().map(|m| m.name).collect();
+            EmbedderError::ModelNotFound(format!(
+                "Unknown embedding model '{}' in {}; expected one of {}",
+                name,
+                MODEL_SETTING,
+                names.join(", ")
+            ))
+        })
+    }
+
+    fn local_from_settings(db: &Database) -> Result<Option<Self>, EmbedderError> {
+        let Some(dir) = read_setting(db, MODEL_PATH_SETTING).filter(|p| !p.trim().is_empty()) else {
+            return Ok(None);
+        };
+        let pooling = match read_setting(db, MODEL_POOLING_SETTING).as_deref() {
+            None | Some("mean") => Pooling::Mean,
+            Some("cls") => Pooling::Cls,
+            Some(other) => {
+                return Err(EmbedderError::InitError(format!(
+                    "Unknown pooling '{}', expected mean or cls",
+                    other
+                )))
+            }
+        };
+        let dim = match read_setting(db, MODEL_DIM_SETTING) {
+            Some(dim) => Some(dim.trim().parse().map_err(|_| {
+                EmbedderError::InitError(format!("{} must be a number, got '{}'", MODEL_DIM_SETTING, dim))
+            })?),
+            None => None,
+        };
+        Self::local(PathBuf::from(dir.trim()), pooling, dim).map(Some)
+    }
+}
+
+impl Default for ModelSpec {
+    fn default() -> Self {
+        Self::from_name(SUPPORTED_MODELS[0].name).unwrap()
+    }
+}
+
+/// Embedding provider running a fastembed TextEmbedding model in-process
 pub struct Embedder {
     model: Mutex<Option<TextEmbedding>>,
     cache_dir: Option<PathBuf>,
-    embedding_dim: usize,
+    spec: ModelSpec,
 }
 
 impl Embedder {
-    /// Create a new embedder (lazy initialization)
+    /// Create a new embedder for the default model (lazy initialization)
     pub fn new() -> Self {
         Self {
             model: Mutex::new(None),
             cache_dir: None,
-            embedding_dim: 384, // AllMiniLML6V2 dimension
+            spec: ModelSpec::default(),
         }
     }
 
     /// Create a new embedder with a custom cache directory for model persistence
     pub fn with_cache_dir(cache_dir: PathBuf) -> Self {
         Self {
-            model: Mutex::new(None),
             cache_dir: Some(cache_dir),
-            embedding_dim: 384, // AllMiniLML6V2 dimension
+            ..Self::new()
         }
     }
 
-    /// Initialize the embedding model
-    /// This downloads the model on first use (~80MB) to the cache directory
-    pub fn initialize(&self) -> Result<(), EmbedderError> {
-        let mut model_guard = self.model.lock().unwrap();
-        if model_guard.is_some() {
-            return Ok(());
-        }
+    /// Use `spec` instead of the default model
+    pub fn with_model(self, spec: ModelSpec) -> Self {
+        Self { spec, ..self }
+    }
 
-        let mut options = TextInitOptions::new(EmbeddingModel::AllMiniLML6V2)
+    /// Load a fastembed model from the cache, downloading it if needed
+    fn load_builtin(&self, model: &EmbeddingModel) -> Result<TextEmbedding, EmbedderError> {
+        let mut options = TextInitOptions::new(model.clone())
             .with_show_download_progress(true);
 
         // Set cache directory if provided for persistent model storage
@@ -59,25 +223,66 @@ impl Embedder {
             options = options.with_cache_dir(cache_dir.clone());
         }
 
-        let model = TextEmbedding::try_new(options)
-            .map_err(|e| EmbedderError::InitError(e.to_string()))?;
+        TextEmbedding::try_new(options).map_err(|e| {
+            if self.is_cached(model) {
+                EmbedderError::InitError(e.to_string())
+            } else {
+                EmbedderError::ModelNotFound(format!(
+                    "{} is not in the model cache and could not be downloaded ({}); set {} to load a model from a local directory",
+                    self.spec.name, e, MODEL_PATH_SETTING
+                ))
+            }
+        })
+    }
+
+    /// Whether a fastembed model has been downloaded to the cache directory
+    fn is_cached(&self, model: &EmbeddingModel) -> bool {
+        let Some(cache_dir) = &self.cache_dir else {
+            return false;
+        };
+        // hf-hub keeps each repo under `models--{owner}--{name}`
+        TextEmbedding::get_model_info(model)
+            .map(|info| {
+                cache_dir
+                    .join(format!("models--{}", info.model_code.replace('/', "--")))
+                    .is_dir()
+            })
+            .unwrap_or(false)
+    }
+}
+
+impl EmbeddingProvider for Embedder {
+    fn model_name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn embedding_dim(&self) -> usize {
+        self.spec.dim
+    }
+
+    /// Initialize the embedding model
+    /// Built-in models are downloaded on first use (~80MB for the default) to
+    /// the cache directory; local models are loaded from their directory
+    fn initialize(&self) -> Result<(), EmbedderError> {
+        let mut model_guard = self.model.lock().unwrap();
+        if model_guard.is_some() {
+            return Ok(());
+        }
+
+        let model = match &self.spec.source {
+            ModelSource::Builtin(model) => self.load_builtin(model)?,
+            ModelSource::Local { dir, pooling } => load_local(dir, pooling)?,
+        };
 
         *model_guard = Some(model);
         Ok(())
     }
 
-    /// Check if the model is initialized
-    pub fn is_initialized(&self) -> bool {
+    fn is_initialized(&self) -> bool {
         self.model.lock().unwrap().is_some()
     }
 
-    /// Get the embedding dimension
-    pub fn embedding_dim(&self) -> usize {
-        self.embedding_dim
-    }
-
-    /// Generate embeddings for a batch of texts
-    pub fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedderError> {
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedderError> {
         let mut model_guard = self.model.lock().unwrap();
         let model = model_guard
             .as_mut()
@@ -90,39 +295,6 @@ impl Embedder {
             .embed(text_refs, None)
             .map_err(|e| EmbedderError::EmbedError(e.to_string()))
     }
-
-    /// Generate embedding for a single text
-    pub fn embed_one(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
-        let embeddings = self.embed(vec![text.to_string()])?;
-        embeddings
-            .into_iter()
-            .next()
-            .ok_or_else(|| EmbedderError::EmbedError("No embedding returned".to_string()))
-    }
-
-    /// Generate embeddings with progress callback
-    pub fn embed_with_progress<F>(
-        &self,
-        texts: Vec<String>,
-        batch_size: usize,
-        mut on_progress: F,
-    ) -> Result<Vec<Vec<f32>>, EmbedderError>
-    where
-        F: FnMut(usize, usize),
-    {
-        let total = texts.len();
-        let mut all_embeddings = Vec::with_capacity(total);
-
-        for (batch_idx, batch) in texts.chunks(batch_size).enumerate() {
-            let batch_embeddings = self.embed(batch.to_vec())?;
-            all_embeddings.extend(batch_embeddings);
-            
-            let processed = ((batch_idx + 1) * batch_size).min(total);
-            on_progress(processed, total);
-        }
-
-        Ok(all_embeddings)
-    }
 }
 
 impl Default for Embedder {
@@ -131,15 +303,125 @@ impl Default for Embedder {
     }
 }
 
+/// Load a user-defined model from the files in `dir`
+fn load_local(dir: &Path, pooling: &Pooling) -> Result<TextEmbedding, EmbedderError> {
+    let [onnx, tokenizer, config, special_tokens_map, tokenizer_config] = LOCAL_MODEL_FILES;
+    let tokenizer_files = TokenizerFiles {
+        tokenizer_file: read_model_file(dir, tokenizer)?,
+        config_file: read_model_file(dir, config)?,
+        special_tokens_map_file: read_model_file(dir, special_tokens_map)?,
+        tokenizer_config_file: read_model_file(dir, tokenizer_config)?,
+    };
+    let model = UserDefinedEmbeddingModel::new(read_model_file(dir, onnx)?, tokenizer_files)
+        .with_pooling(pooling.clone());
+
+    TextEmbedding::try_new_from_user_defined(model, InitOptionsUserDefined::new())
+        .map_err(|e| EmbedderError::InitError(e.to_string()))
+}
+
+/// Read one of a local model's files, reporting a missing file as `ModelNotFound`
+fn read_model_file(dir: &Path, name: &str) -> Result<Vec<u8>, EmbedderError> {
+    let path = dir.join(name);
+    fs::read(&path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => EmbedderError::ModelNotFound(format!("{} does not exist", path.display())),
+        _ => EmbedderError::InitError(format!("Failed to read {}: {}", path.display(), e)),
+    })
+}
+
+/// Read the embedding dimension from a local model's `config.json`
+fn read_hidden_size(dir: &Path) -> Result<usize, EmbedderError> {
+    let config: serde_json::Value = serde_json::from_slice(&read_model_file(dir, "config.json")?)
+        .map_err(|e| EmbedderError::InitError(format!("Invalid config.json in {}: {}", dir.display(), e)))?;
+    config["hidden_size"]
+        .as_u64()
+        .map(|dim| dim as usize)
+        .ok_or_else(|| {
+            EmbedderError::InitError(format!(
+                "config.json in {} has no hidden_size; set {}",
+                dir.display(),
+                MODEL_DIM_SETTING
+            ))
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_embedder_creation() {
         let embedder = Embedder::new();
         assert!(!embedder.is_initialized());
         assert_eq!(embedder.embedding_dim(), 384);
+        assert_eq!(embedder.model_name(), "all-minilm-l6-v2");
     }
-}
 
+    #[test]
+    fn test_model_from_name() {
+        let spec = ModelSpec::from_name("jina-embeddings-v2-base-code").unwrap();
+        assert_eq!(spec.source, ModelSource::Builtin(EmbeddingModel::JinaEmbeddingsV2BaseCode));
+
+        let embedder = Embedder::new().with_model(spec);
+        assert_eq!(embedder.model_name(), "jina-embeddings-v2-base-code");
+        assert_eq!(embedder.embedding_dim(), 768);
+
+        assert!(ModelSpec::from_name("all-MiniLM-L6-v2").is_none());
+    }
+
+    fn fixture_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tiny-embedding-model")
+    }
+
+    #[test]
+    fn test_local_model_embeds_offline() {
+        let spec = ModelSpec::local(fixture_dir(), Pooling::Mean, None).unwrap();
+        assert_eq!(spec.dim, 8);
+        assert!(spec.name.starts_with("local:"));
+
+        let embedder = Embedder::new().with_model(spec);
+        embedder.initialize().unwrap();
+        let embeddings = embedder
+            .embed(vec!["fn main".to_string(), "struct value".to_string()])
+            .unwrap();
+        assert_eq!(embeddings.len(), 2);
+        assert!(embeddings.iter().all(|e| e.len() == embedder.embedding_dim()));
+        assert_ne!(embeddings[0], embeddings[1]);
+    }
+
+    #[test]
+    fn test_model_from_settings() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_path_buf()).unwrap();
+        assert_eq!(ModelSpec::from_settings(&db).unwrap(), ModelSpec::default());
+
+        db.set_setting(MODEL_SETTING, "bge-small-en-v1.5").unwrap();
+        assert_eq!(ModelSpec::from_settings(&db).unwrap().name, "bge-small-en-v1.5");
+
+        // A typo is reported instead of loading the default model
+        db.set_setting(MODEL_SETTING, "bge-smal-en").unwrap();
+        assert!(matches!(ModelSpec::from_settings(&db), Err(EmbedderError::ModelNotFound(_))));
+
+        db.set_setting(MODEL_PATH_SETTING, &dir.path().join("missing").to_string_lossy()).unwrap();
+        db.set_setting(MODEL_DIM_SETTING, "8").unwrap();
+        assert!(matches!(ModelSpec::from_settings(&db), Err(EmbedderError::ModelNotFound(_))));
+
+        db.set_setting(MODEL_PATH_SETTING, &fixture_dir().to_string_lossy()).unwrap();
+        assert!(ModelSpec::from_settings(&db).unwrap().name.starts_with("local:"));
+    }
+
+    #[test]
+    fn test_missing_local_model() {
+        let empty = TempDir::new().unwrap();
+
+        // No config.json to read the dimension from
+        assert!(matches!(
+            ModelSpec::local(empty.path().to_path_buf(), Pooling::Mean, None),
+            Err(EmbedderError::ModelNotFound(_))
+        ));
+
+        let embedder = Embedder::new().with_model(ModelSpec::local(empty.path().to_path_buf(), Pooling::Cls, Some(8)).unwrap());
+        assert!(matches!(embedder.initialize(), Err(EmbedderError::ModelNotFound(_))));
+        assert!(!embedder.is_initialized());
+    }
+}
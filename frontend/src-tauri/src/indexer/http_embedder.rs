@@ -0,0 +1,268 @@
+//! Embedding generation over HTTP
+//!
+//! Calls an OpenAI-compatible `/embeddings` endpoint, such as a local Ollama
+//! or llama.cpp server, or a hosted embedding API.
+
+use super::embedder::EmbedderError;
+use super::provider::{read_setting, EmbeddingProvider};
+use crate::database::Database;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use ureq::Agent;
+
+/// Settings key for the server's base URL; `/embeddings` is appended
+pub const ENDPOINT_SETTING: &str = "indexer:http-endpoint";
+/// Settings key for the model name sent with each request
+pub const MODEL_SETTING: &str = "indexer:http-model";
+/// Settings key for the model's embedding dimension
+pub const DIM_SETTING: &str = "indexer:http-dim";
+/// Settings key for an optional bearer token
+pub const API_KEY_SETTING: &str = "indexer:http-api-key";
+
+/// Ollama's OpenAI-compatible API
+const DEFAULT_ENDPOINT: &str = "http://localhost:11434/v1";
+/// Time allowed for one batch, which can be slow on a CPU-only server
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: usize,
+}
+
+/// Embedding provider backed by an OpenAI-compatible embedding server
+pub struct HttpEmbedder {
+    agent: Agent,
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+    /// Recorded with each filesystem's index
+    name: String,
+    dim: usize,
+    /// Set once the server has answered with embeddings of the expected size
+    ready: AtomicBool,
+}
+
+impl HttpEmbedder {
+    /// Create an embedder for `model` served at `endpoint`, returning `dim`-sized embeddings
+    pub fn new(endpoint: &str, model: &str, dim: usize) -> Self {
+        let agent: Agent = Agent::config_builder()
+            .timeout_global(Some(REQUEST_TIMEOUT))
+            .build()
+            .into();
+        Self {
+            agent,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key: None,
+            name: format!("http:{}", model),
+            dim,
+            ready: AtomicBool::new(false),
+        }
+    }
+
+    /// Send `api_key` as a bearer token
+    pub fn with_api_key(self, api_key: String) -> Self {
+        Self {
+            api_key: Some(api_key),
+            ..self
+        }
+    }
+
+    /// The embedder configured in settings; the model and dimension are required
+    pub fn from_settings(db: &Database) -> Result<Self, EmbedderError> {
+        let endpoint = read_setting(db, ENDPOINT_SETTING).unwrap_or_else(|| DEFAULT_ENDPOINT.to_string());
+        let model = read_setting(db, MODEL_SETTING)
+            .ok_or_else(|| EmbedderError::InitError(format!("{} is not set", MODEL_SETTING)))?;
+        let dim = read_setting(db, DIM_SETTING)
+            .ok_or_else(|| EmbedderError::InitError(format!("{} is not set", DIM_SETTING)))?;
+        let dim = dim.trim().parse().map_err(|_| {
+            EmbedderError::InitError(format!("{} must be a number, got '{}'", DIM_SETTING, dim))
+        })?;
+
+        let embedder = Self::new(&endpoint, &model, dim);
+        Ok(match read_setting(db, API_KEY_SETTING).filter(|k| !k.is_empty()) {
+            Some(api_key) => embedder.with_api_key(api_key),
+            None => embedder,
+        })
+    }
+
+    /// POST a batch to the server and check the embeddings it returns
+    fn request(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, EmbedderError> {
+        let url = format!("{}/embeddings", self.endpoint);
+        let mut request = self.agent.post(&url);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", &format!("Bearer {}", api_key));
+        }
+
+        let body = EmbeddingRequest {
+            model: &self.model,
+            input: texts,
+        };
+        let mut response: EmbeddingResponse = request
+            .send_json(&body)
+            .and_then(|mut r| r.body_mut().read_json())
+            .map_err(|e| EmbedderError::EmbedError(format!("{}: {}", url, e)))?;
+
+        if response.data.len() != texts.len() {
+            return Err(EmbedderError::EmbedError(format!(
+                "{} returned {} embeddings for {} inputs",
+                url,
+                response.data.len(),
+                texts.len()
+            )));
+        }
+        response.data.sort_by_key(|d| d.index);
+
+        let embeddings: Vec<Vec<f32>> = response.data.into_iter().map(|d| d.embedding).collect();
+        if let Some(wrong) = embeddings.iter().find(|e| e.len() != self.dim) {
+            return Err(EmbedderError::EmbedError(format!(
+                "{} returned {}-dimensional embeddings for {}, expected {}",
+                url,
+                wrong.len(),
+                self.model,
+                self.dim
+            )));
+        }
+
+        Ok(embeddings)
+    }
+}
+
+impl EmbeddingProvider for HttpEmbedder {
+    fn model_name(&self) -> &str {
+        &self.name
+    }
+
+    fn embedding_dim(&self) -> usize {
+        self.dim
+    }
+
+    /// Check that the server is reachable and returns embeddings of the configured size
+    fn initialize(&self) -> Result<(), EmbedderError> {
+        if self.is_initialized() {
+            return Ok(());
+        }
+
+        self.request(&["initialize".to_string()])
+            .map_err(|e| EmbedderError::InitError(e.to_string()))?;
+        self.ready.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_initialized(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedderError> {
+        if !self.is_initialized() {
+            return Err(EmbedderError::NotInitialized);
+        }
+        self.request(&texts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::{self, JoinHandle};
+
+    /// Serve `requests` embedding requests, answering with `dim`-sized
+    /// embeddings in reverse order; returns the server's base URL and the
+    /// head and body of each request it received
+    fn serve(requests: usize, dim: usize) -> (String, JoinHandle<Vec<(String, serde_json::Value)>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}/v1/", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut received = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                    head.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let inputs = request["input"].as_array().unwrap();
+                let data: Vec<serde_json::Value> = inputs
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(i, _)| serde_json::json!({ "index": i, "embedding": vec![i as f32; dim] }))
+                    .collect();
+                let response = serde_json::json!({ "object": "list", "data": data }).to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.len(),
+                    response
+                )
+                .unwrap();
+
+                received.push((head, request));
+            }
+            received
+        });
+
+        (endpoint, handle)
+    }
+
+    #[test]
+    fn test_http_embedder_requests_embeddings() {
+        let (endpoint, server) = serve(2, 4);
+        let embedder = HttpEmbedder::new(&endpoint, "nomic-embed-text", 4).with_api_key("secret".to_string());
+        assert_eq!(embedder.model_name(), "http:nomic-embed-text");
+        assert!(matches!(embedder.embed(vec!["x".to_string()]), Err(EmbedderError::NotInitialized)));
+
+        embedder.initialize().unwrap();
+        let embeddings = embedder
+            .embed(vec!["fn a() {}".to_string(), "fn b() {}".to_string(), "fn c() {}".to_string()])
+            .unwrap();
+        // Reordered by index
+        assert_eq!(embeddings, vec![vec![0.0; 4], vec![1.0; 4], vec![2.0; 4]]);
+
+        let received = server.join().unwrap();
+        let (head, body) = &received[1];
+        assert!(head.starts_with("POST /v1/embeddings "));
+        assert!(head.to_ascii_lowercase().contains("authorization: bearer secret"));
+        assert_eq!(body["model"], "nomic-embed-text");
+        assert_eq!(body["input"], serde_json::json!(["fn a() {}", "fn b() {}", "fn c() {}"]));
+    }
+
+    #[test]
+    fn test_http_embedder_rejects_wrong_dimension() {
+        let (endpoint, server) = serve(1, 3);
+        let embedder = HttpEmbedder::new(&endpoint, "all-minilm", 384);
+        assert!(matches!(embedder.initialize(), Err(EmbedderError::InitError(_))));
+        assert!(!embedder.is_initialized());
+        server.join().unwrap();
+    }
+}
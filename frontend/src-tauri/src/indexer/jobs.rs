@@ -0,0 +1,485 @@
+//! Indexing job queue
+//!
+//! Every index update runs as a job on a single worker thread, so embedding
+//! and SQLite work stay off the async runtime and concurrent requests (two
+//! boards starting, a watcher burst) are serialized instead of racing. File
+//! updates run before full rescans, and a request for a file that is already
+//! queued joins the queued job instead of adding another.
+
+use super::commands::IndexResult;
+use serde::Serialize;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// Event sent while a directory is being indexed
+#[derive(Debug, Clone, Serialize)]
+pub enum IndexEvent {
+    /// The job was accepted; its id can be passed to `indexer_cancel`
+    Queued { job_id: String },
+    /// The worker picked the job up
+    Started { job_id: String },
+    /// The walk finished and found this many indexable files
+    Discovered { files: usize },
+    /// Sent when a file starts and as its chunks are embedded
+    Progress {
+        files_done: usize,
+        files_total: usize,
+        chunks_embedded: usize,
+        current_file: Option<String>,
+    },
+    /// A file could not be read, embedded or stored; indexing continues
+    FileError { path: String, error: String },
+    /// The job stopped early after `indexer_cancel`
+    Cancelled,
+}
+
+/// What an indexing job does
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobKind {
+    /// `file_path` is relative to `base_path`, the filesystem hex's root
+    IndexFile { base_path: PathBuf, file_path: String },
+    RemoveFile { file_path: String },
+    /// `directory_path` is a directory under `base_path`, the filesystem hex's root
+    IndexDirectory { base_path: PathBuf, directory_path: PathBuf },
+    ClearFilesystem,
+}
+
+impl JobKind {
+    pub fn priority(&self) -> JobPriority {
+        match self {
+            JobKind::IndexDirectory { .. } => JobPriority::Rescan,
+            _ => JobPriority::Update,
+        }
+    }
+
+    /// Queued jobs for the same filesystem hex and target are merged
+    fn target(&self) -> (&'static str, Option<&str>) {
+        match self {
+            JobKind::IndexFile { file_path, .. } | JobKind::RemoveFile { file_path } => {
+                ("file", Some(file_path))
+            }
+            JobKind::IndexDirectory { directory_path, .. } => ("directory", directory_path.to_str()),
+            JobKind::ClearFilesystem => ("clear", None),
+        }
+    }
+}
+
+/// Jobs run highest priority first, in submission order within a priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobPriority {
+    Rescan,
+    Update,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+}
+
+/// A job as reported by `indexer_list_jobs`
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub job_id: String,
+    pub filesystem_hex_id: String,
+    #[serde(flatten)]
+    pub kind: JobKind,
+    pub priority: JobPriority,
+    pub status: JobStatus,
+}
+
+/// What a finished job produced
+#[derive(Debug, Clone)]
+pub enum JobOutput {
+    /// Chunks written (or, for removals, deleted)
+    Chunks(usize),
+    Directory(IndexResult),
+}
+
+pub type Listener = Box<dyn Fn(IndexEvent) + Send>;
+
+/// A job taken off the queue by the worker
+pub struct Job {
+    pub id: String,
+    pub filesystem_hex_id: String,
+    pub kind: JobKind,
+    /// Set by `indexer_cancel` while the job runs
+    pub cancelled: Arc<AtomicBool>,
+    seq: u64,
+    listeners: Vec<Listener>,
+    waiters: Vec<oneshot::Sender<Result<JobOutput, String>>>,
+}
+
+impl Job {
+    /// Send an event to everyone following the job
+    pub fn emit(&self, event: IndexEvent) {
+        for listener in &self.listeners {
+            listener(event.clone());
+        }
+    }
+
+    fn info(&self, status: JobStatus) -> JobInfo {
+        JobInfo {
+            job_id: self.id.clone(),
+            filesystem_hex_id: self.filesystem_hex_id.clone(),
+            kind: self.kind.clone(),
+            priority: self.kind.priority(),
+            status,
+        }
+    }
+
+    fn complete(self, result: Result<JobOutput, String>) {
+        for waiter in self.waiters {
+            let _ = waiter.send(result.clone());
+        }
+    }
+
+    /// Finish a job that was cancelled before it ran
+    fn complete_cancelled(self) {
+        if let JobKind::IndexDirectory { .. } = self.kind {
+            self.emit(IndexEvent::Cancelled);
+            let result = IndexResult {
+                cancelled: true,
+                ..Default::default()
+            };
+            self.complete(Ok(JobOutput::Directory(result)));
+        } else {
+            let message = format!("Indexing job cancelled: {}", self.id);
+            self.complete(Err(message));
+        }
+    }
+}
+
+/// A submitted job's id and a handle on its result
+pub struct Submitted {
+    pub job_id: String,
+    result: oneshot::Receiver<Result<JobOutput, String>>,
+}
+
+impl Submitted {
+    /// Wait for the job to finish
+    pub async fn wait(self) -> Result<JobOutput, String> {
+        self.result
+            .await
+            .map_err(|_| format!("Indexing worker stopped before job {} finished", self.job_id))?
+    }
+}
+
+#[derive(Default)]
+struct JobQueue {
+    queued: Vec<Job>,
+    running: Option<(JobInfo, Arc<AtomicBool>)>,
+    next_seq: u64,
+}
+
+/// Queue of indexing jobs managed by `IndexerState`
+#[derive(Default)]
+pub struct IndexJobs {
+    queue: Mutex<JobQueue>,
+    ready: Condvar,
+}
+
+impl IndexJobs {
+    /// Queue a job, or join a queued job with the same target
+    ///
+    /// `job_id` is used for a new job (one is generated if not given); when the
+    /// request joins a queued job, the returned id is that job's. `listener`
+    /// gets `Queued` before the worker can pick the job up, so it always
+    /// comes first.
+    pub fn submit(
+        &self,
+        filesystem_hex_id: &str,
+        kind: JobKind,
+        job_id: Option<String>,
+        listener: Option<Listener>,
+    ) -> Submitted {
+        let (sender, receiver) = oneshot::channel();
+        let mut queue = self.queue.lock().unwrap();
+
+        let existing = queue.queued.iter_mut().find(|job| {
+            job.filesystem_hex_id == filesystem_hex_id && job.kind.target() == kind.target()
+        });
+        let job_id = match existing {
+            Some(job) => {
+                if let Some(listener) = &listener {
+                    listener(IndexEvent::Queued { job_id: job.id.clone() });
+                }
+                // The latest request for a file wins (e.g. a write after a delete)
+                job.kind = kind;
+                job.listeners.extend(listener);
+                job.waiters.push(sender);
+                job.id.clone()
+            }
+            None => {
+                let id = job_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+                if let Some(listener) = &listener {
+                    listener(IndexEvent::Queued { job_id: id.clone() });
+                }
+                let seq = queue.next_seq;
+                queue.next_seq += 1;
+                queue.queued.push(Job {
+                    id: id.clone(),
+                    filesystem_hex_id: filesystem_hex_id.to_string(),
+                    kind,
+                    cancelled: Arc::new(AtomicBool::new(false)),
+                    seq,
+                    listeners: listener.into_iter().collect(),
+                    waiters: vec![sender],
+                });
+                self.ready.notify_one();
+                id
+            }
+        };
+
+        Submitted {
+            job_id,
+            result: receiver,
+        }
+    }
+
+    /// Block until a job is queued, then take the highest priority one
+    pub fn next(&self) -> Job {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            let next = queue
+                .queued
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, job)| (job.kind.priority(), std::cmp::Reverse(job.seq)))
+                .map(|(index, _)| index);
+            if let Some(index) = next {
+                let job = queue.queued.remove(index);
+                queue.running = Some((job.info(JobStatus::Running), job.cancelled.clone()));
+                return job;
+            }
+            queue = self.ready.wait(queue).unwrap();
+        }
+    }
+
+    /// Take the next job, run it and report its result
+    ///
+    /// A panicking job fails with an error instead of taking down the worker;
+    /// a directory job's listeners get a `FileError` for it, or `Cancelled`
+    /// if it was cancelled first.
+    pub fn run_next(&self, run: impl FnOnce(&Job) -> Result<JobOutput, String>) {
+        let job = self.next();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| run(&job))).unwrap_or_else(|payload| {
+            let error = format!("Indexing job {} panicked: {}", job.id, panic_message(payload.as_ref()));
+            eprintln!("[Indexer] {}", error);
+            if let JobKind::IndexDirectory { directory_path, .. } = &job.kind {
+                if job.cancelled.load(Ordering::SeqCst) {
+                    job.emit(IndexEvent::Cancelled);
+                } else {
+                    job.emit(IndexEvent::FileError {
+                        path: directory_path.to_string_lossy().to_string(),
+                        error: error.clone(),
+                    });
+                }
+            }
+            Err(error)
+        });
+        self.finish(job, result);
+    }
+
+    /// Report a job's result to everyone waiting on it
+    pub fn finish(&self, job: Job, result: Result<JobOutput, String>) {
+        self.queue.lock().unwrap().running = None;
+        job.complete(result);
+    }
+
+    /// Cancel a job: queued jobs are dropped, a running job stops before its
+    /// next file. Returns false if no such job is queued or running.
+    pub fn cancel(&self, job_id: &str) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(index) = queue.queued.iter().position(|job| job.id == job_id) {
+            let job = queue.queued.remove(index);
+            drop(queue);
+            job.complete_cancelled();
+            return true;
+        }
+        match &queue.running {
+            Some((info, cancelled)) if info.job_id == job_id => {
+                cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Cancel every queued and running job for a filesystem hex
+    pub fn cancel_filesystem(&self, filesystem_hex_id: &str) {
+        let job_ids: Vec<String> = self
+            .list()
+            .into_iter()
+            .filter(|job| job.filesystem_hex_id == filesystem_hex_id)
+            .map(|job| job.job_id)
+            .collect();
+        for job_id in job_ids {
+            self.cancel(&job_id);
+        }
+    }
+
+    /// The running job, then queued jobs in the order they will run
+    pub fn list(&self) -> Vec<JobInfo> {
+        let queue = self.queue.lock().unwrap();
+        let mut queued: Vec<&Job> = queue.queued.iter().collect();
+        queued.sort_by_key(|job| (std::cmp::Reverse(job.kind.priority()), job.seq));
+
+        queue
+            .running
+            .iter()
+            .map(|(info, _)| info.clone())
+            .chain(queued.into_iter().map(|job| job.info(JobStatus::Queued)))
+            .collect()
+    }
+}
+
+/// The message a panic was raised with, if it has one
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_file(file_path: &str) -> JobKind {
+        JobKind::IndexFile {
+            base_path: PathBuf::from("/repo"),
+            file_path: file_path.to_string(),
+        }
+    }
+
+    fn rescan() -> JobKind {
+        JobKind::IndexDirectory {
+            base_path: PathBuf::from("/repo"),
+            directory_path: PathBuf::from("/repo"),
+        }
+    }
+
+    #[test]
+    fn test_file_updates_run_before_rescans() {
+        let jobs = IndexJobs::default();
+        jobs.submit("fs", rescan(), None, None);
+        jobs.submit("fs", index_file("a.rs"), None, None);
+        jobs.submit("fs", index_file("b.rs"), None, None);
+
+        let order: Vec<JobKind> = jobs.list().into_iter().map(|job| job.kind).collect();
+        assert_eq!(order, vec![index_file("a.rs"), index_file("b.rs"), rescan()]);
+
+        let first = jobs.next();
+        assert_eq!(first.kind, index_file("a.rs"));
+        assert_eq!(jobs.list()[0].status, JobStatus::Running);
+    }
+
+    #[test]
+    fn test_repeated_requests_join_queued_job() {
+        let jobs = IndexJobs::default();
+        let first = jobs.submit("fs", index_file("a.rs"), None, None);
+        let removal = JobKind::RemoveFile {
+            file_path: "a.rs".to_string(),
+        };
+        let second = jobs.submit("fs", removal.clone(), None, None);
+        let other_hex = jobs.submit("fs2", index_file("a.rs"), None, None);
+
+        assert_eq!(first.job_id, second.job_id);
+        assert_ne!(first.job_id, other_hex.job_id);
+        assert_eq!(jobs.list().len(), 2);
+
+        let job = jobs.next();
+        assert_eq!(job.kind, removal);
+        jobs.finish(job, Ok(JobOutput::Chunks(3)));
+
+        for submitted in [first, second] {
+            let output = submitted.result.blocking_recv().unwrap().unwrap();
+            assert!(matches!(output, JobOutput::Chunks(3)));
+        }
+    }
+
+    #[test]
+    fn test_queued_event_comes_first() {
+        let jobs = IndexJobs::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let listener = |events: &Arc<Mutex<Vec<IndexEvent>>>| -> Option<Listener> {
+            let events = events.clone();
+            Some(Box::new(move |event| events.lock().unwrap().push(event)))
+        };
+        let first = jobs.submit("fs", rescan(), None, listener(&events));
+        let second = jobs.submit("fs", rescan(), None, listener(&events));
+
+        jobs.run_next(|job| {
+            job.emit(IndexEvent::Started { job_id: job.id.clone() });
+            Ok(JobOutput::Directory(IndexResult::default()))
+        });
+        assert!(first.result.blocking_recv().unwrap().is_ok());
+        assert!(second.result.blocking_recv().unwrap().is_ok());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], IndexEvent::Queued { job_id } if *job_id == first.job_id));
+        assert!(matches!(&events[1], IndexEvent::Queued { job_id } if *job_id == first.job_id));
+        assert!(matches!(events[2], IndexEvent::Started { .. }));
+    }
+
+    #[test]
+    fn test_panicking_job_fails_and_worker_continues() {
+        let jobs = IndexJobs::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let listener: Listener = Box::new(move |event| recorded.lock().unwrap().push(event));
+        let panicked = jobs.submit("fs", rescan(), None, Some(listener));
+        let next = jobs.submit("fs2", index_file("a.rs"), None, None);
+
+        // The file job runs first; the rescan panics afterwards
+        jobs.run_next(|_| Ok(JobOutput::Chunks(1)));
+        jobs.run_next(|_| panic!("embedder exploded"));
+
+        let error = panicked.result.blocking_recv().unwrap().unwrap_err();
+        assert!(error.contains("embedder exploded"));
+        assert!(matches!(events.lock().unwrap().last(), Some(IndexEvent::FileError { .. })));
+        assert!(next.result.blocking_recv().unwrap().is_ok());
+        assert!(jobs.list().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_queued_and_running_jobs() {
+        let jobs = IndexJobs::default();
+        let running = jobs.submit("fs", rescan(), Some("job-1".to_string()), None);
+        let queued = jobs.submit("fs", index_file("a.rs"), None, None);
+
+        // The queued file job runs first
+        let file_job = jobs.next();
+        jobs.finish(file_job, Ok(JobOutput::Chunks(1)));
+        assert!(queued.result.blocking_recv().unwrap().is_ok());
+
+        let job = jobs.next();
+        assert_eq!(job.id, "job-1");
+        assert!(jobs.cancel("job-1"));
+        assert!(job.cancelled.load(Ordering::SeqCst));
+        jobs.finish(job, Ok(JobOutput::Directory(IndexResult::default())));
+        assert!(running.result.blocking_recv().unwrap().is_ok());
+        assert!(!jobs.cancel("job-1"));
+
+        let dropped = jobs.submit("fs", rescan(), None, None);
+        jobs.cancel_filesystem("fs");
+        assert!(jobs.list().is_empty());
+        match dropped.result.blocking_recv().unwrap().unwrap() {
+            JobOutput::Directory(result) => assert!(result.cancelled),
+            other => panic!("unexpected output: {:?}", other),
+        }
+    }
+}
@@ -6,6 +6,10 @@
 pub mod chunker;
 pub mod commands;
 pub mod embedder;
+pub mod http_embedder;
+pub mod jobs;
+pub mod pipeline;
+pub mod provider;
 pub mod store;
 pub mod tree_sitter_parser;
 
@@ -28,6 +32,16 @@ pub struct CodeChunk {
     pub content: String,
     /// Optional language hint (e.g., "rust", "typescript")
     pub language: Option<String>,
+    /// Kind of semantic unit (e.g., "function", "class"); `None` for line-based chunks
+    pub kind: Option<String>,
+    /// Name of the symbol the chunk declares (e.g., "add_board_usage")
+    pub symbol: Option<String>,
+    /// Tree-sitter node kind of the declaration (e.g., "function_item")
+    pub node_kind: Option<String>,
+    /// Symbol the declaration lives in (e.g., "Database" for a method)
+    pub parent_symbol: Option<String>,
+    /// The declaration line (e.g., "pub fn add_board_usage(&self, tokens: u64)")
+    pub signature: Option<String>,
 }
 
 /// A search result with similarity score
@@ -35,8 +49,12 @@ pub struct CodeChunk {
 pub struct SearchResult {
     /// The matching code chunk
     pub chunk: CodeChunk,
-    /// Cosine similarity distance (lower is more similar)
-    pub distance: f32,
+    /// Embedding distance to the query (lower is more similar); `None` for
+    /// keyword searches
+    pub distance: Option<f32>,
+    /// Reciprocal rank fusion score (higher is better), comparable only
+    /// within one search
+    pub score: f32,
 }
 
 /// Configuration for the indexer
@@ -71,6 +89,8 @@ impl Default for IndexerConfig {
                 "java".to_string(),
                 "c".to_string(),
                 "cpp".to_string(),
+                "cc".to_string(),
+                "cxx".to_string(),
                 "h".to_string(),
                 "hpp".to_string(),
                 "cs".to_string(),
@@ -0,0 +1,615 @@
+//! Directory indexing pipeline
+//!
+//! Files are read, hashed and chunked in parallel a window at a time. Their
+//! chunks are pooled across files, embedded in fixed-size batches, and
+//! written together with the records of the files they complete in one
+//! transaction per write batch. A file whose chunks span several batches
+//! and then fails or is cancelled has its partly written chunks removed.
+
+use super::chunker;
+use super::commands::{IndexResult, IndexerState};
+use super::jobs::IndexEvent;
+use super::store::{FileRecord, StoreBatch};
+use super::CodeChunk;
+use crate::files::write::hash_bytes;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+use walkdir::WalkDir;
+
+/// Files read and chunked in parallel before their chunks are queued
+const FILE_WINDOW: usize = 64;
+/// Chunks per embedding model call
+pub const EMBED_BATCH_SIZE: usize = 64;
+/// Chunks stored per transaction
+const WRITE_BATCH_CHUNKS: usize = 256;
+
+/// An indexable file found while walking a directory
+struct DiscoveredFile {
+    path: PathBuf,
+    relative_path: String,
+    modified_ms: Option<i64>,
+}
+
+/// A file after it has been read and chunked
+enum Prepared {
+    /// Mtime or hash matched the last index; carries the record to store if
+    /// only the mtime moved
+    Unchanged(Option<FileRecord>),
+    Changed {
+        chunks: Vec<CodeChunk>,
+        record: FileRecord,
+    },
+    /// Binary or not valid UTF-8
+    NotText,
+    Failed(String),
+}
+
+/// A changed file waiting for its chunks to be stored
+struct PendingFile {
+    record: FileRecord,
+    previously_indexed: bool,
+    /// Chunks not yet written
+    remaining: usize,
+    /// Some of its chunks were written by an earlier batch, replacing its old ones
+    partly_written: bool,
+}
+
+/// Chunks pooled across files, waiting to be embedded and stored
+#[derive(Default)]
+struct WriteQueue {
+    chunks: Vec<CodeChunk>,
+    /// Files whose old chunks must be deleted before their new ones land
+    replace_files: Vec<String>,
+    /// Records of unchanged files whose mtime moved
+    refreshed: Vec<(String, FileRecord)>,
+    pending: HashMap<String, PendingFile>,
+}
+
+/// State for one `index_directory` run
+struct DirectoryRun<'a, F: Fn(IndexEvent)> {
+    state: &'a IndexerState,
+    filesystem_hex_id: &'a str,
+    on_event: F,
+    result: IndexResult,
+    files_total: usize,
+    files_done: usize,
+    current_file: Option<String>,
+    queue: WriteQueue,
+}
+
+impl IndexerState {
+    /// Index every indexable file under `directory_path`, skipping files
+    /// unchanged since the last index and removing files under it that no
+    /// longer exist
+    ///
+    /// Files are stored by their path relative to `base_path`, the root of
+    /// the filesystem hex, so indexing a subdirectory updates the same
+    /// entries as indexing the whole root.
+    ///
+    /// A filesystem indexed with a different embedding model is cleared and
+    /// indexed from scratch.
+    ///
+    /// Stops before the next window of files or write batch once `cancelled`
+    /// is set; files already stored stay indexed, and files only partly
+    /// stored are removed until the next run indexes them.
+    pub fn index_directory(
+        &self,
+        filesystem_hex_id: &str,
+        base_path: &Path,
+        directory_path: &Path,
+        cancelled: &AtomicBool,
+        on_event: impl Fn(IndexEvent),
+    ) -> Result<IndexResult, String> {
+        let prefix = directory_path.strip_prefix(base_path).map_err(|_| {
+            format!(
+                "{} is not inside {}",
+                directory_path.display(),
+                base_path.display()
+            )
+        })?;
+
+        // An index built with another model can't be searched or extended, so start over
+        let indexed_model = self
+            .store
+            .get_filesystem_model(filesystem_hex_id)
+            .map_err(|e| e.to_string())?;
+        if let Some(indexed) = indexed_model.filter(|m| m.model != self.store.model()) {
+            eprintln!(
+                "[Indexer] Reindexing {} with {} (indexed with {})",
+                filesystem_hex_id,
+                self.store.model(),
+                indexed.model
+            );
+            self.store
+                .clear_filesystem(filesystem_hex_id)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let records = self
+            .store
+            .get_file_records(filesystem_hex_id)
+            .map_err(|e| e.to_string())?;
+        // Files indexed before hashes were recorded only show up in the chunks table
+        let indexed: HashSet<String> = self
+            .store
+            .get_indexed_files(filesystem_hex_id)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .chain(records.keys().cloned())
+            .collect();
+
+        let files = self.discover_files(base_path, directory_path);
+        on_event(IndexEvent::Discovered { files: files.len() });
+
+        let mut run = DirectoryRun {
+            state: self,
+            filesystem_hex_id,
+            on_event,
+            result: IndexResult::default(),
+            files_total: files.len(),
+            files_done: 0,
+            current_file: None,
+            queue: WriteQueue::default(),
+        };
+
+        // Drop files under the directory that disappeared since the last index
+        let seen: HashSet<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        let removed = indexed
+            .iter()
+            .filter(|p| Path::new(p).starts_with(prefix) && !seen.contains(p.as_str()));
+        for file_path in removed {
+            match self.store.remove_file(filesystem_hex_id, file_path) {
+                Ok(_) => run.result.files_removed += 1,
+                Err(e) => eprintln!("Failed to remove file {}: {}", file_path, e),
+            }
+        }
+
+        for window in files.chunks(FILE_WINDOW) {
+            if cancelled.load(Ordering::SeqCst) {
+                return Ok(run.cancel());
+            }
+
+            let prepared: Vec<Prepared> = window
+                .par_iter()
+                .map(|file| self.prepare_file(filesystem_hex_id, file, records.get(&file.relative_path)))
+                .collect();
+
+            for (file, prepared) in window.iter().zip(prepared) {
+                let previously_indexed = indexed.contains(&file.relative_path);
+                run.add_file(&file.relative_path, prepared, previously_indexed);
+
+                while run.queue.chunks.len() >= WRITE_BATCH_CHUNKS {
+                    if cancelled.load(Ordering::SeqCst) {
+                        return Ok(run.cancel());
+                    }
+                    run.flush();
+                }
+            }
+        }
+
+        while !run.queue.chunks.is_empty()
+            || !run.queue.pending.is_empty()
+            || !run.queue.refreshed.is_empty()
+        {
+            if cancelled.load(Ordering::SeqCst) {
+                return Ok(run.cancel());
+            }
+            run.flush();
+        }
+
+        run.current_file = None;
+        run.emit_progress(run.result.chunks_indexed);
+        Ok(run.result)
+    }
+
+    /// Read, hash and chunk a file, comparing it with its last index record
+    fn prepare_file(
+        &self,
+        filesystem_hex_id: &str,
+        file: &DiscoveredFile,
+        previous: Option<&FileRecord>,
+    ) -> Prepared {
+        // Unchanged mtime: trust the last index without reading the file
+        if previous.is_some_and(|r| file.modified_ms.is_some() && r.modified_ms == file.modified_ms) {
+            return Prepared::Unchanged(None);
+        }
+
+        let content = match fs::read_to_string(&file.path) {
+            Ok(content) => content,
+            // Skip binary files or files that can't be read as text
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => return Prepared::NotText,
+            Err(e) => return Prepared::Failed(format!("Failed to read file: {}", e)),
+        };
+        let content_hash = hash_bytes(content.as_bytes());
+
+        // Touched but identical: refresh the mtime so the next run skips the read
+        if let Some(previous) = previous.filter(|r| r.content_hash == content_hash) {
+            return Prepared::Unchanged(Some(FileRecord {
+                modified_ms: file.modified_ms,
+                ..previous.clone()
+            }));
+        }
+
+        let chunks = chunker::chunk_file(filesystem_hex_id, &file.relative_path, &content, &self.config);
+        Prepared::Changed {
+            record: FileRecord {
+                content_hash,
+                modified_ms: file.modified_ms,
+                chunk_count: chunks.len(),
+            },
+            chunks,
+        }
+    }
+
+    /// Walk `directory_path` for indexable files, skipping ignored directories
+    fn discover_files(&self, base_path: &Path, directory_path: &Path) -> Vec<DiscoveredFile> {
+        WalkDir::new(directory_path)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                // Skip ignored directories
+                !self.config.ignore_dirs.iter().any(|d| name == *d)
+            })
+            .filter_map(|e| e.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                // Get relative path from base
+                let path = entry.path();
+                let relative_path = path
+                    .strip_prefix(base_path)
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_else(|_| path.to_string_lossy().to_string());
+
+                self.is_indexable(&relative_path).then(|| DiscoveredFile {
+                    modified_ms: entry.metadata().ok().as_ref().and_then(modified_ms),
+                    path: path.to_path_buf(),
+                    relative_path,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<F: Fn(IndexEvent)> DirectoryRun<'_, F> {
+    fn emit_progress(&self, chunks_embedded: usize) {
+        (self.on_event)(IndexEvent::Progress {
+            files_done: self.files_done,
+            files_total: self.files_total,
+            chunks_embedded,
+            current_file: self.current_file.clone(),
+        });
+    }
+
+    fn file_failed(&mut self, file_path: &str, error: String) {
+        eprintln!("Failed to index file {}: {}", file_path, error);
+        (self.on_event)(IndexEvent::FileError {
+            path: file_path.to_string(),
+            error,
+        });
+        self.files_done += 1;
+    }
+
+    /// Queue a prepared file's chunks, or account for it if there is nothing to store
+    fn add_file(&mut self, file_path: &str, prepared: Prepared, previously_indexed: bool) {
+        self.current_file = Some(file_path.to_string());
+
+        match prepared {
+            Prepared::Unchanged(refreshed) => {
+                self.queue.refreshed.extend(refreshed.map(|r| (file_path.to_string(), r)));
+                self.result.files_skipped += 1;
+                self.files_done += 1;
+            }
+            Prepared::Changed { chunks, record } => {
+                self.queue.replace_files.push(file_path.to_string());
+                self.queue.pending.insert(
+                    file_path.to_string(),
+                    PendingFile {
+                        record,
+                        previously_indexed,
+                        remaining: chunks.len(),
+                        partly_written: false,
+                    },
+                );
+                self.queue.chunks.extend(chunks);
+            }
+            Prepared::NotText => self.files_done += 1,
+            Prepared::Failed(error) => self.file_failed(file_path, error),
+        }
+
+        self.emit_progress(self.result.chunks_indexed);
+    }
+
+    /// Embed and store up to one write batch of queued chunks, along with the
+    /// records of every file the batch completes
+    fn flush(&mut self) {
+        let take = self.queue.chunks.len().min(WRITE_BATCH_CHUNKS);
+        let chunks: Vec<CodeChunk> = self.queue.chunks.drain(..take).collect();
+        for chunk in &chunks {
+            if let Some(file) = self.queue.pending.get_mut(&chunk.file_path) {
+                file.remaining -= 1;
+            }
+        }
+        let completed: Vec<String> = self
+            .queue
+            .pending
+            .iter()
+            .filter(|(_, file)| file.remaining == 0)
+            .map(|(path, _)| path.clone())
+            .collect();
+        let replace_files = std::mem::take(&mut self.queue.replace_files);
+        let refreshed = std::mem::take(&mut self.queue.refreshed);
+
+        let chunks_before = self.result.chunks_indexed;
+        let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
+        let embedded = if texts.is_empty() {
+            Ok(Vec::new())
+        } else {
+            self.state
+                .embedder
+                .embed_with_progress(texts, EMBED_BATCH_SIZE, &mut |embedded, _| {
+                    self.emit_progress(chunks_before + embedded)
+                })
+                .map_err(|e| e.to_string())
+        };
+
+        let written = embedded.and_then(|embeddings| {
+            let records = completed
+                .iter()
+                .map(|path| (path.clone(), self.queue.pending[path].record.clone()))
+                .chain(refreshed)
+                .collect();
+            let batch = StoreBatch {
+                replace_files: replace_files.clone(),
+                chunks: chunks.iter().cloned().zip(embeddings).collect(),
+                records,
+            };
+            self.state
+                .store
+                .write_batch(self.filesystem_hex_id, &batch)
+                .map_err(|e| e.to_string())
+        });
+
+        match written {
+            Ok(()) => {
+                self.result.chunks_indexed += chunks.len();
+                for chunk in &chunks {
+                    if let Some(file) = self.queue.pending.get_mut(&chunk.file_path) {
+                        file.partly_written = true;
+                    }
+                }
+                for path in completed {
+                    let file = self.queue.pending.remove(&path).unwrap();
+                    self.result.files_processed += 1;
+                    if file.previously_indexed {
+                        self.result.files_updated += 1;
+                    } else {
+                        self.result.files_added += 1;
+                    }
+                    self.files_done += 1;
+                }
+            }
+            Err(error) => {
+                // Every file with chunks in the batch fails; its other chunks are dropped
+                let failed: HashSet<String> = chunks
+                    .iter()
+                    .map(|c| c.file_path.clone())
+                    .chain(completed)
+                    .collect();
+                for path in &failed {
+                    if let Some(file) = self.queue.pending.remove(path) {
+                        if file.partly_written {
+                            self.remove_partial_file(path);
+                        }
+                        self.file_failed(path, error.clone());
+                    }
+                }
+                let pending = &self.queue.pending;
+                self.queue.chunks.retain(|c| pending.contains_key(&c.file_path));
+                // The rolled-back deletes still have to happen before the survivors' inserts
+                let mut replace_files: Vec<String> = replace_files
+                    .into_iter()
+                    .filter(|path| pending.contains_key(path))
+                    .collect();
+                replace_files.append(&mut self.queue.replace_files);
+                self.queue.replace_files = replace_files;
+            }
+        }
+
+        self.emit_progress(self.result.chunks_indexed);
+    }
+
+    /// Remove the chunks an earlier batch wrote for a file that won't be completed
+    fn remove_partial_file(&self, file_path: &str) {
+        if let Err(e) = self.state.store.remove_file(self.filesystem_hex_id, file_path) {
+            eprintln!("Failed to remove partly indexed file {}: {}", file_path, e);
+        }
+    }
+
+    fn cancel(self) -> IndexResult {
+        for (path, file) in &self.queue.pending {
+            if file.partly_written {
+                self.remove_partial_file(path);
+            }
+        }
+        (self.on_event)(IndexEvent::Cancelled);
+        IndexResult {
+            cancelled: true,
+            ..self.result
+        }
+    }
+}
+
+/// Modification time in milliseconds since the Unix epoch
+pub fn modified_ms(metadata: &fs::Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::embedder::EmbedderError;
+    use crate::indexer::jobs::IndexJobs;
+    use crate::indexer::provider::EmbeddingProvider;
+    use crate::indexer::store::VectorStore;
+    use crate::indexer::IndexerConfig;
+    use std::cell::RefCell;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    /// Embeds every text as the same vector, failing from call `fail_from` on
+    /// and setting `cancel` once called
+    struct FakeEmbedder {
+        calls: AtomicUsize,
+        fail_from: usize,
+        cancel: Option<Arc<AtomicBool>>,
+    }
+
+    impl EmbeddingProvider for FakeEmbedder {
+        fn model_name(&self) -> &str {
+            "test-model"
+        }
+
+        fn embedding_dim(&self) -> usize {
+            4
+        }
+
+        fn initialize(&self) -> Result<(), EmbedderError> {
+            Ok(())
+        }
+
+        fn is_initialized(&self) -> bool {
+            true
+        }
+
+        fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedderError> {
+            if let Some(cancel) = &self.cancel {
+                cancel.store(true, Ordering::SeqCst);
+            }
+            if self.calls.fetch_add(1, Ordering::SeqCst) >= self.fail_from {
+                return Err(EmbedderError::EmbedError("model crashed".to_string()));
+            }
+            Ok(texts.iter().map(|_| vec![0.5; 4]).collect())
+        }
+    }
+
+    /// A directory of text files with one chunk per line, and a state to index it
+    fn setup(files: &[(&str, usize)], embedder: FakeEmbedder) -> (TempDir, IndexerState) {
+        let dir = TempDir::new().unwrap();
+        for (name, lines) in files {
+            let content: String = (0..*lines).map(|i| format!("line {}\n", i)).collect();
+            let path = dir.path().join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let store = VectorStore::new("test-model", 4);
+        store.initialize(dir.path().join("index.db")).unwrap();
+        let state = IndexerState {
+            embedder: Arc::new(embedder),
+            store: Arc::new(store),
+            config: IndexerConfig {
+                max_chunk_lines: 1,
+                min_chunk_lines: 1,
+                overlap_lines: 0,
+                extensions: vec!["txt".to_string()],
+                ..IndexerConfig::default()
+            },
+            jobs: IndexJobs::default(),
+            settings_error: None,
+        };
+        (dir, state)
+    }
+
+    fn embedder(fail_from: usize) -> FakeEmbedder {
+        FakeEmbedder {
+            calls: AtomicUsize::new(0),
+            fail_from,
+            cancel: None,
+        }
+    }
+
+    #[test]
+    fn test_files_spanning_write_batches() {
+        // b.txt's chunks straddle the first write batch
+        let (dir, state) = setup(&[("a.txt", 200), ("b.txt", 100)], embedder(usize::MAX));
+        let result = state
+            .index_directory("fs", dir.path(), dir.path(), &AtomicBool::new(false), |_| {})
+            .unwrap();
+
+        assert_eq!(result.files_added, 2);
+        assert_eq!(result.chunks_indexed, 300);
+        assert_eq!(state.store.get_chunk_count("fs").unwrap(), 300);
+        let records = state.store.get_file_records("fs").unwrap();
+        assert_eq!(records["a.txt"].chunk_count, 200);
+        assert_eq!(records["b.txt"].chunk_count, 100);
+    }
+
+    #[test]
+    fn test_failed_batch_removes_partly_written_file() {
+        // The first write batch embeds in four calls; the second fails
+        let (dir, state) = setup(&[("big.txt", WRITE_BATCH_CHUNKS + 44)], embedder(4));
+        let errors = RefCell::new(Vec::new());
+        let result = state
+            .index_directory("fs", dir.path(), dir.path(), &AtomicBool::new(false), |event| {
+                if let IndexEvent::FileError { path, .. } = event {
+                    errors.borrow_mut().push(path);
+                }
+            })
+            .unwrap();
+
+        assert_eq!(errors.into_inner(), vec!["big.txt"]);
+        assert_eq!(result.files_added, 0);
+        assert_eq!(state.store.get_chunk_count("fs").unwrap(), 0);
+        assert!(state.store.get_file_records("fs").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_removes_partly_written_file() {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let embedder = FakeEmbedder {
+            cancel: Some(cancelled.clone()),
+            ..embedder(usize::MAX)
+        };
+        let (dir, state) = setup(&[("big.txt", WRITE_BATCH_CHUNKS + 44)], embedder);
+        let result = state
+            .index_directory("fs", dir.path(), dir.path(), &cancelled, |_| {})
+            .unwrap();
+
+        // The first batch was written before the cancel was seen
+        assert!(result.cancelled);
+        assert_eq!(result.chunks_indexed, WRITE_BATCH_CHUNKS);
+        assert_eq!(state.store.get_chunk_count("fs").unwrap(), 0);
+        assert!(state.store.get_file_records("fs").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_subdirectory_keeps_files_outside_it() {
+        let (dir, state) = setup(&[("a.txt", 1), ("src/b.txt", 1)], embedder(usize::MAX));
+        state
+            .index_directory("fs", dir.path(), dir.path(), &AtomicBool::new(false), |_| {})
+            .unwrap();
+
+        fs::remove_file(dir.path().join("src/b.txt")).unwrap();
+        fs::write(dir.path().join("src/c.txt"), "line 0\n").unwrap();
+        let src = dir.path().join("src");
+        let result = state
+            .index_directory("fs", dir.path(), &src, &AtomicBool::new(false), |_| {})
+            .unwrap();
+
+        assert_eq!((result.files_added, result.files_removed), (1, 1));
+        let records = state.store.get_file_records("fs").unwrap();
+        let mut paths: Vec<String> = records.into_keys().collect();
+        paths.sort();
+        assert_eq!(paths, vec!["a.txt", "src/c.txt"]);
+    }
+}
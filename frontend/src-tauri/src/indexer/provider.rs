@@ -0,0 +1,121 @@
+//! Embedding providers
+//!
+//! The indexer embeds chunks and queries through `EmbeddingProvider`, so the
+//! model can run in-process with fastembed or on an embedding server.
+
+use super::embedder::{Embedder, EmbedderError, ModelSpec};
+use super::http_embedder::HttpEmbedder;
+use crate::database::Database;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Settings key choosing the embedding provider: `fastembed` (default) or `http`
+pub const PROVIDER_SETTING: &str = "indexer:embedding-provider";
+
+/// A source of embeddings for code chunks and search queries
+pub trait EmbeddingProvider: Send + Sync {
+    /// Name recorded with each filesystem's index
+    fn model_name(&self) -> &str;
+
+    /// Length of every embedding the provider returns
+    fn embedding_dim(&self) -> usize;
+
+    /// Prepare the provider; `embed` fails with `NotInitialized` until this succeeds
+    fn initialize(&self) -> Result<(), EmbedderError>;
+
+    /// Check if the provider is initialized
+    fn is_initialized(&self) -> bool;
+
+    /// Generate embeddings for a batch of texts
+    fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedderError>;
+
+    /// Generate embedding for a single text
+    fn embed_one(&self, text: &str) -> Result<Vec<f32>, EmbedderError> {
+        let embeddings = self.embed(vec![text.to_string()])?;
+        embeddings
+            .into_iter()
+            .next()
+            .ok_or_else(|| EmbedderError::EmbedError("No embedding returned".to_string()))
+    }
+
+    /// Generate embeddings with progress callback
+    fn embed_with_progress(
+        &self,
+        texts: Vec<String>,
+        batch_size: usize,
+        on_progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<Vec<Vec<f32>>, EmbedderError> {
+        let total = texts.len();
+        let mut all_embeddings = Vec::with_capacity(total);
+
+        for (batch_idx, batch) in texts.chunks(batch_size).enumerate() {
+            let batch_embeddings = self.embed(batch.to_vec())?;
+            all_embeddings.extend(batch_embeddings);
+
+            let processed = ((batch_idx + 1) * batch_size).min(total);
+            on_progress(processed, total);
+        }
+
+        Ok(all_embeddings)
+    }
+}
+
+/// The provider configured in settings
+///
+/// Fails if the settings name an unknown provider, an incomplete HTTP
+/// provider or a fastembed model that doesn't exist.
+pub fn from_settings(db: &Database, cache_dir: PathBuf) -> Result<Arc<dyn EmbeddingProvider>, EmbedderError> {
+    match read_setting(db, PROVIDER_SETTING).as_deref() {
+        None | Some("fastembed") => {
+            let spec = ModelSpec::from_settings(db)?;
+            Ok(Arc::new(Embedder::with_cache_dir(cache_dir).with_model(spec)))
+        }
+        Some("http") => Ok(Arc::new(HttpEmbedder::from_settings(db)?)),
+        Some(other) => Err(EmbedderError::InitError(format!(
+            "Unknown embedding provider '{}'",
+            other
+        ))),
+    }
+}
+
+/// Stands in for a provider whose settings are invalid, so the app still
+/// starts; initializing or embedding fails with the settings error
+pub struct UnavailableProvider {
+    error: EmbedderError,
+}
+
+impl UnavailableProvider {
+    pub fn new(error: EmbedderError) -> Self {
+        Self { error }
+    }
+}
+
+impl EmbeddingProvider for UnavailableProvider {
+    fn model_name(&self) -> &str {
+        "unavailable"
+    }
+
+    fn embedding_dim(&self) -> usize {
+        0
+    }
+
+    fn initialize(&self) -> Result<(), EmbedderError> {
+        Err(self.error.clone())
+    }
+
+    fn is_initialized(&self) -> bool {
+        false
+    }
+
+    fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>, EmbedderError> {
+        Err(self.error.clone())
+    }
+}
+
+/// Read a setting, logging and ignoring database errors
+pub(super) fn read_setting(db: &Database, key: &str) -> Option<String> {
+    db.get_setting(key).unwrap_or_else(|e| {
+        eprintln!("[Indexer] Failed to read {}: {}", key, e);
+        None
+    })
+}
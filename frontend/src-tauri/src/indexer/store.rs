@@ -1,10 +1,17 @@
 //! Vector storage using SQLite with sqlite-vec extension
 //!
-//! Stores code chunk embeddings and provides KNN search functionality.
+//! Stores code chunk embeddings and provides KNN search functionality, plus
+//! an FTS5 keyword index over chunk contents and paths for hybrid search.
 
 use super::{CodeChunk, SearchResult};
-use rusqlite::{ffi::sqlite3_auto_extension, params, Connection};
-use std::path::PathBuf;
+use crate::files::tree::glob_overrides;
+use ignore::overrides::Override;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{ffi::sqlite3_auto_extension, params, Connection, OptionalExtension, Row, ToSql};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use thiserror::Error;
 
@@ -16,24 +23,168 @@ pub enum StoreError {
     Serialization(String),
     #[error("Store not initialized")]
     NotInitialized,
+    #[error("Filesystem {filesystem_hex_id} was indexed with {indexed}, but the indexer is using {current}; reindex it first")]
+    ModelMismatch {
+        filesystem_hex_id: String,
+        indexed: String,
+        current: String,
+    },
+    #[error("{0:?} search needs a query embedding")]
+    MissingEmbedding(SearchMode),
+    #[error("Invalid search filter: {0}")]
+    InvalidFilter(String),
+}
+
+/// Model every index was built with before models were recorded
+const LEGACY_MODEL: (&str, usize) = ("all-minilm-l6-v2", 384);
+
+/// The embedding model a filesystem hex was indexed with
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IndexedModel {
+    pub model: String,
+    pub dimension: usize,
+}
+
+/// Reciprocal rank fusion constant; damps the lead of top-ranked results
+const RRF_K: f32 = 60.0;
+/// Candidates each ranking contributes to a hybrid search, as a multiple of the limit
+const HYBRID_CANDIDATES: usize = 4;
+/// Nearest neighbours a path-filtered vector search looks through; sqlite-vec's largest `k`
+const FILTERED_KNN_CANDIDATES: usize = 4096;
+
+/// `chunk_embeddings` metadata columns copied from `code_chunks`, so the KNN
+/// query itself can filter on them. vec0 metadata can't be NULL; a missing
+/// language or kind is stored as ''.
+const EMBEDDING_METADATA_COLUMNS: &[&str] = &["filesystem_hex_id", "language", "kind"];
+
+/// `code_chunks` columns added since the table was first created. Adding one
+/// forgets every file record, so the next scan re-chunks each file to fill it in.
+const ADDED_CHUNK_COLUMNS: &[(&str, &str)] = &[
+    ("kind", "TEXT"),
+    ("symbol", "TEXT"),
+    ("node_kind", "TEXT"),
+    ("parent_symbol", "TEXT"),
+    ("signature", "TEXT"),
+];
+
+/// `code_chunks` with an explicit integer key for the keyword index. FTS5
+/// stores the key of each row it indexes, and an implicit rowid may change
+/// on VACUUM.
+const CREATE_CHUNKS_SQL: &str = r#"
+    CREATE TABLE IF NOT EXISTS code_chunks (
+        chunk_rowid INTEGER PRIMARY KEY,
+        id TEXT NOT NULL UNIQUE,
+        filesystem_hex_id TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        start_line INTEGER NOT NULL,
+        end_line INTEGER NOT NULL,
+        content TEXT NOT NULL,
+        language TEXT,
+        kind TEXT,
+        symbol TEXT,
+        node_kind TEXT,
+        parent_symbol TEXT,
+        signature TEXT,
+        created_at TEXT DEFAULT CURRENT_TIMESTAMP
+    )
+"#;
+
+/// Columns `chunk_from_row` reads, in order
+const CHUNK_COLUMNS: &str = "c.id, c.filesystem_hex_id, c.file_path, c.start_line, c.end_line, c.content, \
+     c.language, c.kind, c.symbol, c.node_kind, c.parent_symbol, c.signature";
+
+/// How `VectorStore::search` ranks chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchMode {
+    /// Nearest embeddings to the query
+    #[default]
+    Vector,
+    /// BM25 over chunk contents and file paths
+    Keyword,
+    /// Both rankings, fused with reciprocal rank fusion
+    Hybrid,
+}
+
+/// A query for `VectorStore::search`
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Text matched against chunk contents and file paths by keyword searches
+    pub text: String,
+    /// Query embedding; required unless `mode` is `Keyword`
+    pub embedding: Option<Vec<f32>>,
+    pub mode: SearchMode,
+    /// Share of a hybrid score taken from the keyword ranking, from 0.0 to 1.0
+    pub keyword_weight: f32,
+    pub filters: SearchFilters,
+}
+
+/// Restrictions applied inside both rankings of a search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchFilters {
+    /// Only chunks in one of these languages (e.g., "python")
+    pub languages: Vec<String>,
+    /// Only files matching one of these globs
+    pub include: Vec<String>,
+    /// Skip files and directories matching any of these globs
+    pub exclude: Vec<String>,
+    /// Only semantic units of these kinds (e.g., "function", "class")
+    pub kinds: Vec<String>,
+    /// Drop matches with a lower cosine similarity to the query; ignored by
+    /// keyword searches, which have no query embedding
+    pub min_similarity: Option<f32>,
+}
+
+impl SearchFilters {
+    /// Whether include/exclude globs narrow the files searched
+    fn filters_paths(&self) -> bool {
+        !self.include.is_empty() || !self.exclude.is_empty()
+    }
+}
+
+/// What was indexed for a file, used to skip unchanged files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRecord {
+    /// SHA-256 of the file contents
+    pub content_hash: String,
+    /// Modification time in milliseconds since the Unix epoch
+    pub modified_ms: Option<i64>,
+    pub chunk_count: usize,
+}
+
+/// Writes applied together in one transaction by `VectorStore::write_batch`
+#[derive(Debug, Default)]
+pub struct StoreBatch {
+    /// Files whose existing chunks and records are deleted before the inserts
+    pub replace_files: Vec<String>,
+    pub chunks: Vec<(CodeChunk, Vec<f32>)>,
+    /// Files whose last chunk is in this batch
+    pub records: Vec<(String, FileRecord)>,
 }
 
 /// Vector store for code chunk embeddings
 pub struct VectorStore {
     conn: Mutex<Option<Connection>>,
+    /// Embedding model the stored vectors must come from
+    model: String,
     embedding_dim: usize,
 }
 
 impl VectorStore {
-    /// Create a new vector store
-    pub fn new(embedding_dim: usize) -> Self {
+    /// Create a new vector store for embeddings from `model`
+    pub fn new(model: &str, embedding_dim: usize) -> Self {
         Self {
             conn: Mutex::new(None),
+            model: model.to_string(),
             embedding_dim,
         }
     }
 
     /// Initialize the store with a database path
+    ///
+    /// If the embeddings table was created for a different dimension, every
+    /// index is dropped so filesystems are reindexed with the current model.
     pub fn initialize(&self, db_path: PathBuf) -> Result<(), StoreError> {
         // Register sqlite-vec extension before opening connection
         unsafe {
@@ -44,33 +195,156 @@ impl VectorStore {
 
         let conn = Connection::open(&db_path)?;
 
-        // Create tables
+        let columns: Vec<String> = conn
+            .prepare("SELECT name FROM pragma_table_info('code_chunks')")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let missing: Vec<_> = ADDED_CHUNK_COLUMNS
+            .iter()
+            .filter(|(name, _)| !columns.is_empty() && !columns.iter().any(|c| c == name))
+            .collect();
+        if !columns.is_empty() && !columns.iter().any(|c| c == "chunk_rowid") {
+            // Chunks keyed by their text id alone move to a table with an
+            // integer key; the keyword index is rebuilt on the new key below
+            conn.execute_batch(&format!(
+                r#"
+                DROP TRIGGER IF EXISTS code_chunks_fts_insert;
+                DROP TRIGGER IF EXISTS code_chunks_fts_delete;
+                DROP TRIGGER IF EXISTS code_chunks_fts_update;
+                DROP TABLE IF EXISTS chunk_fts;
+                DROP INDEX IF EXISTS idx_chunks_filesystem;
+                DROP INDEX IF EXISTS idx_chunks_file;
+                ALTER TABLE code_chunks RENAME TO old_code_chunks;
+                {create};
+                INSERT INTO code_chunks ({columns}) SELECT {columns} FROM old_code_chunks;
+                DROP TABLE old_code_chunks;
+                "#,
+                create = CREATE_CHUNKS_SQL,
+                columns = columns.join(", ")
+            ))?;
+        } else {
+            for (name, sql_type) in &missing {
+                conn.execute(&format!("ALTER TABLE code_chunks ADD COLUMN {} {}", name, sql_type), [])?;
+            }
+        }
+
+        let has_fts: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'chunk_fts')",
+            [],
+            |row| row.get(0),
+        )?;
+
+        // Create tables. The keyword index reads its text from code_chunks and
+        // is kept in sync by triggers; recursive triggers make `INSERT OR
+        // REPLACE` fire the delete trigger for the row it replaces.
         conn.execute_batch(&format!(
             r#"
-            CREATE TABLE IF NOT EXISTS code_chunks (
-                id TEXT PRIMARY KEY,
-                filesystem_hex_id TEXT NOT NULL,
-                file_path TEXT NOT NULL,
-                start_line INTEGER NOT NULL,
-                end_line INTEGER NOT NULL,
-                content TEXT NOT NULL,
-                language TEXT,
-                created_at TEXT DEFAULT CURRENT_TIMESTAMP
-            );
+            PRAGMA recursive_triggers = ON;
+
+            {create_chunks};
             
             CREATE INDEX IF NOT EXISTS idx_chunks_filesystem 
                 ON code_chunks(filesystem_hex_id);
             CREATE INDEX IF NOT EXISTS idx_chunks_file 
                 ON code_chunks(filesystem_hex_id, file_path);
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS chunk_fts USING fts5(
+                content,
+                file_path,
+                content = 'code_chunks',
+                content_rowid = 'chunk_rowid'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS code_chunks_fts_insert AFTER INSERT ON code_chunks BEGIN
+                INSERT INTO chunk_fts (rowid, content, file_path)
+                    VALUES (new.chunk_rowid, new.content, new.file_path);
+            END;
+            CREATE TRIGGER IF NOT EXISTS code_chunks_fts_delete AFTER DELETE ON code_chunks BEGIN
+                INSERT INTO chunk_fts (chunk_fts, rowid, content, file_path)
+                    VALUES ('delete', old.chunk_rowid, old.content, old.file_path);
+            END;
+            CREATE TRIGGER IF NOT EXISTS code_chunks_fts_update AFTER UPDATE ON code_chunks BEGIN
+                INSERT INTO chunk_fts (chunk_fts, rowid, content, file_path)
+                    VALUES ('delete', old.chunk_rowid, old.content, old.file_path);
+                INSERT INTO chunk_fts (rowid, content, file_path)
+                    VALUES (new.chunk_rowid, new.content, new.file_path);
+            END;
             
-            CREATE VIRTUAL TABLE IF NOT EXISTS chunk_embeddings USING vec0(
-                chunk_id TEXT PRIMARY KEY,
-                embedding float[{dim}]
+            CREATE TABLE IF NOT EXISTS indexed_files (
+                filesystem_hex_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                content_hash TEXT NOT NULL,
+                modified_ms INTEGER,
+                chunk_count INTEGER NOT NULL,
+                indexed_at TEXT DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (filesystem_hex_id, file_path)
+            );
+
+            CREATE TABLE IF NOT EXISTS indexed_models (
+                filesystem_hex_id TEXT PRIMARY KEY,
+                model TEXT NOT NULL,
+                dimension INTEGER NOT NULL
             );
+
+            INSERT OR IGNORE INTO indexed_models (filesystem_hex_id, model, dimension)
+                SELECT DISTINCT filesystem_hex_id, '{legacy_model}', {legacy_dim} FROM code_chunks;
             "#,
-            dim = self.embedding_dim
+            create_chunks = CREATE_CHUNKS_SQL,
+            legacy_model = LEGACY_MODEL.0,
+            legacy_dim = LEGACY_MODEL.1
         ))?;
 
+        if !missing.is_empty() {
+            eprintln!("[Indexer] Chunk metadata changed; files will be re-chunked on their next scan");
+            conn.execute("DELETE FROM indexed_files", [])?;
+        }
+
+        // Chunks indexed before the keyword index existed
+        if !has_fts {
+            conn.execute("INSERT INTO chunk_fts (chunk_fts) VALUES ('rebuild')", [])?;
+        }
+
+        // vec0 columns have a fixed size, so vectors of another size can't share the table
+        let existing_sql = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE name = 'chunk_embeddings'",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()?;
+        let existing_dim = existing_sql.as_deref().and_then(parse_embedding_dim);
+        if existing_dim.is_some_and(|dim| dim != self.embedding_dim) {
+            eprintln!(
+                "[Indexer] Embedding dimension changed to {}; clearing every index",
+                self.embedding_dim
+            );
+            conn.execute_batch(
+                r#"
+                DROP TABLE chunk_embeddings;
+                DELETE FROM code_chunks;
+                DELETE FROM indexed_files;
+                DELETE FROM indexed_models;
+                "#,
+            )?;
+        } else if existing_sql.is_some_and(|sql| !has_metadata_columns(&sql)) {
+            // Embeddings from before the metadata columns keep their vectors
+            // and take their metadata from the chunks
+            conn.execute_batch(&format!(
+                r#"
+                CREATE TEMP TABLE old_embeddings AS SELECT chunk_id, embedding FROM chunk_embeddings;
+                DROP TABLE chunk_embeddings;
+                {create};
+                INSERT INTO chunk_embeddings (chunk_id, filesystem_hex_id, language, kind, embedding)
+                    SELECT o.chunk_id, c.filesystem_hex_id, COALESCE(c.language, ''), COALESCE(c.kind, ''), o.embedding
+                    FROM old_embeddings o INNER JOIN code_chunks c ON c.id = o.chunk_id;
+                DROP TABLE old_embeddings;
+                "#,
+                create = create_embeddings_sql(self.embedding_dim)
+            ))?;
+        }
+
+        conn.execute_batch(&create_embeddings_sql(self.embedding_dim))?;
+
         *self.conn.lock().unwrap() = Some(conn);
         Ok(())
     }
@@ -80,37 +354,31 @@ impl VectorStore {
         self.conn.lock().unwrap().is_some()
     }
 
-    /// Insert a chunk with its embedding
-    pub fn insert(&self, chunk: &CodeChunk, embedding: &[f32]) -> Result<(), StoreError> {
+    /// Name of the embedding model the store accepts vectors from
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Get the embedding model a filesystem hex was indexed with
+    pub fn get_filesystem_model(&self, filesystem_hex_id: &str) -> Result<Option<IndexedModel>, StoreError> {
         let conn_guard = self.conn.lock().unwrap();
         let conn = conn_guard.as_ref().ok_or(StoreError::NotInitialized)?;
 
-        // Insert chunk metadata
-        conn.execute(
-            r#"INSERT OR REPLACE INTO code_chunks 
-               (id, filesystem_hex_id, file_path, start_line, end_line, content, language)
-               VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"#,
-            params![
-                chunk.id,
-                chunk.filesystem_hex_id,
-                chunk.file_path,
-                chunk.start_line,
-                chunk.end_line,
-                chunk.content,
-                chunk.language
-            ],
-        )?;
-
-        // Insert embedding as JSON array
-        let embedding_json = serde_json::to_string(embedding)
-            .map_err(|e| StoreError::Serialization(e.to_string()))?;
+        let model = conn
+            .query_row(
+                "SELECT model, dimension FROM indexed_models WHERE filesystem_hex_id = ?1",
+                params![filesystem_hex_id],
+                |row| {
+                    let dimension: i64 = row.get(1)?;
+                    Ok(IndexedModel {
+                        model: row.get(0)?,
+                        dimension: dimension as usize,
+                    })
+                },
+            )
+            .optional()?;
 
-        conn.execute(
-            "INSERT OR REPLACE INTO chunk_embeddings (chunk_id, embedding) VALUES (?1, ?2)",
-            params![chunk.id, embedding_json],
-        )?;
-
-        Ok(())
+        Ok(model)
     }
 
     /// Remove all chunks for a file
@@ -118,102 +386,174 @@ impl VectorStore {
         let conn_guard = self.conn.lock().unwrap();
         let conn = conn_guard.as_ref().ok_or(StoreError::NotInitialized)?;
 
-        // Get chunk IDs for this file
-        let mut stmt = conn.prepare(
-            "SELECT id FROM code_chunks WHERE filesystem_hex_id = ?1 AND file_path = ?2"
-        )?;
-        let chunk_ids: Vec<String> = stmt
-            .query_map(params![filesystem_hex_id, file_path], |row| row.get(0))?
-            .filter_map(|r| r.ok())
-            .collect();
+        delete_file(conn, filesystem_hex_id, file_path)
+    }
 
-        // Delete embeddings
-        for chunk_id in &chunk_ids {
-            conn.execute(
-                "DELETE FROM chunk_embeddings WHERE chunk_id = ?1",
-                params![chunk_id],
-            )?;
+    /// Apply a batch of file replacements, chunk inserts and file records in
+    /// a single transaction
+    ///
+    /// Fails with `ModelMismatch` if the filesystem hex was indexed with
+    /// another model, rather than mixing the two.
+    pub fn write_batch(&self, filesystem_hex_id: &str, batch: &StoreBatch) -> Result<(), StoreError> {
+        let mut conn_guard = self.conn.lock().unwrap();
+        let conn = conn_guard.as_mut().ok_or(StoreError::NotInitialized)?;
+        let tx = conn.transaction()?;
+
+        self.check_model(&tx, filesystem_hex_id)?;
+        tx.prepare_cached(
+            r#"INSERT OR REPLACE INTO indexed_models (filesystem_hex_id, model, dimension)
+               VALUES (?1, ?2, ?3)"#,
+        )?
+        .execute(params![filesystem_hex_id, self.model, self.embedding_dim as i64])?;
+
+        for file_path in &batch.replace_files {
+            delete_file(&tx, filesystem_hex_id, file_path)?;
+        }
+        for (chunk, embedding) in &batch.chunks {
+            insert_chunk(&tx, chunk, embedding)?;
+        }
+        for (file_path, record) in &batch.records {
+            upsert_record(&tx, filesystem_hex_id, file_path, record)?;
         }
 
-        // Delete chunks
-        let deleted = conn.execute(
-            "DELETE FROM code_chunks WHERE filesystem_hex_id = ?1 AND file_path = ?2",
-            params![filesystem_hex_id, file_path],
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the index records for every file in a filesystem hex, keyed by path
+    pub fn get_file_records(
+        &self,
+        filesystem_hex_id: &str,
+    ) -> Result<HashMap<String, FileRecord>, StoreError> {
+        let conn_guard = self.conn.lock().unwrap();
+        let conn = conn_guard.as_ref().ok_or(StoreError::NotInitialized)?;
+
+        let mut stmt = conn.prepare(
+            r#"SELECT file_path, content_hash, modified_ms, chunk_count
+               FROM indexed_files WHERE filesystem_hex_id = ?1"#,
         )?;
 
-        Ok(deleted)
+        let records = stmt
+            .query_map(params![filesystem_hex_id], |row| {
+                let chunk_count: i64 = row.get(3)?;
+                Ok((
+                    row.get(0)?,
+                    FileRecord {
+                        content_hash: row.get(1)?,
+                        modified_ms: row.get(2)?,
+                        chunk_count: chunk_count as usize,
+                    },
+                ))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(records)
     }
 
-    /// Search for similar chunks using KNN
+    /// Search for chunks matching a query
+    ///
+    /// Vector and hybrid searches fail with `ModelMismatch` if any searched
+    /// filesystem hex was indexed with another model; an empty list searches,
+    /// and checks, every hex. Results are ordered by `score`.
+    ///
+    /// Filters are part of each ranking's query; hexes, languages and kinds
+    /// are matched inside the KNN search. Path globs can't be, so a vector
+    /// search with include/exclude globs looks through the nearest
+    /// `FILTERED_KNN_CANDIDATES` chunks and can return fewer than `limit`
+    /// when only distant chunks match them.
     pub fn search(
         &self,
-        query_embedding: &[f32],
+        query: &SearchQuery,
         filesystem_hex_ids: &[String],
         limit: usize,
     ) -> Result<Vec<SearchResult>, StoreError> {
         let conn_guard = self.conn.lock().unwrap();
         let conn = conn_guard.as_ref().ok_or(StoreError::NotInitialized)?;
 
-        let embedding_json = serde_json::to_string(query_embedding)
-            .map_err(|e| StoreError::Serialization(e.to_string()))?;
+        let keyword_weight = match query.mode {
+            SearchMode::Vector => 0.0,
+            SearchMode::Keyword => 1.0,
+            SearchMode::Hybrid => query.keyword_weight.clamp(0.0, 1.0),
+        };
+        // Looking past `limit` lets a chunk both rankings place fairly high
+        // outscore one that only a single ranking places first
+        let candidates = match query.mode {
+            SearchMode::Hybrid => limit * HYBRID_CANDIDATES,
+            _ => limit,
+        };
 
-        // Build filesystem filter
-        let fs_filter = if filesystem_hex_ids.is_empty() {
-            "1=1".to_string()
-        } else {
-            let placeholders: Vec<String> = filesystem_hex_ids
-                .iter()
-                .enumerate()
-                .map(|(i, _)| format!("?{}", i + 3))
-                .collect();
-            format!("c.filesystem_hex_id IN ({})", placeholders.join(","))
+        let embedding_json = match (query.mode, &query.embedding) {
+            (SearchMode::Keyword, _) => None,
+            (_, Some(embedding)) => Some(
+                serde_json::to_string(embedding).map_err(|e| StoreError::Serialization(e.to_string()))?,
+            ),
+            (mode, None) => return Err(StoreError::MissingEmbedding(mode)),
         };
 
-        let query = format!(
-            r#"
-            SELECT
-                c.id, c.filesystem_hex_id, c.file_path, c.start_line, c.end_line,
-                c.content, c.language, e.distance
-            FROM chunk_embeddings e
-            INNER JOIN code_chunks c ON e.chunk_id = c.id
-            WHERE e.embedding MATCH ?1
-              AND k = ?2
-              AND {}
-            ORDER BY e.distance
-            "#,
-            fs_filter
-        );
+        let filters = &query.filters;
+        if !filters.include.is_empty() || !filters.exclude.is_empty() {
+            let overrides = glob_overrides(Path::new(""), &filters.include, &filters.exclude)
+                .map_err(|e| StoreError::InvalidFilter(e.to_string()))?;
+            let overrides = AssertUnwindSafe(overrides);
+            conn.create_scalar_function(
+                "path_matches",
+                1,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                move |ctx| Ok(path_matches(&overrides, &ctx.get::<String>(0)?)),
+            )?;
+        }
 
-        let mut stmt = conn.prepare(&query)?;
+        let by_vector = match &embedding_json {
+            Some(embedding_json) => {
+                self.check_models(conn, filesystem_hex_ids)?;
+                vector_ranking(conn, embedding_json, filesystem_hex_ids, filters, candidates)?
+            }
+            None => Vec::new(),
+        };
+        let by_keyword = match query.mode {
+            SearchMode::Vector => Vec::new(),
+            _ => keyword_ranking(
+                conn,
+                &query.text,
+                embedding_json.as_deref(),
+                filesystem_hex_ids,
+                filters,
+                candidates,
+            )?,
+        };
 
-        // Build params
-        let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![
-            Box::new(embedding_json),
-            Box::new(limit as i32),
-        ];
-        for fs_id in filesystem_hex_ids {
-            params_vec.push(Box::new(fs_id.clone()));
+        // Reciprocal rank fusion: each ranking adds weight / (k + rank)
+        let mut results: Vec<SearchResult> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        for (weight, ranking) in [(1.0 - keyword_weight, by_vector), (keyword_weight, by_keyword)] {
+            if weight <= 0.0 {
+                continue;
+            }
+            for (rank, result) in ranking.into_iter().enumerate() {
+                let score = weight / (RRF_K + rank as f32 + 1.0);
+                match positions.get(&result.chunk.id) {
+                    Some(&i) => results[i].score += score,
+                    None => {
+                        positions.insert(result.chunk.id.clone(), results.len());
+                        results.push(SearchResult { score, ..result });
+                    }
+                }
+            }
         }
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        results.truncate(limit);
 
-        let results = stmt.query_map(
-            rusqlite::params_from_iter(params_vec.iter().map(|p| p.as_ref())),
-            |row| {
-                Ok(SearchResult {
-                    chunk: CodeChunk {
-                        id: row.get(0)?,
-                        filesystem_hex_id: row.get(1)?,
-                        file_path: row.get(2)?,
-                        start_line: row.get(3)?,
-                        end_line: row.get(4)?,
-                        content: row.get(5)?,
-                        language: row.get(6)?,
-                    },
-                    distance: row.get(7)?,
-                })
-            },
-        )?;
+        // Keyword-only matches still get a distance when there is a query embedding
+        if let Some(embedding_json) = &embedding_json {
+            for result in results.iter_mut().filter(|r| r.distance.is_none()) {
+                result.distance = conn
+                    .prepare_cached("SELECT vec_distance_l2(embedding, ?1) FROM chunk_embeddings WHERE chunk_id = ?2")?
+                    .query_row(params![embedding_json, result.chunk.id], |row| row.get(0))
+                    .optional()?;
+            }
+        }
 
-        results.collect::<Result<Vec<_>, _>>().map_err(StoreError::Database)
+        Ok(results)
     }
 
     /// Get count of chunks for a filesystem hex
@@ -275,7 +615,832 @@ impl VectorStore {
             params![filesystem_hex_id],
         )?;
 
+        conn.execute(
+            "DELETE FROM indexed_files WHERE filesystem_hex_id = ?1",
+            params![filesystem_hex_id],
+        )?;
+        conn.execute(
+            "DELETE FROM indexed_models WHERE filesystem_hex_id = ?1",
+            params![filesystem_hex_id],
+        )?;
+
         Ok(deleted)
     }
+
+    /// Fail if any of the filesystem hexes, or any hex at all if none are
+    /// given, was indexed with a model other than the store's
+    fn check_models(&self, conn: &Connection, filesystem_hex_ids: &[String]) -> Result<(), StoreError> {
+        if filesystem_hex_ids.is_empty() {
+            let stale: Option<String> = conn
+                .query_row(
+                    "SELECT filesystem_hex_id FROM indexed_models WHERE model != ?1 LIMIT 1",
+                    params![self.model],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(filesystem_hex_id) = stale {
+                self.check_model(conn, &filesystem_hex_id)?;
+            }
+        }
+        for filesystem_hex_id in filesystem_hex_ids {
+            self.check_model(conn, filesystem_hex_id)?;
+        }
+        Ok(())
+    }
+
+    /// Fail if a filesystem hex was indexed with a model other than the store's
+    fn check_model(&self, conn: &Connection, filesystem_hex_id: &str) -> Result<(), StoreError> {
+        let indexed: Option<String> = conn
+            .prepare_cached("SELECT model FROM indexed_models WHERE filesystem_hex_id = ?1")?
+            .query_row(params![filesystem_hex_id], |row| row.get(0))
+            .optional()?;
+
+        match indexed {
+            Some(indexed) if indexed != self.model => Err(StoreError::ModelMismatch {
+                filesystem_hex_id: filesystem_hex_id.to_string(),
+                indexed,
+                current: self.model.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The `CREATE` statement for the embeddings table
+fn create_embeddings_sql(embedding_dim: usize) -> String {
+    format!(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS chunk_embeddings USING vec0(
+            chunk_id TEXT PRIMARY KEY,
+            filesystem_hex_id TEXT,
+            language TEXT,
+            kind TEXT,
+            embedding float[{}]
+        )
+        "#,
+        embedding_dim
+    )
+}
+
+/// Read the vector size from a vec0 table's `CREATE` statement
+fn parse_embedding_dim(sql: &str) -> Option<usize> {
+    let start = sql.find("float[")? + "float[".len();
+    let len = sql[start..].find(']')?;
+    sql[start..start + len].trim().parse().ok()
+}
+
+/// Whether a vec0 table's `CREATE` statement has every metadata column
+fn has_metadata_columns(sql: &str) -> bool {
+    EMBEDDING_METADATA_COLUMNS.iter().all(|column| sql.contains(column))
+}
+
+/// Build the conditions that restrict a search to the requested filesystem
+/// hexes and filters, appending their parameters to `params`
+///
+/// Hexes, languages and kinds are matched on the `table` alias, which has
+/// `code_chunks`' column names; paths are always matched on `c`.
+fn chunk_filter<'a>(
+    table: &str,
+    filesystem_hex_ids: &'a [String],
+    filters: &'a SearchFilters,
+    params: &mut Vec<&'a dyn ToSql>,
+) -> String {
+    let mut conditions = Vec::new();
+    for (column, values) in [
+        ("filesystem_hex_id", filesystem_hex_ids),
+        ("language", &filters.languages),
+        ("kind", &filters.kinds),
+    ] {
+        if values.is_empty() {
+            continue;
+        }
+        let placeholders: Vec<String> = values
+            .iter()
+            .map(|value| {
+                params.push(value);
+                format!("?{}", params.len())
+            })
+            .collect();
+        conditions.push(format!("{}.{} IN ({})", table, column, placeholders.join(",")));
+    }
+    if filters.filters_paths() {
+        conditions.push("path_matches(c.file_path)".to_string());
+    }
+
+    if conditions.is_empty() {
+        "1=1".to_string()
+    } else {
+        conditions.join(" AND ")
+    }
+}
+
+/// Whether a file passes include/exclude overrides, skipping files under an
+/// excluded directory the way `list_tree` and `search_files` do
+fn path_matches(overrides: &Override, file_path: &str) -> bool {
+    let path = Path::new(file_path);
+    let in_excluded_dir = path
+        .ancestors()
+        .skip(1)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .any(|dir| overrides.matched(dir, true).is_ignore());
+
+    !in_excluded_dir && !overrides.matched(path, false).is_ignore()
+}
+
+/// Read a chunk from the `CHUNK_COLUMNS` at the start of a search row
+fn chunk_from_row(row: &Row) -> rusqlite::Result<CodeChunk> {
+    Ok(CodeChunk {
+        id: row.get(0)?,
+        filesystem_hex_id: row.get(1)?,
+        file_path: row.get(2)?,
+        start_line: row.get(3)?,
+        end_line: row.get(4)?,
+        content: row.get(5)?,
+        language: row.get(6)?,
+        kind: row.get(7)?,
+        symbol: row.get(8)?,
+        node_kind: row.get(9)?,
+        parent_symbol: row.get(10)?,
+        signature: row.get(11)?,
+    })
+}
+
+/// Chunks nearest to a query embedding, closest first
+fn vector_ranking(
+    conn: &Connection,
+    embedding_json: &str,
+    filesystem_hex_ids: &[String],
+    filters: &SearchFilters,
+    limit: usize,
+) -> Result<Vec<SearchResult>, StoreError> {
+    // Metadata conditions on `e` filter inside the KNN search, but sqlite-vec
+    // picks the k nearest before the join's path filter sees them
+    let k = if filters.filters_paths() {
+        FILTERED_KNN_CANDIDATES.max(limit)
+    } else {
+        limit
+    } as i64;
+    let limit = limit as i64;
+    let mut params_vec: Vec<&dyn ToSql> = vec![&embedding_json, &k, &limit];
+
+    let mut conditions = chunk_filter("e", filesystem_hex_ids, filters, &mut params_vec);
+    if let Some(min_similarity) = &filters.min_similarity {
+        params_vec.push(min_similarity);
+        conditions.push_str(&format!(
+            " AND 1 - vec_distance_cosine(e.embedding, ?1) >= ?{}",
+            params_vec.len()
+        ));
+    }
+
+    let query = format!(
+        r#"
+        SELECT {}, e.distance
+        FROM chunk_embeddings e
+        INNER JOIN code_chunks c ON e.chunk_id = c.id
+        WHERE e.embedding MATCH ?1
+          AND k = ?2
+          AND {}
+        ORDER BY e.distance
+        LIMIT ?3
+        "#,
+        CHUNK_COLUMNS, conditions
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let results = stmt.query_map(params_vec.as_slice(), |row| {
+        Ok(SearchResult {
+            chunk: chunk_from_row(row)?,
+            distance: Some(row.get(12)?),
+            score: 0.0,
+        })
+    })?;
+
+    results.collect::<Result<Vec<_>, _>>().map_err(StoreError::Database)
+}
+
+/// Chunks whose contents or paths match the words of a query, best BM25 score first
+///
+/// `min_similarity` applies when there is a query embedding to compare with.
+fn keyword_ranking(
+    conn: &Connection,
+    text: &str,
+    embedding_json: Option<&str>,
+    filesystem_hex_ids: &[String],
+    filters: &SearchFilters,
+    limit: usize,
+) -> Result<Vec<SearchResult>, StoreError> {
+    let Some(fts_query) = fts_query(text) else {
+        return Ok(Vec::new());
+    };
+
+    let limit = limit as i64;
+    let mut params_vec: Vec<&dyn ToSql> = vec![&fts_query, &limit];
+    let mut conditions = chunk_filter("c", filesystem_hex_ids, filters, &mut params_vec);
+    if let (Some(embedding_json), Some(min_similarity)) = (&embedding_json, &filters.min_similarity) {
+        params_vec.push(embedding_json);
+        params_vec.push(min_similarity);
+        conditions.push_str(&format!(
+            " AND 1 - (SELECT vec_distance_cosine(embedding, ?{}) FROM chunk_embeddings WHERE chunk_id = c.id) >= ?{}",
+            params_vec.len() - 1,
+            params_vec.len()
+        ));
+    }
+
+    let query = format!(
+        r#"
+        SELECT {}
+        FROM chunk_fts
+        INNER JOIN code_chunks c ON c.chunk_rowid = chunk_fts.rowid
+        WHERE chunk_fts MATCH ?1
+          AND {}
+        ORDER BY chunk_fts.rank
+        LIMIT ?2
+        "#,
+        CHUNK_COLUMNS, conditions
+    );
+
+    let mut stmt = conn.prepare(&query)?;
+    let results = stmt.query_map(params_vec.as_slice(), |row| {
+        Ok(SearchResult {
+            chunk: chunk_from_row(row)?,
+            distance: None,
+            score: 0.0,
+        })
+    })?;
+
+    results.collect::<Result<Vec<_>, _>>().map_err(StoreError::Database)
+}
+
+/// Turn free text into an FTS5 query matching any of its words
+///
+/// Each word is quoted, so punctuation can't be read as query syntax and an
+/// identifier like `reset_board_usage` matches as the phrase of its parts.
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"", word))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" OR "))
+    }
+}
+
+/// Insert a chunk's metadata and embedding
+fn insert_chunk(conn: &Connection, chunk: &CodeChunk, embedding: &[f32]) -> Result<(), StoreError> {
+    // Insert chunk metadata
+    conn.prepare_cached(
+        r#"INSERT OR REPLACE INTO code_chunks 
+           (id, filesystem_hex_id, file_path, start_line, end_line, content, language, kind,
+            symbol, node_kind, parent_symbol, signature)
+           VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)"#,
+    )?
+    .execute(params![
+        chunk.id,
+        chunk.filesystem_hex_id,
+        chunk.file_path,
+        chunk.start_line,
+        chunk.end_line,
+        chunk.content,
+        chunk.language,
+        chunk.kind,
+        chunk.symbol,
+        chunk.node_kind,
+        chunk.parent_symbol,
+        chunk.signature
+    ])?;
+
+    // Insert embedding as JSON array
+    let embedding_json = serde_json::to_string(embedding)
+        .map_err(|e| StoreError::Serialization(e.to_string()))?;
+
+    conn.prepare_cached(
+        r#"INSERT OR REPLACE INTO chunk_embeddings (chunk_id, filesystem_hex_id, language, kind, embedding)
+           VALUES (?1, ?2, ?3, ?4, ?5)"#,
+    )?
+    .execute(params![
+        chunk.id,
+        chunk.filesystem_hex_id,
+        chunk.language.as_deref().unwrap_or(""),
+        chunk.kind.as_deref().unwrap_or(""),
+        embedding_json
+    ])?;
+
+    Ok(())
+}
+
+/// Delete a file's chunks, embeddings and record, returning the chunks deleted
+fn delete_file(conn: &Connection, filesystem_hex_id: &str, file_path: &str) -> Result<usize, StoreError> {
+    // Get chunk IDs for this file
+    let mut stmt = conn.prepare_cached(
+        "SELECT id FROM code_chunks WHERE filesystem_hex_id = ?1 AND file_path = ?2"
+    )?;
+    let chunk_ids: Vec<String> = stmt
+        .query_map(params![filesystem_hex_id, file_path], |row| row.get(0))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Delete embeddings
+    for chunk_id in &chunk_ids {
+        conn.prepare_cached("DELETE FROM chunk_embeddings WHERE chunk_id = ?1")?
+            .execute(params![chunk_id])?;
+    }
+
+    // Delete chunks
+    let deleted = conn
+        .prepare_cached("DELETE FROM code_chunks WHERE filesystem_hex_id = ?1 AND file_path = ?2")?
+        .execute(params![filesystem_hex_id, file_path])?;
+
+    conn.prepare_cached("DELETE FROM indexed_files WHERE filesystem_hex_id = ?1 AND file_path = ?2")?
+        .execute(params![filesystem_hex_id, file_path])?;
+
+    Ok(deleted)
 }
 
+fn upsert_record(
+    conn: &Connection,
+    filesystem_hex_id: &str,
+    file_path: &str,
+    record: &FileRecord,
+) -> Result<(), StoreError> {
+    conn.prepare_cached(
+        r#"INSERT OR REPLACE INTO indexed_files
+           (filesystem_hex_id, file_path, content_hash, modified_ms, chunk_count)
+           VALUES (?1, ?2, ?3, ?4, ?5)"#,
+    )?
+    .execute(params![
+        filesystem_hex_id,
+        file_path,
+        record.content_hash,
+        record.modified_ms,
+        record.chunk_count as i64
+    ])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    use tempfile::TempDir;
+
+    /// A store in a temporary directory, initialized at `store_path(&dir)`
+    fn temp_store() -> (TempDir, VectorStore) {
+        let dir = TempDir::new().unwrap();
+        let store = VectorStore::new("test-model", 4);
+        store.initialize(store_path(&dir)).unwrap();
+        (dir, store)
+    }
+
+    fn store_path(dir: &TempDir) -> PathBuf {
+        dir.path().join("index.db")
+    }
+
+    fn chunk(file_path: &str) -> CodeChunk {
+        chunk_with(file_path, "fn main() {}")
+    }
+
+    fn chunk_with(file_path: &str, content: &str) -> CodeChunk {
+        CodeChunk {
+            id: Uuid::new_v4().to_string(),
+            filesystem_hex_id: "fs".to_string(),
+            file_path: file_path.to_string(),
+            start_line: 1,
+            end_line: 2,
+            content: content.to_string(),
+            language: Some("rust".to_string()),
+            kind: Some("function".to_string()),
+            symbol: None,
+            node_kind: Some("function_item".to_string()),
+            parent_symbol: None,
+            signature: None,
+        }
+    }
+
+    fn vector_query(embedding: &[f32]) -> SearchQuery {
+        SearchQuery {
+            embedding: Some(embedding.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    fn record(hash: &str) -> FileRecord {
+        FileRecord {
+            content_hash: hash.to_string(),
+            modified_ms: Some(1_700_000_000_000),
+            chunk_count: 1,
+        }
+    }
+
+    #[test]
+    fn test_file_records_follow_chunks() {
+        let (_dir, store) = temp_store();
+
+        let batch = StoreBatch {
+            replace_files: vec!["src/main.rs".to_string()],
+            chunks: vec![(chunk("src/main.rs"), vec![0.1, 0.2, 0.3, 0.4])],
+            records: vec![("src/main.rs".to_string(), record("aaa"))],
+        };
+        store.write_batch("fs", &batch).unwrap();
+        let records = StoreBatch {
+            records: vec![
+                ("src/lib.rs".to_string(), record("bbb")),
+                ("src/main.rs".to_string(), record("ccc")),
+            ],
+            ..Default::default()
+        };
+        store.write_batch("fs", &records).unwrap();
+
+        let records = store.get_file_records("fs").unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records["src/main.rs"], record("ccc"));
+        assert!(store.get_file_records("other").unwrap().is_empty());
+
+        assert_eq!(store.remove_file("fs", "src/main.rs").unwrap(), 1);
+        let records = store.get_file_records("fs").unwrap();
+        assert_eq!(records.keys().collect::<Vec<_>>(), vec!["src/lib.rs"]);
+
+        store.clear_filesystem("fs").unwrap();
+        assert!(store.get_file_records("fs").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_write_batch_replaces_file_chunks() {
+        let (_dir, store) = temp_store();
+
+        let first = StoreBatch {
+            replace_files: vec!["a.rs".to_string()],
+            chunks: vec![
+                (chunk("a.rs"), vec![0.1, 0.2, 0.3, 0.4]),
+                (chunk("a.rs"), vec![0.4, 0.3, 0.2, 0.1]),
+            ],
+            records: vec![("a.rs".to_string(), record("v1"))],
+        };
+        store.write_batch("fs", &first).unwrap();
+        assert_eq!(store.get_chunk_count("fs").unwrap(), 2);
+
+        let second = StoreBatch {
+            replace_files: vec!["a.rs".to_string()],
+            chunks: vec![(chunk("a.rs"), vec![0.1, 0.2, 0.3, 0.4])],
+            records: vec![("a.rs".to_string(), record("v2"))],
+        };
+        store.write_batch("fs", &second).unwrap();
+        assert_eq!(store.get_chunk_count("fs").unwrap(), 1);
+        assert_eq!(store.get_file_records("fs").unwrap()["a.rs"], record("v2"));
+
+        // A failing insert rolls back the whole batch, including the delete
+        let bad = StoreBatch {
+            replace_files: vec!["a.rs".to_string()],
+            chunks: vec![(chunk("a.rs"), vec![0.1, 0.2])],
+            records: Vec::new(),
+        };
+        assert!(store.write_batch("fs", &bad).is_err());
+        assert_eq!(store.get_chunk_count("fs").unwrap(), 1);
+        assert_eq!(store.get_file_records("fs").unwrap()["a.rs"], record("v2"));
+    }
+
+    #[test]
+    fn test_model_mismatch_is_refused() {
+        let (dir, store) = temp_store();
+        let path = store_path(&dir);
+        let batch = StoreBatch {
+            replace_files: vec!["a.rs".to_string()],
+            chunks: vec![(chunk("a.rs"), vec![0.1, 0.2, 0.3, 0.4])],
+            records: vec![("a.rs".to_string(), record("v1"))],
+        };
+        store.write_batch("fs", &batch).unwrap();
+        assert_eq!(
+            store.get_filesystem_model("fs").unwrap(),
+            Some(IndexedModel {
+                model: "test-model".to_string(),
+                dimension: 4
+            })
+        );
+        drop(store);
+
+        // Same dimension, different model: the index survives but is refused
+        let store = VectorStore::new("other-model", 4);
+        store.initialize(path.clone()).unwrap();
+        assert!(matches!(
+            store.write_batch("fs", &batch),
+            Err(StoreError::ModelMismatch { .. })
+        ));
+        for ids in [vec!["fs".to_string()], Vec::new()] {
+            assert!(matches!(
+                store.search(&vector_query(&[0.1, 0.2, 0.3, 0.4]), &ids, 5),
+                Err(StoreError::ModelMismatch { .. })
+            ));
+        }
+
+        // Clearing the hex lets it be indexed with the new model
+        store.clear_filesystem("fs").unwrap();
+        assert_eq!(store.get_filesystem_model("fs").unwrap(), None);
+        store.write_batch("fs", &batch).unwrap();
+        assert_eq!(store.search(&vector_query(&[0.1, 0.2, 0.3, 0.4]), &[], 5).unwrap().len(), 1);
+        drop(store);
+
+        // A new dimension rebuilds the embeddings table and drops every index
+        let store = VectorStore::new("wide-model", 8);
+        store.initialize(path.clone()).unwrap();
+        assert_eq!(store.get_chunk_count("fs").unwrap(), 0);
+        assert!(store.get_file_records("fs").unwrap().is_empty());
+        assert_eq!(store.get_filesystem_model("fs").unwrap(), None);
+        let wide = StoreBatch {
+            replace_files: Vec::new(),
+            chunks: vec![(chunk("a.rs"), vec![0.5; 8])],
+            records: Vec::new(),
+        };
+        store.write_batch("fs", &wide).unwrap();
+    }
+
+    #[test]
+    fn test_embeddings_gain_metadata_columns() {
+        let (dir, store) = temp_store();
+        let path = store_path(&dir);
+        drop(store);
+
+        // An embeddings table from before the metadata columns
+        let python = CodeChunk {
+            language: Some("python".to_string()),
+            ..chunk("app.py")
+        };
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            r#"
+            DROP TABLE chunk_embeddings;
+            CREATE VIRTUAL TABLE chunk_embeddings USING vec0(chunk_id TEXT PRIMARY KEY, embedding float[4]);
+            INSERT INTO indexed_models (filesystem_hex_id, model, dimension) VALUES ('fs', 'test-model', 4);
+            "#,
+        )
+        .unwrap();
+        conn.execute(
+            r#"INSERT INTO code_chunks (id, filesystem_hex_id, file_path, start_line, end_line, content, language)
+               VALUES (?1, 'fs', 'app.py', 1, 2, 'pass', 'python')"#,
+            params![python.id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO chunk_embeddings (chunk_id, embedding) VALUES (?1, '[0.1, 0.2, 0.3, 0.4]')",
+            params![python.id],
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = VectorStore::new("test-model", 4);
+        store.initialize(path.clone()).unwrap();
+        let query = SearchQuery {
+            filters: SearchFilters {
+                languages: vec!["python".to_string()],
+                ..Default::default()
+            },
+            ..vector_query(&[0.1, 0.2, 0.3, 0.4])
+        };
+        let results = store.search(&query, &["fs".to_string()], 5).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.id, python.id);
+    }
+
+    #[test]
+    fn test_keyword_index_survives_rekey_and_vacuum() {
+        let (dir, store) = temp_store();
+        let path = store_path(&dir);
+        drop(store);
+
+        // Chunks keyed by their text id alone, indexed on the implicit rowid
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            r#"
+            DROP TABLE chunk_fts;
+            DROP TABLE code_chunks;
+            CREATE TABLE code_chunks (
+                id TEXT PRIMARY KEY,
+                filesystem_hex_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                start_line INTEGER NOT NULL,
+                end_line INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                language TEXT,
+                created_at TEXT DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO code_chunks (id, filesystem_hex_id, file_path, start_line, end_line, content)
+                VALUES ('a', 'fs', 'a.rs', 1, 1, 'fn alpha() {}'),
+                       ('b', 'fs', 'b.rs', 1, 1, 'fn beta() {}');
+            INSERT INTO indexed_models (filesystem_hex_id, model, dimension) VALUES ('fs', 'test-model', 4);
+            "#,
+        )
+        .unwrap();
+        drop(conn);
+
+        let store = VectorStore::new("test-model", 4);
+        store.initialize(path.clone()).unwrap();
+        let ids = vec!["fs".to_string()];
+        let keyword = |text: &str| SearchQuery {
+            text: text.to_string(),
+            embedding: None,
+            mode: SearchMode::Keyword,
+            keyword_weight: 0.5,
+            filters: SearchFilters::default(),
+        };
+        let found = |text: &str| -> Vec<String> {
+            store
+                .search(&keyword(text), &ids, 5)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.chunk.id)
+                .collect()
+        };
+        assert_eq!(found("beta"), vec!["b"]);
+
+        // Deleting a chunk and vacuuming keeps the index pointing at the right rows
+        store.remove_file("fs", "a.rs").unwrap();
+        store.write_batch("fs", &StoreBatch {
+            replace_files: Vec::new(),
+            chunks: vec![(chunk_with("c.rs", "fn gamma() {}"), vec![1.0, 0.0, 0.0, 0.0])],
+            records: Vec::new(),
+        }).unwrap();
+        store.conn.lock().unwrap().as_ref().unwrap().execute_batch("VACUUM").unwrap();
+        assert_eq!(found("beta"), vec!["b"]);
+        assert_eq!(found("gamma").len(), 1);
+        assert!(found("alpha").is_empty());
+    }
+
+    #[test]
+    fn test_hybrid_search_finds_identifiers() {
+        let (_dir, store) = temp_store();
+        let reset = CodeChunk {
+            symbol: Some("reset_board_usage".to_string()),
+            parent_symbol: Some("Database".to_string()),
+            signature: Some("fn reset_board_usage(board: &str)".to_string()),
+            ..chunk_with("src/db.rs", "fn reset_board_usage(board: &str) {}")
+        };
+        let batch = StoreBatch {
+            replace_files: Vec::new(),
+            chunks: vec![
+                (chunk_with("src/board.rs", "fn add_board_usage(board: &str, tokens: u64) {}"), vec![1.0, 0.0, 0.0, 0.0]),
+                (chunk_with("src/usage.rs", "fn get_board_usage(board: &str) -> u64 {}"), vec![0.9, 0.1, 0.0, 0.0]),
+                (reset.clone(), vec![0.0, 0.0, 1.0, 0.0]),
+            ],
+            records: Vec::new(),
+        };
+        store.write_batch("fs", &batch).unwrap();
+
+        let ids = vec!["fs".to_string()];
+        let query = |mode, keyword_weight| SearchQuery {
+            text: "where is reset_board_usage?".to_string(),
+            embedding: Some(vec![1.0, 0.0, 0.0, 0.0]),
+            mode,
+            keyword_weight,
+            filters: SearchFilters::default(),
+        };
+        let top = |query: SearchQuery| -> Vec<String> {
+            store
+                .search(&query, &ids, 2)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.chunk.file_path)
+                .collect()
+        };
+
+        // Similar embeddings win a pure vector search
+        assert_eq!(top(query(SearchMode::Vector, 0.5)), vec!["src/board.rs", "src/usage.rs"]);
+        assert_eq!(top(query(SearchMode::Hybrid, 0.0)), vec!["src/board.rs", "src/usage.rs"]);
+
+        // The exact identifier only matches its own chunk
+        let keyword = SearchQuery {
+            embedding: None,
+            ..query(SearchMode::Keyword, 0.5)
+        };
+        let results = store.search(&keyword, &ids, 2).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.id, reset.id);
+        assert_eq!(results[0].chunk.symbol.as_deref(), Some("reset_board_usage"));
+        assert_eq!(results[0].chunk.parent_symbol.as_deref(), Some("Database"));
+        assert_eq!(results[0].chunk.signature, reset.signature);
+        assert_eq!(results[0].distance, None);
+
+        let results = store.search(&query(SearchMode::Hybrid, 0.5), &ids, 2).unwrap();
+        assert_eq!(results[0].chunk.id, reset.id);
+        assert!(results[0].distance.is_some());
+        assert!(results[0].score > results[1].score);
+
+        assert!(matches!(
+            store.search(&SearchQuery { embedding: None, ..query(SearchMode::Hybrid, 0.5) }, &ids, 2),
+            Err(StoreError::MissingEmbedding(SearchMode::Hybrid))
+        ));
+
+        // Removed chunks leave the keyword index too
+        store.remove_file("fs", "src/db.rs").unwrap();
+        assert!(store.search(&keyword, &ids, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_filters() {
+        let (_dir, store) = temp_store();
+        let python = CodeChunk {
+            language: Some("python".to_string()),
+            kind: Some("class".to_string()),
+            ..chunk_with("app/models.py", "class Board: pass")
+        };
+        let batch = StoreBatch {
+            replace_files: Vec::new(),
+            chunks: vec![
+                (chunk_with("src/board.rs", "fn board() {}"), vec![1.0, 0.0, 0.0, 0.0]),
+                (chunk_with("tests/board_test.rs", "fn board_test() {}"), vec![0.8, 0.6, 0.0, 0.0]),
+                (chunk_with("vendor/lib/board.rs", "fn vendored_board() {}"), vec![0.6, 0.8, 0.0, 0.0]),
+                (python, vec![0.0, 1.0, 0.0, 0.0]),
+            ],
+            records: Vec::new(),
+        };
+        store.write_batch("fs", &batch).unwrap();
+
+        let ids = vec!["fs".to_string()];
+        let search = |mode, filters| -> Vec<String> {
+            let query = SearchQuery {
+                text: "board".to_string(),
+                embedding: Some(vec![1.0, 0.0, 0.0, 0.0]),
+                mode,
+                keyword_weight: 0.5,
+                filters,
+            };
+            store
+                .search(&query, &ids, 10)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.chunk.file_path)
+                .collect()
+        };
+
+        for mode in [SearchMode::Vector, SearchMode::Keyword, SearchMode::Hybrid] {
+            let python = SearchFilters {
+                languages: vec!["python".to_string()],
+                ..Default::default()
+            };
+            assert_eq!(search(mode, python), vec!["app/models.py"]);
+
+            let classes = SearchFilters {
+                kinds: vec!["class".to_string()],
+                ..Default::default()
+            };
+            assert_eq!(search(mode, classes), vec!["app/models.py"]);
+
+            let tests = SearchFilters {
+                include: vec!["*_test.rs".to_string()],
+                ..Default::default()
+            };
+            assert_eq!(search(mode, tests), vec!["tests/board_test.rs"]);
+
+            // Excluding a directory skips everything under it
+            let mut found = search(
+                mode,
+                SearchFilters {
+                    include: vec!["*.rs".to_string()],
+                    exclude: vec!["vendor".to_string()],
+                    ..Default::default()
+                },
+            );
+            found.sort();
+            assert_eq!(found, vec!["src/board.rs", "tests/board_test.rs"]);
+        }
+
+        // Cosine similarities to the query are 1.0, 0.8, 0.6 and 0.0
+        let similar = SearchFilters {
+            min_similarity: Some(0.7),
+            ..Default::default()
+        };
+        assert_eq!(search(SearchMode::Vector, similar.clone()), vec!["src/board.rs", "tests/board_test.rs"]);
+        // Keyword-only hits are held to it too when there is a query embedding
+        let mut found = search(SearchMode::Hybrid, similar.clone());
+        found.sort();
+        assert_eq!(found, vec!["src/board.rs", "tests/board_test.rs"]);
+        assert_eq!(search(SearchMode::Keyword, similar).len(), 4);
+
+        // Metadata filters apply inside the KNN search, so the nearest chunk
+        // of a language is found even when it isn't among the nearest `limit`
+        let python = SearchQuery {
+            embedding: Some(vec![1.0, 0.0, 0.0, 0.0]),
+            filters: SearchFilters {
+                languages: vec!["python".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let results = store.search(&python, &ids, 1).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].chunk.file_path, "app/models.py");
+
+        let invalid = SearchQuery {
+            embedding: Some(vec![1.0, 0.0, 0.0, 0.0]),
+            filters: SearchFilters {
+                include: vec!["src/[".to_string()],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(matches!(store.search(&invalid, &ids, 10), Err(StoreError::InvalidFilter(_))));
+    }
+}
@@ -14,6 +14,12 @@ pub enum SupportedLanguage {
     JavaScript,
     Python,
     Go,
+    Java,
+    C,
+    Cpp,
+    CSharp,
+    Ruby,
+    Php,
 }
 
 impl SupportedLanguage {
@@ -26,6 +32,12 @@ impl SupportedLanguage {
             SupportedLanguage::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
             SupportedLanguage::Python => tree_sitter_python::LANGUAGE.into(),
             SupportedLanguage::Go => tree_sitter_go::LANGUAGE.into(),
+            SupportedLanguage::Java => tree_sitter_java::LANGUAGE.into(),
+            SupportedLanguage::C => tree_sitter_c::LANGUAGE.into(),
+            SupportedLanguage::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+            SupportedLanguage::CSharp => tree_sitter_c_sharp::LANGUAGE.into(),
+            SupportedLanguage::Ruby => tree_sitter_ruby::LANGUAGE.into(),
+            SupportedLanguage::Php => tree_sitter_php::LANGUAGE_PHP.into(),
         }
     }
 
@@ -47,6 +59,7 @@ impl SupportedLanguage {
             SupportedLanguage::TypeScript | SupportedLanguage::Tsx => &[
                 "function_declaration",
                 "class_declaration",
+                "abstract_class_declaration",
                 "interface_declaration",
                 "type_alias_declaration",
                 "enum_declaration",
@@ -73,6 +86,132 @@ impl SupportedLanguage {
                 "const_declaration",
                 "var_declaration",
             ],
+            SupportedLanguage::Java => &[
+                "class_declaration",
+                "interface_declaration",
+                "enum_declaration",
+                "record_declaration",
+                "annotation_type_declaration",
+            ],
+            SupportedLanguage::C => &[
+                "function_definition",
+                "struct_specifier",
+                "union_specifier",
+                "enum_specifier",
+                "type_definition",
+                // Prototypes, globals and `#define` constants are usually one
+                // line each; they're chunked with the lines around them
+                "preproc_function_def",
+            ],
+            SupportedLanguage::Cpp => &[
+                "function_definition",
+                "class_specifier",
+                "struct_specifier",
+                "union_specifier",
+                "enum_specifier",
+                "type_definition",
+                "alias_declaration",
+                "template_declaration",
+                "preproc_function_def",
+            ],
+            SupportedLanguage::CSharp => &[
+                "class_declaration",
+                "interface_declaration",
+                "struct_declaration",
+                "record_declaration",
+                "enum_declaration",
+                "delegate_declaration",
+            ],
+            SupportedLanguage::Ruby => &["method", "singleton_method", "class", "module"],
+            SupportedLanguage::Php => &[
+                "function_definition",
+                "class_declaration",
+                "interface_declaration",
+                "trait_declaration",
+                "enum_declaration",
+                "const_declaration",
+            ],
+        }
+    }
+
+    /// Get the node kinds that document or annotate the declaration after them
+    pub fn leading_kinds(&self) -> &'static [&'static str] {
+        match self {
+            SupportedLanguage::Rust => &["line_comment", "block_comment", "attribute_item"],
+            SupportedLanguage::TypeScript | SupportedLanguage::Tsx | SupportedLanguage::JavaScript => {
+                &["comment", "decorator"]
+            }
+            SupportedLanguage::Java => &["line_comment", "block_comment"],
+            // Java, C# and PHP attributes are part of the declaration they annotate
+            SupportedLanguage::Python
+            | SupportedLanguage::Go
+            | SupportedLanguage::C
+            | SupportedLanguage::Cpp
+            | SupportedLanguage::CSharp
+            | SupportedLanguage::Ruby
+            | SupportedLanguage::Php => &["comment"],
+        }
+    }
+
+    /// Get the node kinds whose members are chunked one by one when the
+    /// whole declaration is too large for a single chunk
+    pub fn container_kinds(&self) -> &'static [&'static str] {
+        match self {
+            SupportedLanguage::Rust => &["impl_item", "trait_item", "mod_item"],
+            SupportedLanguage::TypeScript | SupportedLanguage::Tsx => {
+                &["class_declaration", "abstract_class_declaration"]
+            }
+            SupportedLanguage::JavaScript => &["class_declaration"],
+            SupportedLanguage::Python => &["class_definition"],
+            SupportedLanguage::Go | SupportedLanguage::C => &[],
+            SupportedLanguage::Java => &["class_declaration", "interface_declaration", "record_declaration"],
+            SupportedLanguage::Cpp => &["class_specifier", "struct_specifier"],
+            SupportedLanguage::CSharp => &[
+                "class_declaration",
+                "interface_declaration",
+                "struct_declaration",
+                "record_declaration",
+            ],
+            SupportedLanguage::Ruby => &["class", "module"],
+            SupportedLanguage::Php => &["class_declaration", "interface_declaration", "trait_declaration"],
+        }
+    }
+
+    /// Get the node kinds in a container's body that become its members
+    pub fn member_kinds(&self, container_kind: &str) -> &'static [&'static str] {
+        match self {
+            // An inline module holds the same items as a file
+            SupportedLanguage::Rust if container_kind == "mod_item" => self.top_level_kinds(),
+            SupportedLanguage::Rust => &["function_item", "function_signature_item"],
+            SupportedLanguage::TypeScript | SupportedLanguage::Tsx => {
+                &["method_definition", "abstract_method_signature"]
+            }
+            SupportedLanguage::JavaScript => &["method_definition"],
+            SupportedLanguage::Python => &["function_definition", "decorated_definition", "class_definition"],
+            SupportedLanguage::Go | SupportedLanguage::C => &[],
+            SupportedLanguage::Java => &[
+                "method_declaration",
+                "constructor_declaration",
+                "class_declaration",
+                "interface_declaration",
+                "enum_declaration",
+                "record_declaration",
+            ],
+            // Method prototypes stay in the class outline
+            SupportedLanguage::Cpp => &["function_definition", "template_declaration"],
+            SupportedLanguage::CSharp => &[
+                "method_declaration",
+                "constructor_declaration",
+                "destructor_declaration",
+                "operator_declaration",
+                "class_declaration",
+                "interface_declaration",
+                "struct_declaration",
+                "record_declaration",
+                "enum_declaration",
+            ],
+            SupportedLanguage::Ruby => self.top_level_kinds(),
+            SupportedLanguage::Php => &["method_declaration"],
         }
     }
 
@@ -86,6 +225,13 @@ impl SupportedLanguage {
             "jsx" => Some(SupportedLanguage::JavaScript), // JSX uses JS parser
             "py" => Some(SupportedLanguage::Python),
             "go" => Some(SupportedLanguage::Go),
+            "java" => Some(SupportedLanguage::Java),
+            "c" => Some(SupportedLanguage::C),
+            // The C++ grammar also parses C, but the C grammar can't parse classes
+            "cpp" | "hpp" | "cc" | "cxx" | "h" => Some(SupportedLanguage::Cpp),
+            "cs" => Some(SupportedLanguage::CSharp),
+            "rb" => Some(SupportedLanguage::Ruby),
+            "php" => Some(SupportedLanguage::Php),
             _ => None,
         }
     }
@@ -99,6 +245,12 @@ impl SupportedLanguage {
             SupportedLanguage::JavaScript => "javascript",
             SupportedLanguage::Python => "python",
             SupportedLanguage::Go => "go",
+            SupportedLanguage::Java => "java",
+            SupportedLanguage::C => "c",
+            SupportedLanguage::Cpp => "cpp",
+            SupportedLanguage::CSharp => "csharp",
+            SupportedLanguage::Ruby => "ruby",
+            SupportedLanguage::Php => "php",
         }
     }
 }
@@ -115,11 +267,11 @@ pub struct ParsedSource {
 pub struct SemanticUnit {
     /// The kind of node (e.g., "function_item", "class_declaration")
     pub kind: String,
-    /// Start byte offset in the source
+    /// Start byte offset in the source, including leading comments and attributes
     pub start_byte: usize,
     /// End byte offset in the source
     pub end_byte: usize,
-    /// Start line (0-indexed)
+    /// Start line (0-indexed), including leading comments and attributes
     pub start_line: usize,
     /// End line (0-indexed)
     pub end_line: usize,
@@ -127,6 +279,50 @@ pub struct SemanticUnit {
     pub content: String,
     /// Optional name of the unit (function name, class name, etc.)
     pub name: Option<String>,
+    /// Language-independent kind of the unit (e.g., "function", "class")
+    pub category: Option<&'static str>,
+    /// Name of the enclosing declaration (e.g., a namespace), if any
+    pub parent: Option<String>,
+    /// The unit's declaration line, such as a function's name, parameters and return type
+    pub signature: Option<String>,
+    /// Signature of the container a member belongs to, which names it in the member's chunks
+    pub header: Option<String>,
+    /// Methods and other declarations in a container's body
+    pub members: Vec<SemanticUnit>,
+}
+
+impl SemanticUnit {
+    /// The unit's content with each member reduced to its signature, as
+    /// lines paired with the 0-indexed source line each starts on
+    pub fn outline_lines(&self) -> Vec<(usize, String)> {
+        let mut lines = vec![(self.start_line, String::new())];
+        let mut line = self.start_line;
+        let mut pos = 0;
+        for member in &self.members {
+            push_outline_text(&mut lines, &mut line, &self.content[pos..member.start_byte - self.start_byte]);
+            let signature = member.signature.as_deref().unwrap_or_default();
+            let signature_offset = member.content.find(signature).unwrap_or(0);
+            line = member.start_line + member.content[..signature_offset].matches('\n').count();
+            push_outline_text(&mut lines, &mut line, signature);
+            line = member.end_line;
+            pos = member.end_byte - self.start_byte;
+        }
+        push_outline_text(&mut lines, &mut line, &self.content[pos..]);
+        lines
+    }
+}
+
+/// Append `text` from source line `line` onwards to outline `lines`
+fn push_outline_text(lines: &mut Vec<(usize, String)>, line: &mut usize, text: &str) {
+    for (i, part) in text.split('\n').enumerate() {
+        if i > 0 {
+            *line += 1;
+            lines.push((*line, String::new()));
+        }
+        if let Some((_, last)) = lines.last_mut() {
+            last.push_str(part);
+        }
+    }
 }
 
 /// Parse source code with tree-sitter
@@ -147,12 +343,11 @@ pub fn parse_source(source: &str, language: SupportedLanguage) -> Option<ParsedS
 pub fn extract_semantic_units(parsed: &ParsedSource) -> Vec<SemanticUnit> {
     let mut units = Vec::new();
     let root_node = parsed.tree.root_node();
-    let top_level_kinds = parsed.language.top_level_kinds();
 
     // Walk the tree and extract top-level declarations
     let mut cursor = root_node.walk();
     for child in root_node.children(&mut cursor) {
-        collect_semantic_units(&child, &parsed.source, top_level_kinds, &mut units);
+        collect_semantic_units(&child, &parsed.source, parsed.language, None, &mut units);
     }
 
     units
@@ -162,40 +357,315 @@ pub fn extract_semantic_units(parsed: &ParsedSource) -> Vec<SemanticUnit> {
 fn collect_semantic_units(
     node: &Node,
     source: &str,
-    top_level_kinds: &[&str],
+    language: SupportedLanguage,
+    parent: Option<&str>,
     units: &mut Vec<SemanticUnit>,
 ) {
     // Check if this node is a top-level declaration
-    if top_level_kinds.contains(&node.kind()) {
-        let content = &source[node.byte_range()];
-        let name = extract_name(node, source);
-
-        units.push(SemanticUnit {
-            kind: node.kind().to_string(),
-            start_byte: node.start_byte(),
-            end_byte: node.end_byte(),
-            start_line: node.start_position().row,
-            end_line: node.end_position().row,
-            content: content.to_string(),
-            name,
-        });
-        return; // Don't recurse into top-level declarations
+    if language.top_level_kinds().contains(&node.kind()) {
+        units.push(build_unit(node, source, language, parent, None));
+        return; // Top-level declarations collect their own members
     }
 
+    // Named wrappers, like TypeScript namespaces, become the parent of what
+    // they contain; a C `#ifndef` guard's macro doesn't
+    let name = node
+        .child_by_field_name("name")
+        .filter(|_| !node.kind().starts_with("preproc_"))
+        .map(|name_node| &source[name_node.byte_range()]);
+    let parent = name.or(parent);
+
     // Recurse into children (e.g., to find decorated functions in Python)
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        collect_semantic_units(&child, source, top_level_kinds, units);
+        collect_semantic_units(&child, source, language, parent, units);
+    }
+}
+
+/// Build the unit for a declaration, with its members if it is a container
+fn build_unit(
+    node: &Node,
+    source: &str,
+    language: SupportedLanguage,
+    parent: Option<&str>,
+    header: Option<&str>,
+) -> SemanticUnit {
+    let name = extract_name(node, source);
+    let signature = extract_signature(node, source);
+
+    let mut members = Vec::new();
+    if let Some((container_kind, body)) = container_body(node, language) {
+        let member_kinds = language.member_kinds(container_kind);
+        let mut cursor = body.walk();
+        for child in body.named_children(&mut cursor) {
+            if member_kinds.contains(&child.kind()) {
+                members.push(build_unit(&child, source, language, name.as_deref(), signature.as_deref()));
+            }
+        }
+    }
+
+    let first = leading_node(node, source, language);
+
+    SemanticUnit {
+        kind: node.kind().to_string(),
+        start_byte: first.start_byte(),
+        end_byte: node.end_byte(),
+        start_line: first.start_position().row,
+        end_line: node.end_position().row,
+        content: source[first.start_byte()..node.end_byte()].to_string(),
+        name,
+        category: unit_category(node),
+        parent: parent.map(str::to_string),
+        signature,
+        header: header.map(str::to_string),
+        members,
+    }
+}
+
+/// Find the first of the comments, attributes and decorators directly above
+/// a declaration, or the declaration itself if there are none
+///
+/// A blank line, a comment trailing code, or a Rust inner doc comment (`//!`)
+/// ends the run.
+fn leading_node<'tree>(node: &Node<'tree>, source: &str, language: SupportedLanguage) -> Node<'tree> {
+    let leading_kinds = language.leading_kinds();
+    let mut first = *node;
+
+    while let Some(prev) = first.prev_sibling() {
+        if !leading_kinds.contains(&prev.kind()) || last_row(&prev) + 1 < first.start_position().row {
+            break;
+        }
+        let text = &source[prev.byte_range()];
+        if text.starts_with("//!") || text.starts_with("/*!") {
+            break;
+        }
+        if prev.prev_sibling().is_some_and(|before| last_row(&before) == prev.start_position().row) {
+            break;
+        }
+        first = prev;
+    }
+
+    first
+}
+
+/// The last row a node has text on; line comments can end at column 0 of the next row
+fn last_row(node: &Node) -> usize {
+    let end = node.end_position();
+    if end.column == 0 && end.row > node.start_position().row {
+        end.row - 1
+    } else {
+        end.row
+    }
+}
+
+/// Find the kind and body of a container declaration, looking through
+/// `export` and decorators
+fn container_body<'tree>(node: &Node<'tree>, language: SupportedLanguage) -> Option<(&'static str, Node<'tree>)> {
+    let declaration = match node.kind() {
+        "export_statement" => node.child_by_field_name("declaration")?,
+        "decorated_definition" => node.child_by_field_name("definition")?,
+        "template_declaration" => templated_declaration(node)?,
+        _ => *node,
+    };
+    let container_kind = language
+        .container_kinds()
+        .iter()
+        .find(|kind| **kind == declaration.kind())?;
+    Some((container_kind, declaration.child_by_field_name("body")?))
+}
+
+/// Classify a declaration as "function", "class", "impl", "struct", "enum",
+/// "trait", "interface", "type", "module", "constant", "variable" or "macro"
+fn unit_category(node: &Node) -> Option<&'static str> {
+    let category = match node.kind() {
+        "function_item"
+        | "function_signature_item"
+        | "function_declaration"
+        | "generator_function_declaration"
+        | "function_definition"
+        | "method_declaration"
+        | "method_definition"
+        | "abstract_method_signature"
+        | "constructor_declaration"
+        | "destructor_declaration"
+        | "operator_declaration"
+        | "method"
+        | "singleton_method" => "function",
+        "class_declaration"
+        | "abstract_class_declaration"
+        | "class_definition"
+        | "class_specifier"
+        | "record_declaration"
+        | "class" => "class",
+        "impl_item" => "impl",
+        "struct_item" | "struct_specifier" | "union_specifier" | "struct_declaration" => "struct",
+        "enum_item" | "enum_declaration" | "enum_specifier" => "enum",
+        "trait_item" | "trait_declaration" => "trait",
+        "interface_declaration" | "annotation_type_declaration" => "interface",
+        "type_item" | "type_alias_declaration" | "type_definition" | "alias_declaration" | "delegate_declaration" => {
+            "type"
+        }
+        "mod_item" | "module" => "module",
+        "const_item" | "static_item" | "const_declaration" => "constant",
+        "var_declaration" | "variable_declaration" => "variable",
+        "macro_definition" | "preproc_function_def" => "macro",
+        // C declares both prototypes and globals as `declaration`
+        "declaration" => {
+            let mut declarator = node.child_by_field_name("declarator");
+            while let Some(inner) = declarator.filter(|d| d.kind() != "function_declarator") {
+                declarator = inner.child_by_field_name("declarator");
+            }
+            if declarator.is_some() {
+                "function"
+            } else {
+                "variable"
+            }
+        }
+        // `const handler = () => {}` declares a function
+        "lexical_declaration" => {
+            let mut cursor = node.walk();
+            let is_function = node.named_children(&mut cursor).any(|declarator| {
+                declarator.child_by_field_name("value").is_some_and(|value| {
+                    matches!(value.kind(), "arrow_function" | "function_expression" | "function")
+                })
+            });
+            if is_function {
+                "function"
+            } else {
+                "variable"
+            }
+        }
+        // Go declares structs and interfaces as `type Name struct {...}`
+        "type_declaration" => {
+            let mut cursor = node.walk();
+            let spec_type = node
+                .named_children(&mut cursor)
+                .find(|child| child.kind() == "type_spec")
+                .and_then(|spec| spec.child_by_field_name("type"));
+            match spec_type.map(|t| t.kind()) {
+                Some("struct_type") => "struct",
+                Some("interface_type") => "interface",
+                _ => "type",
+            }
+        }
+        // Wrappers take the kind of what they wrap
+        "export_statement" => return node.child_by_field_name("declaration").and_then(|d| unit_category(&d)),
+        "decorated_definition" => return node.child_by_field_name("definition").and_then(|d| unit_category(&d)),
+        "template_declaration" => return templated_declaration(node).and_then(|d| unit_category(&d)),
+        _ => return None,
+    };
+    Some(category)
+}
+
+/// Longest signature kept, in characters
+const MAX_SIGNATURE_CHARS: usize = 200;
+
+/// Extract a unit's declaration line: the text before its body with
+/// whitespace collapsed, or its first line if it has no body
+fn extract_signature(node: &Node, source: &str) -> Option<String> {
+    // Decorators aren't part of the signature
+    if node.kind() == "decorated_definition" {
+        return node
+            .child_by_field_name("definition")
+            .and_then(|definition| extract_signature(&definition, source));
+    }
+
+    // `export function f() {}` keeps its `export`, but the body is the declaration's
+    let declaration = match node.kind() {
+        "export_statement" => node.child_by_field_name("declaration").unwrap_or(*node),
+        "template_declaration" => templated_declaration(node).unwrap_or(*node),
+        _ => *node,
+    };
+    let start = signature_start(node);
+    let header = match declaration.child_by_field_name("body") {
+        Some(body) => &source[start..body.start_byte()],
+        None => source[start..node.end_byte()].lines().next().unwrap_or(""),
+    };
+
+    // Join a parameter list split over lines back into one line
+    let signature = header
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .replace(" )", ")");
+    let signature = signature.trim_end_matches('{').trim_end();
+    if signature.is_empty() {
+        return None;
+    }
+    Some(signature.chars().take(MAX_SIGNATURE_CHARS).collect())
+}
+
+/// Find where a declaration's signature starts, after any Java annotations or
+/// C# and PHP attributes that open it
+fn signature_start(node: &Node) -> usize {
+    const ATTRIBUTE_KINDS: [&str; 3] = ["attribute_list", "annotation", "marker_annotation"];
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        // Java keeps annotations among its modifiers
+        if child.kind() == "modifiers" {
+            let mut modifier_cursor = child.walk();
+            let modifier = child
+                .children(&mut modifier_cursor)
+                .find(|modifier| !ATTRIBUTE_KINDS.contains(&modifier.kind()));
+            match modifier {
+                Some(modifier) => return modifier.start_byte(),
+                None => continue,
+            }
+        }
+        if !ATTRIBUTE_KINDS.contains(&child.kind()) {
+            return child.start_byte();
+        }
     }
+    node.start_byte()
+}
+
+/// The declaration a C++ `template <...>` introduces
+fn templated_declaration<'tree>(node: &Node<'tree>) -> Option<Node<'tree>> {
+    let mut cursor = node.walk();
+    let declaration = node.named_children(&mut cursor).last();
+    declaration.filter(|declaration| declaration.kind() != "template_parameter_list")
 }
 
 /// Extract the name of a semantic unit (function name, class name, etc.)
 fn extract_name(node: &Node, source: &str) -> Option<String> {
-    // Look for identifier or name child nodes
-    let name_field_names = ["name", "identifier"];
+    match node.kind() {
+        // Wrappers are named after what they wrap
+        "export_statement" => {
+            return node
+                .child_by_field_name("declaration")
+                .and_then(|declaration| extract_name(&declaration, source));
+        }
+        "decorated_definition" => {
+            return node
+                .child_by_field_name("definition")
+                .and_then(|definition| extract_name(&definition, source));
+        }
+        "template_declaration" => {
+            return templated_declaration(node).and_then(|declaration| extract_name(&declaration, source));
+        }
+        // `impl Trait for Type` is named after the type
+        "impl_item" => {
+            return node
+                .child_by_field_name("type")
+                .map(|type_node| source[type_node.byte_range()].to_string());
+        }
+        // The innermost C declarator, such as `Box::set` in `void Box::set(T v)`
+        "identifier" | "field_identifier" | "type_identifier" | "qualified_identifier" | "destructor_name"
+        | "operator_name" => return Some(source[node.byte_range()].to_string()),
+        _ => {}
+    }
+
+    // Look for identifier or name child nodes, or C's `*make(int a)` declarators
+    let name_field_names = ["name", "identifier", "declarator"];
 
     for field_name in name_field_names {
         if let Some(name_node) = node.child_by_field_name(field_name) {
+            if field_name == "declarator" {
+                return extract_name(&name_node, source);
+            }
             return Some(source[name_node.byte_range()].to_string());
         }
     }
@@ -208,7 +678,15 @@ fn extract_name(node: &Node, source: &str) -> Option<String> {
         }
     }
 
-    None
+    // `const a = 1` and Go's `type A struct` are named by their first declarator or spec
+    let mut cursor = node.walk();
+    let declarator = node.named_children(&mut cursor).find(|child| {
+        matches!(
+            child.kind(),
+            "variable_declarator" | "type_spec" | "const_spec" | "var_spec" | "const_element"
+        )
+    });
+    declarator.and_then(|declarator| extract_name(&declarator, source))
 }
 
 #[cfg(test)]
@@ -299,6 +777,481 @@ func (s *Server) Start() error {
         assert_eq!(units[1].kind, "method_declaration");
     }
 
+    #[test]
+    fn test_parse_java_class() {
+        let source = r#"
+package com.example.boards;
+
+import java.util.Map;
+
+/** Loads boards by id. */
+@Service
+public class BoardService extends Service {
+    private final Map<String, Board> cache;
+
+    public BoardService() {}
+
+    @Override
+    public Board load(String id) {
+        return cache.get(id);
+    }
+}
+
+interface Loader {
+    Board load(String id);
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Java).unwrap();
+        let units = extract_semantic_units(&parsed);
+
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].kind, "class_declaration");
+        assert_eq!(units[0].name.as_deref(), Some("BoardService"));
+        assert_eq!(units[0].signature.as_deref(), Some("public class BoardService extends Service"));
+        assert!(units[0].content.starts_with("/** Loads boards by id. */\n@Service"));
+        let members: Vec<_> = units[0].members.iter().map(|m| m.name.as_deref()).collect();
+        assert_eq!(members, vec![Some("BoardService"), Some("load")]);
+        assert_eq!(units[0].members[1].signature.as_deref(), Some("public Board load(String id)"));
+        assert_eq!(units[1].category, Some("interface"));
+        assert_eq!(units[1].members[0].signature.as_deref(), Some("Board load(String id);"));
+    }
+
+    #[test]
+    fn test_parse_c_functions() {
+        let source = r#"
+#ifndef BOARD_H
+#define BOARD_H
+
+#define MAX_BOARDS 16
+
+struct board {
+    int id;
+};
+
+typedef struct {
+    long tokens;
+} usage_t;
+
+static int *find_board(int id);
+
+/* Count boards in use */
+int count_boards(struct board **boards, int len) {
+    return len;
+}
+
+#endif
+"#;
+        let parsed = parse_source(source, SupportedLanguage::C).unwrap();
+        let units = extract_semantic_units(&parsed);
+
+        // Prototypes and `#define` constants are left to gap chunking
+        let names: Vec<_> = units.iter().map(|u| u.name.as_deref()).collect();
+        assert_eq!(names, vec![Some("board"), Some("usage_t"), Some("count_boards")]);
+        let categories: Vec<_> = units.iter().map(|u| u.category).collect();
+        assert_eq!(categories, vec![Some("struct"), Some("type"), Some("function")]);
+        // The include guard isn't a parent
+        assert!(units.iter().all(|u| u.parent.is_none()));
+        assert_eq!(units[2].signature.as_deref(), Some("int count_boards(struct board **boards, int len)"));
+        assert!(units[2].content.starts_with("/* Count boards in use */"));
+    }
+
+    #[test]
+    fn test_parse_cpp_class() {
+        let source = r#"
+namespace boards {
+
+template <typename T>
+class Cache : public Base {
+public:
+    T get(int id) const {
+        return items[id];
+    }
+    void clear();
+};
+
+void Cache::clear() {}
+
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Cpp).unwrap();
+        let units = extract_semantic_units(&parsed);
+
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].kind, "template_declaration");
+        assert_eq!(units[0].name.as_deref(), Some("Cache"));
+        assert_eq!(units[0].category, Some("class"));
+        assert_eq!(units[0].parent.as_deref(), Some("boards"));
+        assert_eq!(units[0].signature.as_deref(), Some("template <typename T> class Cache : public Base"));
+        assert_eq!(units[0].members.len(), 1);
+        assert_eq!(units[0].members[0].name.as_deref(), Some("get"));
+        assert_eq!(units[1].name.as_deref(), Some("Cache::clear"));
+    }
+
+    #[test]
+    fn test_parse_csharp_class() {
+        let source = r#"
+using System;
+
+namespace Boards
+{
+    /// <summary>Loads boards.</summary>
+    [Serializable]
+    public class BoardService : Service
+    {
+        public int Count { get; set; }
+
+        public BoardService() {}
+
+        public Board Load(string id) => cache[id];
+    }
+
+    public record Usage(long Tokens);
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::CSharp).unwrap();
+        let units = extract_semantic_units(&parsed);
+
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[0].name.as_deref(), Some("BoardService"));
+        assert_eq!(units[0].parent.as_deref(), Some("Boards"));
+        assert_eq!(units[0].signature.as_deref(), Some("public class BoardService : Service"));
+        assert!(units[0].content.starts_with("/// <summary>Loads boards.</summary>"));
+        let members: Vec<_> = units[0].members.iter().map(|m| m.name.as_deref()).collect();
+        assert_eq!(members, vec![Some("BoardService"), Some("Load")]);
+        assert_eq!(units[0].members[1].signature.as_deref(), Some("public Board Load(string id)"));
+        assert_eq!(units[1].category, Some("class"));
+    }
+
+    #[test]
+    fn test_parse_ruby_module() {
+        let source = r#"
+require "json"
+
+# Board storage
+module Boards
+  class Store < Base
+    attr_reader :cache
+
+    def load(id)
+      cache[id]
+    end
+
+    def self.open(path)
+      new(path)
+    end
+  end
+end
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Ruby).unwrap();
+        let units = extract_semantic_units(&parsed);
+
+        assert_eq!(units.len(), 1);
+        assert_eq!(units[0].category, Some("module"));
+        assert!(units[0].content.starts_with("# Board storage"));
+        let store = &units[0].members[0];
+        assert_eq!(store.name.as_deref(), Some("Store"));
+        assert_eq!(store.signature.as_deref(), Some("class Store < Base"));
+        assert_eq!(store.parent.as_deref(), Some("Boards"));
+        let methods: Vec<_> = store.members.iter().map(|m| m.signature.as_deref()).collect();
+        assert_eq!(methods, vec![Some("def load(id)"), Some("def self.open(path)")]);
+        assert_eq!(store.members[1].parent.as_deref(), Some("Store"));
+    }
+
+    #[test]
+    fn test_parse_php_class() {
+        let source = r#"<?php
+
+namespace App\Models;
+
+use Illuminate\Database\Eloquent\Model;
+
+/** A board's owner */
+#[Table('users')]
+final class User extends Model
+{
+    private int $tokens = 0;
+
+    public function addUsage(int $tokens): void
+    {
+        $this->tokens += $tokens;
+    }
+}
+
+function helper($value)
+{
+    return $value;
+}
+
+const LIMIT = 10;
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Php).unwrap();
+        let units = extract_semantic_units(&parsed);
+
+        assert_eq!(units.len(), 3);
+        assert_eq!(units[0].name.as_deref(), Some("User"));
+        assert_eq!(units[0].signature.as_deref(), Some("final class User extends Model"));
+        assert!(units[0].content.starts_with("/** A board's owner */\n#[Table('users')]"));
+        assert_eq!(units[0].members[0].name.as_deref(), Some("addUsage"));
+        assert_eq!(
+            units[0].members[0].signature.as_deref(),
+            Some("public function addUsage(int $tokens): void")
+        );
+        assert_eq!(units[1].name.as_deref(), Some("helper"));
+        assert_eq!(units[2].name.as_deref(), Some("LIMIT"));
+        assert_eq!(units[2].category, Some("constant"));
+    }
+
+    #[test]
+    fn test_unit_categories() {
+        let source = r#"
+struct Board;
+
+impl Board {}
+
+trait Usage {}
+
+const LIMIT: u32 = 10;
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Rust).unwrap();
+        let categories: Vec<_> = extract_semantic_units(&parsed).iter().map(|u| u.category).collect();
+        assert_eq!(categories, vec![Some("struct"), Some("impl"), Some("trait"), Some("constant")]);
+
+        let source = r#"
+export const handler = async () => {};
+export const LIMIT = 10;
+export interface Board {}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::TypeScript).unwrap();
+        let categories: Vec<_> = extract_semantic_units(&parsed).iter().map(|u| u.category).collect();
+        assert_eq!(categories, vec![Some("function"), Some("variable"), Some("interface")]);
+
+        let source = r#"
+@dataclass
+class Board:
+    pass
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Python).unwrap();
+        assert_eq!(extract_semantic_units(&parsed)[0].category, Some("class"));
+
+        let source = r#"
+type Server struct {
+    port int
+}
+
+type Handler interface {
+    Serve()
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Go).unwrap();
+        let categories: Vec<_> = extract_semantic_units(&parsed).iter().map(|u| u.category).collect();
+        assert_eq!(categories, vec![Some("struct"), Some("interface")]);
+    }
+
+    #[test]
+    fn test_symbol_names_and_signatures() {
+        let source = r#"
+impl Display for Database {
+    fn fmt(&self) {}
+}
+
+pub fn add_board_usage(
+    &self,
+    board_id: &str,
+) -> Result<(), DbError> {
+    Ok(())
+}
+
+const LIMIT: u32 = 10;
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Rust).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert_eq!(units[0].name.as_deref(), Some("Database"));
+        assert_eq!(units[0].signature.as_deref(), Some("impl Display for Database"));
+        assert_eq!(units[1].name.as_deref(), Some("add_board_usage"));
+        assert_eq!(
+            units[1].signature.as_deref(),
+            Some("pub fn add_board_usage(&self, board_id: &str) -> Result<(), DbError>")
+        );
+        assert_eq!(units[2].signature.as_deref(), Some("const LIMIT: u32 = 10;"));
+        assert!(units.iter().all(|u| u.parent.is_none()));
+
+        let source = r#"
+namespace Billing {
+    export function resetUsage(boardId: string): void {}
+}
+
+export const handler = async () => {};
+"#;
+        let parsed = parse_source(source, SupportedLanguage::TypeScript).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert_eq!(units[0].name.as_deref(), Some("resetUsage"));
+        assert_eq!(units[0].parent.as_deref(), Some("Billing"));
+        assert_eq!(
+            units[0].signature.as_deref(),
+            Some("export function resetUsage(boardId: string): void")
+        );
+        assert_eq!(units[1].name.as_deref(), Some("handler"));
+        assert_eq!(units[1].parent, None);
+
+        let source = r#"
+@app.route("/")
+def index(request):
+    return "ok"
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Python).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert_eq!(units[0].name.as_deref(), Some("index"));
+        assert_eq!(units[0].signature.as_deref(), Some("def index(request):"));
+
+        let source = r#"
+type Server struct {
+    port int
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Go).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert_eq!(units[0].name.as_deref(), Some("Server"));
+        assert_eq!(units[0].signature.as_deref(), Some("type Server struct"));
+    }
+
+    #[test]
+    fn test_container_members() {
+        let source = r#"
+impl Database {
+    const LIMIT: u32 = 10;
+
+    pub fn add_board_usage(&self, tokens: u64) {
+        self.usage += tokens;
+    }
+
+    fn reset(&mut self) {}
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Rust).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert_eq!(units.len(), 1);
+
+        let members = &units[0].members;
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name.as_deref(), Some("add_board_usage"));
+        assert_eq!(members[0].parent.as_deref(), Some("Database"));
+        assert_eq!(members[0].header.as_deref(), Some("impl Database"));
+        assert_eq!(members[0].start_line, 4);
+        assert_eq!(members[1].name.as_deref(), Some("reset"));
+        let outline = units[0].outline_lines();
+        let text: Vec<&str> = outline.iter().map(|(_, line)| line.as_str()).collect();
+        assert_eq!(
+            text.join("\n"),
+            "impl Database {\n    const LIMIT: u32 = 10;\n\n    pub fn add_board_usage(&self, tokens: u64)\n\n    fn reset(&mut self)\n}"
+        );
+        let lines: Vec<usize> = outline.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, vec![1, 2, 3, 4, 7, 8, 9]);
+
+        let source = r#"
+export class BoardService extends Service {
+    private cache = new Map();
+
+    async load(id: string): Promise<Board> {
+        return this.cache.get(id);
+    }
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::TypeScript).unwrap();
+        let units = extract_semantic_units(&parsed);
+        let members = &units[0].members;
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].name.as_deref(), Some("load"));
+        assert_eq!(members[0].category, Some("function"));
+        assert_eq!(members[0].header.as_deref(), Some("export class BoardService extends Service"));
+
+        let source = r#"
+class Board:
+    class Meta:
+        def ordering(self):
+            pass
+
+    @property
+    def usage(self):
+        return 0
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Python).unwrap();
+        let units = extract_semantic_units(&parsed);
+        let members = &units[0].members;
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].name.as_deref(), Some("Meta"));
+        assert_eq!(members[0].members[0].parent.as_deref(), Some("Meta"));
+        assert_eq!(members[1].name.as_deref(), Some("usage"));
+        assert_eq!(members[1].signature.as_deref(), Some("def usage(self):"));
+    }
+
+    #[test]
+    fn test_leading_docs_and_attributes() {
+        let source = r#"//! Board storage
+
+// Not about the struct
+
+/// Usage totals for a board
+#[derive(Debug, Clone)]
+struct Usage {
+    tokens: u64,
+}
+
+impl Usage {
+    /// Add tokens to the total
+    #[inline]
+    fn add(&mut self, tokens: u64) {}
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Rust).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert!(units[0].content.starts_with("/// Usage totals for a board\n#[derive(Debug, Clone)]\nstruct Usage"));
+        assert_eq!(units[0].start_line, 4);
+        assert_eq!(units[0].signature.as_deref(), Some("struct Usage"));
+        let method = &units[1].members[0];
+        assert!(method.content.starts_with("/// Add tokens to the total\n    #[inline]\n    fn add"));
+        assert_eq!(method.start_line, 11);
+
+        let source = r#"
+/**
+ * Reset a board's usage.
+ * @param boardId the board
+ */
+export function resetUsage(boardId: string) {}
+
+let total = 0; // running total
+function add() {}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::TypeScript).unwrap();
+        let units = extract_semantic_units(&parsed);
+        let reset = units.iter().find(|u| u.name.as_deref() == Some("resetUsage")).unwrap();
+        assert!(reset.content.starts_with("/**\n * Reset a board's usage."));
+        assert_eq!(reset.start_line, 1);
+        // A comment trailing code stays with that code
+        let add = units.iter().find(|u| u.name.as_deref() == Some("add")).unwrap();
+        assert!(add.content.starts_with("function add"));
+
+        let source = r#"
+# Serve the index page
+@app.route("/")
+def index(request):
+    return "ok"
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Python).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert!(units[0].content.starts_with("# Serve the index page\n@app.route"));
+
+        let source = r#"
+// Start listens on the server's port.
+func (s *Server) Start() error {
+    return nil
+}
+"#;
+        let parsed = parse_source(source, SupportedLanguage::Go).unwrap();
+        let units = extract_semantic_units(&parsed);
+        assert!(units[0].content.starts_with("// Start listens"));
+        assert_eq!(units[0].start_line, 1);
+    }
+
     #[test]
     fn test_from_extension() {
         assert_eq!(SupportedLanguage::from_extension("rs"), Some(SupportedLanguage::Rust));
@@ -306,6 +1259,12 @@ func (s *Server) Start() error {
         assert_eq!(SupportedLanguage::from_extension("tsx"), Some(SupportedLanguage::Tsx));
         assert_eq!(SupportedLanguage::from_extension("py"), Some(SupportedLanguage::Python));
         assert_eq!(SupportedLanguage::from_extension("go"), Some(SupportedLanguage::Go));
+        assert_eq!(SupportedLanguage::from_extension("java"), Some(SupportedLanguage::Java));
+        assert_eq!(SupportedLanguage::from_extension("h"), Some(SupportedLanguage::Cpp));
+        assert_eq!(SupportedLanguage::from_extension("hpp"), Some(SupportedLanguage::Cpp));
+        assert_eq!(SupportedLanguage::from_extension("cs"), Some(SupportedLanguage::CSharp));
+        assert_eq!(SupportedLanguage::from_extension("rb"), Some(SupportedLanguage::Ruby));
+        assert_eq!(SupportedLanguage::from_extension("php"), Some(SupportedLanguage::Php));
         assert_eq!(SupportedLanguage::from_extension("unknown"), None);
     }
 }
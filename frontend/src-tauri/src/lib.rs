@@ -1,14 +1,22 @@
 mod commands;
 mod database;
 mod db_commands;
+mod files;
 mod indexer;
+mod pty;
+mod rbac;
+mod sandbox;
+mod watcher;
 
+use commands::ShellState;
 use database::Database;
 use indexer::commands::IndexerState;
-use indexer::embedder::Embedder;
+use indexer::provider;
 use indexer::store::VectorStore;
+use pty::commands::PtyState;
 use std::sync::Arc;
 use tauri::Manager;
+use watcher::WatcherState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -31,33 +39,78 @@ pub fn run() {
                 .expect("Failed to initialize database");
             app.manage(db);
 
-            // Initialize indexer with persistent cache for model files
+            // Registry of running shell processes
+            app.manage(ShellState::default());
+
+            // Interactive PTY sessions for shell hexes
+            app.manage(PtyState::default());
+
+            // Filesystem watches for filesystem hex roots
+            app.manage(WatcherState::default());
+
+            // Initialize indexer with the configured embedding provider and a
+            // persistent cache for model files
             let model_cache_dir = app_data_dir.join("models");
             std::fs::create_dir_all(&model_cache_dir)
                 .expect("Failed to create model cache directory");
-            let embedder = Arc::new(Embedder::with_cache_dir(model_cache_dir));
-            let store = Arc::new(VectorStore::new(384)); // AllMiniLML6V2 dimension
+            let (embedder, settings_error) = match provider::from_settings(&app.state::<Database>(), model_cache_dir) {
+                Ok(embedder) => (embedder, None),
+                Err(e) => {
+                    eprintln!("[Indexer] Embedding settings are invalid: {}", e);
+                    let unavailable: Arc<dyn provider::EmbeddingProvider> =
+                        Arc::new(provider::UnavailableProvider::new(e.clone()));
+                    (unavailable, Some(e.to_string()))
+                }
+            };
+            let store = Arc::new(VectorStore::new(embedder.model_name(), embedder.embedding_dim()));
 
-            // Initialize vector store with database in app data dir
-            let indexer_db_path = app_data_dir.join("indexer.db");
-            store.initialize(indexer_db_path)
-                .expect("Failed to initialize vector store");
+            // Initialize vector store with database in app data dir. Without a
+            // model the store stays closed, so existing indexes aren't dropped
+            // for a dimension mismatch.
+            if settings_error.is_none() {
+                let indexer_db_path = app_data_dir.join("indexer.db");
+                store.initialize(indexer_db_path)
+                    .expect("Failed to initialize vector store");
+            }
 
             let indexer_state = IndexerState {
                 embedder,
                 store,
                 config: indexer::IndexerConfig::default(),
+                jobs: indexer::jobs::IndexJobs::default(),
+                settings_error,
             };
             app.manage(indexer_state);
 
+            // Run queued indexing jobs off the async runtime
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                handle.state::<IndexerState>().run_jobs();
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // Shell/File commands
             commands::execute_shell,
+            commands::shell_kill,
+            commands::shell_signal,
+            commands::shell_list_running,
+            // PTY commands
+            pty::commands::pty_open,
+            pty::commands::pty_attach,
+            pty::commands::pty_write,
+            pty::commands::pty_resize,
+            pty::commands::pty_read,
+            pty::commands::pty_close,
+            pty::commands::pty_list_sessions,
             commands::read_file,
+            commands::read_file_range,
             commands::write_file,
+            commands::apply_patch,
             commands::list_directory,
+            commands::list_tree,
+            commands::search_files,
             commands::file_exists,
             commands::delete_file,
             commands::delete_directory,
@@ -90,12 +143,19 @@ pub fn run() {
             // Indexer commands
             indexer::commands::indexer_initialize,
             indexer::commands::indexer_is_ready,
+            indexer::commands::indexer_settings_error,
             indexer::commands::indexer_index_file,
             indexer::commands::indexer_index_directory,
+            indexer::commands::indexer_cancel,
+            indexer::commands::indexer_list_jobs,
             indexer::commands::indexer_search,
             indexer::commands::indexer_remove_file,
             indexer::commands::indexer_clear_filesystem,
             indexer::commands::indexer_get_stats,
+            // Watcher commands
+            watcher::commands::watcher_start,
+            watcher::commands::watcher_stop,
+            watcher::commands::watcher_list,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
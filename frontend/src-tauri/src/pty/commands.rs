@@ -0,0 +1,173 @@
+//! Tauri commands for interactive PTY sessions
+//!
+//! Exposes PTY sessions to the frontend via Tauri IPC.
+
+use super::{PtyError, PtyEvent, PtyOpenRequest, PtyReadResult, PtySession, PtySessionInfo, EXITED_SESSION_TTL};
+use crate::database::Database;
+use crate::rbac::{self, Permission};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use tauri::ipc::Channel;
+use tauri::State;
+
+/// Default number of bytes returned by `pty_read`
+const DEFAULT_READ_BYTES: usize = 64 * 1024;
+
+/// PTY session registry managed by Tauri
+#[derive(Default)]
+pub struct PtyState {
+    sessions: Mutex<HashMap<String, Arc<PtySession>>>,
+}
+
+impl PtyState {
+    /// Lock the registry, first removing sessions that exited more than
+    /// `EXITED_SESSION_TTL` ago
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, Arc<PtySession>>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, session| !session.exited_longer_than(EXITED_SESSION_TTL));
+        sessions
+    }
+
+    fn get(&self, session_id: &str) -> Result<Arc<PtySession>, String> {
+        self.sessions()
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| PtyError::SessionNotFound(session_id.to_string()).to_string())
+    }
+
+    /// Look up a session, checking the calling agent's permission on its shell hex
+    fn get_authorized(
+        &self,
+        db: &Database,
+        session_id: &str,
+        agent_hex_id: Option<&str>,
+        permission: Permission,
+    ) -> Result<Arc<PtySession>, String> {
+        let session = self.get(session_id)?;
+        rbac::authorize(db, agent_hex_id, &session.shell_hex_id, permission)
+            .map_err(|e| e.to_string())?;
+        Ok(session)
+    }
+}
+
+/// Open a new PTY session for a shell hex
+#[tauri::command]
+pub fn pty_open(
+    state: State<'_, PtyState>,
+    db: State<'_, Database>,
+    request: PtyOpenRequest,
+    on_event: Channel<PtyEvent>,
+) -> Result<PtySessionInfo, String> {
+    rbac::authorize(
+        &db,
+        request.agent_hex_id.as_deref(),
+        &request.shell_hex_id,
+        Permission::Execute,
+    )
+    .map_err(|e| e.to_string())?;
+    let session = PtySession::open(request, on_event).map_err(|e| e.to_string())?;
+    let info = session.info();
+
+    state.sessions().insert(session.id.clone(), session);
+
+    Ok(info)
+}
+
+/// Stream an existing session's live output to another channel
+#[tauri::command]
+pub fn pty_attach(
+    state: State<'_, PtyState>,
+    db: State<'_, Database>,
+    session_id: String,
+    agent_hex_id: Option<String>,
+    on_event: Channel<PtyEvent>,
+) -> Result<PtySessionInfo, String> {
+    let session =
+        state.get_authorized(&db, &session_id, agent_hex_id.as_deref(), Permission::Read)?;
+    session.subscribe(on_event);
+    Ok(session.info())
+}
+
+/// Write input to a session's stdin
+#[tauri::command]
+pub fn pty_write(
+    state: State<'_, PtyState>,
+    db: State<'_, Database>,
+    session_id: String,
+    agent_hex_id: Option<String>,
+    data: String,
+) -> Result<(), String> {
+    state
+        .get_authorized(&db, &session_id, agent_hex_id.as_deref(), Permission::Execute)?
+        .write(data.as_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// Resize a session's terminal
+#[tauri::command]
+pub fn pty_resize(
+    state: State<'_, PtyState>,
+    db: State<'_, Database>,
+    session_id: String,
+    agent_hex_id: Option<String>,
+    cols: u16,
+    rows: u16,
+) -> Result<(), String> {
+    state
+        .get_authorized(&db, &session_id, agent_hex_id.as_deref(), Permission::Execute)?
+        .resize(cols, rows)
+        .map_err(|e| e.to_string())
+}
+
+/// Read buffered output from a session, starting at `offset`
+#[tauri::command]
+pub fn pty_read(
+    state: State<'_, PtyState>,
+    db: State<'_, Database>,
+    session_id: String,
+    agent_hex_id: Option<String>,
+    offset: Option<u64>,
+    max_bytes: Option<usize>,
+) -> Result<PtyReadResult, String> {
+    let session =
+        state.get_authorized(&db, &session_id, agent_hex_id.as_deref(), Permission::Read)?;
+    Ok(session.read(offset, max_bytes.unwrap_or(DEFAULT_READ_BYTES)))
+}
+
+/// Kill a session's shell and remove it
+#[tauri::command]
+pub fn pty_close(
+    state: State<'_, PtyState>,
+    db: State<'_, Database>,
+    session_id: String,
+    agent_hex_id: Option<String>,
+) -> Result<bool, String> {
+    if let Ok(session) = state.get(&session_id) {
+        rbac::authorize(&db, agent_hex_id.as_deref(), &session.shell_hex_id, Permission::Execute)
+            .map_err(|e| e.to_string())?;
+    }
+    let session = state.sessions().remove(&session_id);
+    match session {
+        Some(session) => {
+            session.kill().map_err(|e| e.to_string())?;
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// List sessions, optionally only those belonging to one shell hex
+#[tauri::command]
+pub fn pty_list_sessions(
+    state: State<'_, PtyState>,
+    shell_hex_id: Option<String>,
+) -> Vec<PtySessionInfo> {
+    let sessions = state.sessions();
+    let mut infos: Vec<PtySessionInfo> = sessions
+        .values()
+        .filter(|s| shell_hex_id.as_ref().map_or(true, |id| &s.shell_hex_id == id))
+        .map(|s| s.info())
+        .collect();
+    infos.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    infos
+}
@@ -0,0 +1,459 @@
+//! Interactive PTY sessions for the Shell tool
+//!
+//! Runs shells attached to a pseudo-terminal so agents can drive REPLs,
+//! interactive prompts and anything that checks `isatty`. Sessions belong to a
+//! shell hex, so every agent with access to that hex can share the same terminal.
+
+pub mod commands;
+
+use chrono::Utc;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Bytes of output retained per session for `pty_read`
+const SCROLLBACK_BYTES: usize = 1024 * 1024;
+/// How long an exited session stays readable before it is removed
+const EXITED_SESSION_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Error, Debug)]
+pub enum PtyError {
+    #[error("Failed to open PTY: {0}")]
+    OpenError(String),
+    #[error("PTY session not found: {0}")]
+    SessionNotFound(String),
+    #[error("PTY session has exited: {0}")]
+    Exited(String),
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Event streamed to subscribers of a session
+#[derive(Debug, Clone, Serialize)]
+pub enum PtyEvent {
+    /// Raw bytes written by the terminal
+    Output(Vec<u8>),
+    /// The shell exited with the given code
+    Exit(Option<u32>),
+}
+
+/// Options for opening a new session
+#[derive(Debug, Clone, Deserialize)]
+pub struct PtyOpenRequest {
+    pub shell_hex_id: String,
+    /// Agent hex opening the session; it needs execute permission on the shell hex
+    pub agent_hex_id: Option<String>,
+    pub cwd: Option<String>,
+    /// Program to run instead of the user's default shell
+    pub shell: Option<String>,
+    pub cols: Option<u16>,
+    pub rows: Option<u16>,
+}
+
+/// Public description of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtySessionInfo {
+    pub session_id: String,
+    pub shell_hex_id: String,
+    pub cwd: Option<String>,
+    pub cols: u16,
+    pub rows: u16,
+    pub started_at: String,
+    pub exited: bool,
+    pub exit_code: Option<u32>,
+}
+
+/// Output read from a session's scrollback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PtyReadResult {
+    /// Output since the requested offset, decoded lossily as UTF-8. A
+    /// character cut off at the end is left for the next read.
+    pub data: String,
+    /// Offset to pass to the next read
+    pub next_offset: u64,
+    /// Whether output before `next_offset - data.len()` was dropped from scrollback
+    pub dropped: bool,
+    pub exited: bool,
+    pub exit_code: Option<u32>,
+}
+
+/// Session output retained in a bounded buffer, addressed by absolute byte offset
+struct Scrollback {
+    data: VecDeque<u8>,
+    /// Absolute offset of the first retained byte
+    start_offset: u64,
+    capacity: usize,
+}
+
+impl Scrollback {
+    fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::new(),
+            start_offset: 0,
+            capacity,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.data.extend(bytes);
+        let overflow = self.data.len().saturating_sub(self.capacity);
+        if overflow > 0 {
+            self.data.drain(..overflow);
+            self.start_offset += overflow as u64;
+        }
+    }
+
+    fn end_offset(&self) -> u64 {
+        self.start_offset + self.data.len() as u64
+    }
+
+    /// Read up to `max_bytes` starting at `offset`; returns (bytes, next offset, dropped)
+    fn read_from(&self, offset: u64, max_bytes: usize) -> (Vec<u8>, u64, bool) {
+        let dropped = offset < self.start_offset;
+        let start = offset.clamp(self.start_offset, self.end_offset());
+        let skip = (start - self.start_offset) as usize;
+        let bytes: Vec<u8> = self.data.iter().skip(skip).take(max_bytes).copied().collect();
+        let next_offset = start + bytes.len() as u64;
+        (bytes, next_offset, dropped)
+    }
+
+    /// Read up to `max_bytes` starting at `offset` as text; returns (text, next offset, dropped)
+    ///
+    /// A character split by `max_bytes` or by the shell's writes is left to the
+    /// next read, unless the rest of it can never arrive: the shell has exited,
+    /// or the character alone is longer than `max_bytes`.
+    fn read_text(&self, offset: u64, max_bytes: usize, exited: bool) -> (String, u64, bool) {
+        let (mut bytes, mut next_offset, dropped) = self.read_from(offset, max_bytes);
+
+        let tail = incomplete_utf8_tail(&bytes);
+        let hold_back = if bytes.len() == max_bytes {
+            tail < bytes.len()
+        } else {
+            !exited
+        };
+        if tail > 0 && hold_back {
+            bytes.truncate(bytes.len() - tail);
+            next_offset -= tail as u64;
+        }
+
+        (String::from_utf8_lossy(&bytes).into_owned(), next_offset, dropped)
+    }
+}
+
+/// Exit state of a session's shell
+#[derive(Default)]
+struct ExitState {
+    exited_at: Option<Instant>,
+    exit_code: Option<u32>,
+}
+
+impl ExitState {
+    fn exited(&self) -> bool {
+        self.exited_at.is_some()
+    }
+
+    /// Whether the shell exited more than `ttl` ago
+    fn expired(&self, ttl: Duration) -> bool {
+        self.exited_at.is_some_and(|at| at.elapsed() > ttl)
+    }
+}
+
+/// Length of a UTF-8 character cut off at the end of `bytes`, if any
+fn incomplete_utf8_tail(bytes: &[u8]) -> usize {
+    for (len, &byte) in bytes.iter().rev().take(3).enumerate() {
+        let width = match byte {
+            0b1000_0000..=0b1011_1111 => continue,
+            0b1100_0000..=0b1101_1111 => 2,
+            0b1110_0000..=0b1110_1111 => 3,
+            0b1111_0000..=0b1111_0111 => 4,
+            _ => return 0,
+        };
+        return if len + 1 < width { len + 1 } else { 0 };
+    }
+    0
+}
+
+/// A running shell attached to a pseudo-terminal
+pub struct PtySession {
+    pub id: String,
+    pub shell_hex_id: String,
+    pub cwd: Option<String>,
+    pub started_at: String,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    size: Mutex<PtySize>,
+    scrollback: Mutex<Scrollback>,
+    exit: Mutex<ExitState>,
+    subscribers: Mutex<Vec<Channel<PtyEvent>>>,
+}
+
+impl PtySession {
+    /// Spawn a shell in a new PTY and start streaming its output to `on_event`
+    pub fn open(request: PtyOpenRequest, on_event: Channel<PtyEvent>) -> Result<Arc<Self>, PtyError> {
+        let size = PtySize {
+            rows: request.rows.unwrap_or(24),
+            cols: request.cols.unwrap_or(80),
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+
+        let pair = native_pty_system()
+            .openpty(size)
+            .map_err(|e| PtyError::OpenError(e.to_string()))?;
+
+        let mut cmd = match &request.shell {
+            Some(shell) => CommandBuilder::new(shell),
+            None => CommandBuilder::new_default_prog(),
+        };
+        if let Some(cwd) = &request.cwd {
+            cmd.cwd(cwd);
+        }
+        cmd.env("TERM", "xterm-256color");
+
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| PtyError::OpenError(e.to_string()))?;
+        // Drop our copy of the slave so reads hit EOF once the shell exits
+        drop(pair.slave);
+
+        let mut reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| PtyError::OpenError(e.to_string()))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| PtyError::OpenError(e.to_string()))?;
+
+        let session = Arc::new(Self {
+            id: Uuid::new_v4().to_string(),
+            shell_hex_id: request.shell_hex_id,
+            cwd: request.cwd,
+            started_at: Utc::now().to_rfc3339(),
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            killer: Mutex::new(child.clone_killer()),
+            size: Mutex::new(size),
+            scrollback: Mutex::new(Scrollback::new(SCROLLBACK_BYTES)),
+            exit: Mutex::new(ExitState::default()),
+            subscribers: Mutex::new(vec![on_event]),
+        });
+
+        // PTY reads are blocking, so pump output on a dedicated thread
+        let pump = session.clone();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => pump.push_output(&buf[..n]),
+                }
+            }
+            let exit_code = child.wait().ok().map(|status| status.exit_code());
+            pump.mark_exited(exit_code);
+        });
+
+        Ok(session)
+    }
+
+    /// Add another channel that receives this session's live output
+    pub fn subscribe(&self, on_event: Channel<PtyEvent>) {
+        self.subscribers.lock().unwrap().push(on_event);
+    }
+
+    /// Write input to the shell's stdin
+    pub fn write(&self, data: &[u8]) -> Result<(), PtyError> {
+        if self.exit.lock().unwrap().exited() {
+            return Err(PtyError::Exited(self.id.clone()));
+        }
+        let mut writer = self.writer.lock().unwrap();
+        writer.write_all(data)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Resize the terminal
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), PtyError> {
+        let size = PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        self.master
+            .lock()
+            .unwrap()
+            .resize(size)
+            .map_err(|e| PtyError::Io(std::io::Error::other(e.to_string())))?;
+        *self.size.lock().unwrap() = size;
+        Ok(())
+    }
+
+    /// Read retained output starting at `offset` (defaults to the oldest retained byte)
+    pub fn read(&self, offset: Option<u64>, max_bytes: usize) -> PtyReadResult {
+        // Checked first: once the shell has exited, all of its output is in the scrollback
+        let (exited, exit_code) = {
+            let exit = self.exit.lock().unwrap();
+            (exit.exited(), exit.exit_code)
+        };
+        let (data, next_offset, dropped) = {
+            let scrollback = self.scrollback.lock().unwrap();
+            let offset = offset.unwrap_or(scrollback.start_offset);
+            scrollback.read_text(offset, max_bytes, exited)
+        };
+
+        PtyReadResult {
+            data,
+            next_offset,
+            dropped,
+            exited,
+            exit_code,
+        }
+    }
+
+    /// Kill the shell
+    pub fn kill(&self) -> Result<(), PtyError> {
+        if self.exit.lock().unwrap().exited() {
+            return Ok(());
+        }
+        self.killer.lock().unwrap().kill()?;
+        Ok(())
+    }
+
+    /// Whether the shell exited more than `ttl` ago
+    pub fn exited_longer_than(&self, ttl: Duration) -> bool {
+        self.exit.lock().unwrap().expired(ttl)
+    }
+
+    pub fn info(&self) -> PtySessionInfo {
+        let size = *self.size.lock().unwrap();
+        let exit = self.exit.lock().unwrap();
+        PtySessionInfo {
+            session_id: self.id.clone(),
+            shell_hex_id: self.shell_hex_id.clone(),
+            cwd: self.cwd.clone(),
+            cols: size.cols,
+            rows: size.rows,
+            started_at: self.started_at.clone(),
+            exited: exit.exited(),
+            exit_code: exit.exit_code,
+        }
+    }
+
+    fn push_output(&self, bytes: &[u8]) {
+        self.scrollback.lock().unwrap().push(bytes);
+        self.broadcast(PtyEvent::Output(bytes.to_vec()));
+    }
+
+    fn mark_exited(&self, exit_code: Option<u32>) {
+        {
+            let mut exit = self.exit.lock().unwrap();
+            exit.exited_at = Some(Instant::now());
+            exit.exit_code = exit_code;
+        }
+        self.broadcast(PtyEvent::Exit(exit_code));
+    }
+
+    /// Send an event to every subscriber, dropping channels that have gone away
+    fn broadcast(&self, event: PtyEvent) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|channel| channel.send(event.clone()).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrollback_read_from_offset() {
+        let mut scrollback = Scrollback::new(16);
+        scrollback.push(b"hello ");
+        scrollback.push(b"world");
+
+        let (bytes, next, dropped) = scrollback.read_from(6, 100);
+        assert_eq!(bytes, b"world");
+        assert_eq!(next, 11);
+        assert!(!dropped);
+
+        let (bytes, next, _) = scrollback.read_from(next, 100);
+        assert!(bytes.is_empty());
+        assert_eq!(next, 11);
+    }
+
+    #[test]
+    fn test_scrollback_drops_oldest_bytes() {
+        let mut scrollback = Scrollback::new(8);
+        scrollback.push(b"0123456789");
+
+        assert_eq!(scrollback.start_offset, 2);
+        assert_eq!(scrollback.end_offset(), 10);
+
+        let (bytes, next, dropped) = scrollback.read_from(0, 4);
+        assert_eq!(bytes, b"2345");
+        assert_eq!(next, 6);
+        assert!(dropped);
+    }
+
+    #[test]
+    fn test_scrollback_holds_back_split_characters() {
+        let euro = "€".as_bytes();
+        let mut scrollback = Scrollback::new(16);
+        scrollback.push(&euro[..2]);
+
+        // The shell is still running, so the rest of the character may follow
+        let (text, next, _) = scrollback.read_text(0, 100, false);
+        assert_eq!((text.as_str(), next), ("", 0));
+
+        scrollback.push(&euro[2..]);
+        scrollback.push(b"ab");
+        let (text, next, _) = scrollback.read_text(0, 100, false);
+        assert_eq!((text.as_str(), next), ("€ab", 5));
+
+        // Split by max_bytes
+        let (text, next, _) = scrollback.read_text(0, 2, true);
+        assert_eq!((text.as_str(), next), ("\u{FFFD}", 2));
+        let (text, next, _) = scrollback.read_text(0, 4, true);
+        assert_eq!((text.as_str(), next), ("€a", 4));
+
+        // Nothing more can arrive once the shell has exited
+        let mut scrollback = Scrollback::new(16);
+        scrollback.push(&euro[..2]);
+        let (text, next, _) = scrollback.read_text(0, 100, true);
+        assert_eq!((text.as_str(), next), ("\u{FFFD}", 2));
+    }
+
+    #[test]
+    fn test_incomplete_utf8_tail() {
+        let text = "a€b".as_bytes();
+        assert_eq!(incomplete_utf8_tail(text), 0);
+        assert_eq!(incomplete_utf8_tail(&text[..2]), 1);
+        assert_eq!(incomplete_utf8_tail(&text[..3]), 2);
+        assert_eq!(incomplete_utf8_tail(&text[..4]), 0);
+        assert_eq!(incomplete_utf8_tail(&"🦀".as_bytes()[..3]), 3);
+        assert_eq!(incomplete_utf8_tail(b"\x80\x80\x80"), 0);
+    }
+
+    #[test]
+    fn test_exited_sessions_expire() {
+        let running = ExitState::default();
+        assert!(!running.expired(Duration::ZERO));
+
+        let exited = ExitState {
+            exited_at: Some(Instant::now() - Duration::from_secs(10)),
+            exit_code: Some(0),
+        };
+        assert!(exited.expired(Duration::from_secs(5)));
+        assert!(!exited.expired(EXITED_SESSION_TTL));
+    }
+}
@@ -0,0 +1,410 @@
+//! Board-based access control for tool commands
+//!
+//! Mirrors the frontend RBAC rules in `src/rbac/permissions.ts`: an agent's
+//! access to a tool hex depends on the tool's linking mode, range and
+//! directional zones, all read from `hex_entities`. Commands that receive an
+//! `agent_hex_id` check it here before touching the filesystem or a shell.
+
+use crate::database::{Database, HexEntity};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Range used when a tool hex doesn't configure one
+const DEFAULT_RANGE: i32 = 1;
+
+#[derive(Error, Debug)]
+pub enum RbacError {
+    #[error("Permission denied: {0}")]
+    Denied(String),
+    #[error("Hex not found: {0}")]
+    HexNotFound(String),
+    #[error("Invalid hex config for {0}: {1}")]
+    InvalidConfig(String, String),
+    #[error("Database error: {0}")]
+    Database(String),
+}
+
+/// Permission types that can be granted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Read,
+    Write,
+    Execute,
+    Admin,
+}
+
+impl Permission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::Execute => "execute",
+            Permission::Admin => "admin",
+        }
+    }
+}
+
+/// The six hex directions, from the top moving clockwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HexDirection {
+    N,
+    NE,
+    SE,
+    S,
+    SW,
+    NW,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::N,
+        HexDirection::NE,
+        HexDirection::SE,
+        HexDirection::S,
+        HexDirection::SW,
+        HexDirection::NW,
+    ];
+}
+
+/// Which directions around a tool grant which permissions
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ZoneConfig {
+    pub read_zone: Vec<HexDirection>,
+    pub write_zone: Vec<HexDirection>,
+    pub read_write_zone: Vec<HexDirection>,
+    pub execute_in_all_zones: bool,
+}
+
+/// Explicit access granted to a hex in explicit linking mode
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessGrant {
+    pub target_hex_key: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// A tool hex's `rbacConfig`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RbacConfig {
+    pub enabled: bool,
+    pub default_permissions: Vec<Permission>,
+    /// Missing zone config grants nothing, as in the frontend
+    #[serde(default)]
+    pub zone_config: Option<ZoneConfig>,
+    pub use_zones: bool,
+    pub access_grants: Vec<AccessGrant>,
+    pub deny_list: Vec<String>,
+}
+
+impl Default for RbacConfig {
+    /// Same as the frontend's `DEFAULT_RBAC_CONFIG`
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_permissions: vec![Permission::Read, Permission::Execute],
+            zone_config: Some(ZoneConfig {
+                read_write_zone: HexDirection::ALL.to_vec(),
+                execute_in_all_zones: true,
+                ..Default::default()
+            }),
+            use_zones: true,
+            access_grants: Vec::new(),
+            deny_list: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkingMode {
+    #[default]
+    Range,
+    Explicit,
+}
+
+/// The parts of a tool hex's config that decide who can reach it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ToolAccessConfig {
+    #[serde(default)]
+    range: Option<i32>,
+    #[serde(default)]
+    linking_mode: LinkingMode,
+    #[serde(default)]
+    linked_hexes: Vec<String>,
+    #[serde(default)]
+    rbac_config: Option<RbacConfig>,
+}
+
+/// Axial hex key in the frontend's `"q,r"` format
+pub fn hex_key(q: i32, r: i32) -> String {
+    format!("{},{}", q, r)
+}
+
+/// Distance in hexes between two axial coordinates
+pub fn hex_distance(a: (i32, i32), b: (i32, i32)) -> i32 {
+    let dq = b.0 - a.0;
+    let dr = b.1 - a.1;
+    dq.abs().max(dr.abs()).max((dq + dr).abs())
+}
+
+/// Which direction `to` lies in as seen from `from`
+///
+/// Neighbours use exact direction vectors; further hexes are binned into
+/// 60° cones by their screen-space angle.
+pub fn direction_from_to(from: (i32, i32), to: (i32, i32)) -> Option<HexDirection> {
+    let dq = to.0 - from.0;
+    let dr = to.1 - from.1;
+
+    match (dq, dr) {
+        (0, 0) => return None,
+        (0, -1) => return Some(HexDirection::N),
+        (1, -1) => return Some(HexDirection::NE),
+        (1, 0) => return Some(HexDirection::SE),
+        (0, 1) => return Some(HexDirection::S),
+        (-1, 1) => return Some(HexDirection::SW),
+        (-1, 0) => return Some(HexDirection::NW),
+        _ => {}
+    }
+
+    // Axial to pixel coordinates (flat-top hex)
+    let pixel_x = 1.5 * dq as f64;
+    let pixel_y = 3f64.sqrt() / 2.0 * dq as f64 + 3f64.sqrt() * dr as f64;
+    let angle = (pixel_y.atan2(pixel_x).to_degrees() + 360.0) % 360.0;
+
+    Some(match angle {
+        a if a < 60.0 => HexDirection::SE,
+        a if a < 120.0 => HexDirection::S,
+        a if a < 180.0 => HexDirection::SW,
+        a if a < 240.0 => HexDirection::NW,
+        a if a < 300.0 => HexDirection::N,
+        _ => HexDirection::NE,
+    })
+}
+
+/// Permissions granted to a hex in `direction` by a zone configuration
+pub fn zone_permissions(direction: HexDirection, zone_config: Option<&ZoneConfig>) -> Vec<Permission> {
+    let mut permissions = Vec::new();
+    let Some(zones) = zone_config else {
+        return permissions;
+    };
+
+    if zones.read_write_zone.contains(&direction) {
+        permissions.extend([Permission::Read, Permission::Write]);
+    } else {
+        if zones.read_zone.contains(&direction) {
+            permissions.push(Permission::Read);
+        }
+        if zones.write_zone.contains(&direction) {
+            permissions.push(Permission::Write);
+        }
+    }
+
+    if zones.execute_in_all_zones && !permissions.is_empty() {
+        permissions.push(Permission::Execute);
+    }
+
+    permissions
+}
+
+/// Check whether the agent on `agent` has `permission` on the tool on `tool`
+pub fn check_permission(
+    agent: &HexEntity,
+    tool: &HexEntity,
+    permission: Permission,
+) -> Result<(), RbacError> {
+    let access: ToolAccessConfig = serde_json::from_str(&tool.config)
+        .map_err(|e| RbacError::InvalidConfig(tool.id.clone(), e.to_string()))?;
+    let rbac = access.rbac_config.unwrap_or_default();
+    let range = access.range.unwrap_or(DEFAULT_RANGE);
+
+    let agent_pos = (agent.position_q, agent.position_r);
+    let tool_pos = (tool.position_q, tool.position_r);
+    let agent_key = hex_key(agent.position_q, agent.position_r);
+    let distance = hex_distance(tool_pos, agent_pos);
+
+    let denied = |reason: String| {
+        Err(RbacError::Denied(format!(
+            "{} on {} ({}): {}",
+            permission.as_str(),
+            tool.name,
+            tool.id,
+            reason
+        )))
+    };
+
+    let in_reach = match access.linking_mode {
+        LinkingMode::Explicit => access.linked_hexes.contains(&agent_key),
+        LinkingMode::Range => distance > 0 && distance <= range,
+    };
+
+    // Without RBAC, anything the tool can reach has full access
+    if !rbac.enabled {
+        return if in_reach {
+            Ok(())
+        } else {
+            denied("not in range or linked".to_string())
+        };
+    }
+
+    if rbac.deny_list.contains(&agent_key) {
+        return denied("hex is in deny list".to_string());
+    }
+
+    let granted = match access.linking_mode {
+        LinkingMode::Explicit => {
+            match rbac.access_grants.iter().find(|g| g.target_hex_key == agent_key) {
+                Some(grant) => grant.permissions.contains(&permission),
+                None => return denied("no explicit access grant".to_string()),
+            }
+        }
+        LinkingMode::Range => {
+            if !in_reach {
+                return denied(format!("not within range (distance: {}, range: {})", distance, range));
+            }
+            if rbac.use_zones {
+                let Some(direction) = direction_from_to(tool_pos, agent_pos) else {
+                    return denied("cannot determine direction".to_string());
+                };
+                if !zone_permissions(direction, rbac.zone_config.as_ref()).contains(&permission) {
+                    return denied(format!("not available in {:?} zone", direction));
+                }
+                true
+            } else {
+                rbac.default_permissions.contains(&permission)
+            }
+        }
+    };
+
+    if granted {
+        Ok(())
+    } else {
+        denied("not granted".to_string())
+    }
+}
+
+/// Check `permission` for an optional calling agent against a tool hex
+///
+/// Calls without an agent (from the UI or internal services) are allowed.
+pub fn authorize(
+    db: &Database,
+    agent_hex_id: Option<&str>,
+    tool_hex_id: &str,
+    permission: Permission,
+) -> Result<(), RbacError> {
+    let Some(agent_hex_id) = agent_hex_id else {
+        return Ok(());
+    };
+
+    let load = |id: &str| {
+        db.get_hex(id)
+            .map_err(|e| RbacError::Database(e.to_string()))?
+            .ok_or_else(|| RbacError::HexNotFound(id.to_string()))
+    };
+    let agent = load(agent_hex_id)?;
+    let tool = load(tool_hex_id)?;
+
+    if agent.board_id != tool.board_id {
+        return Err(RbacError::Denied(format!(
+            "{} is on a different board than {}",
+            agent.name, tool.name
+        )));
+    }
+
+    check_permission(&agent, &tool, permission)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(id: &str, q: i32, r: i32, config: &str) -> HexEntity {
+        HexEntity {
+            id: id.to_string(),
+            board_id: "board".to_string(),
+            name: id.to_string(),
+            category: "tool".to_string(),
+            entity_type: "filesystem".to_string(),
+            position_q: q,
+            position_r: r,
+            config: config.to_string(),
+            status: "idle".to_string(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_direction_from_to() {
+        assert_eq!(direction_from_to((0, 0), (0, 0)), None);
+        assert_eq!(direction_from_to((0, 0), (0, -1)), Some(HexDirection::N));
+        assert_eq!(direction_from_to((0, 0), (-1, 0)), Some(HexDirection::NW));
+        assert_eq!(direction_from_to((0, 0), (0, 2)), Some(HexDirection::S));
+        assert_eq!(direction_from_to((0, 0), (2, -1)), Some(HexDirection::SE));
+    }
+
+    #[test]
+    fn test_default_config_grants_neighbours() {
+        let tool = hex("fs", 0, 0, "{}");
+
+        assert!(check_permission(&hex("a", 1, 0, "{}"), &tool, Permission::Write).is_ok());
+        assert!(check_permission(&hex("a", 1, 0, "{}"), &tool, Permission::Execute).is_ok());
+        assert!(check_permission(&hex("a", 2, 0, "{}"), &tool, Permission::Read).is_err());
+        assert!(check_permission(&hex("a", 0, 0, "{}"), &tool, Permission::Read).is_err());
+    }
+
+    #[test]
+    fn test_zones_limit_permissions() {
+        let tool = hex(
+            "fs",
+            0,
+            0,
+            r#"{"range": 2, "rbacConfig": {"zoneConfig": {
+                "readZone": ["NW", "SW"], "writeZone": ["NE", "SE"],
+                "readWriteZone": [], "executeInAllZones": false
+            }}}"#,
+        );
+
+        let west = hex("a", -1, 0, "{}");
+        assert!(check_permission(&west, &tool, Permission::Read).is_ok());
+        assert!(check_permission(&west, &tool, Permission::Write).is_err());
+        assert!(check_permission(&west, &tool, Permission::Execute).is_err());
+
+        let east = hex("b", 2, 0, "{}");
+        assert!(check_permission(&east, &tool, Permission::Write).is_ok());
+        assert!(check_permission(&east, &tool, Permission::Read).is_err());
+
+        let north = hex("c", 0, -1, "{}");
+        assert!(check_permission(&north, &tool, Permission::Read).is_err());
+    }
+
+    #[test]
+    fn test_explicit_linking_and_deny_list() {
+        let tool = hex(
+            "fs",
+            0,
+            0,
+            r#"{"linkingMode": "explicit", "linkedHexes": ["5,5"], "rbacConfig": {
+                "accessGrants": [{"targetHexKey": "5,5", "role": "viewer", "permissions": ["read"]}],
+                "denyList": ["1,0"]
+            }}"#,
+        );
+
+        assert!(check_permission(&hex("a", 5, 5, "{}"), &tool, Permission::Read).is_ok());
+        assert!(check_permission(&hex("a", 5, 5, "{}"), &tool, Permission::Write).is_err());
+        assert!(check_permission(&hex("b", 1, 0, "{}"), &tool, Permission::Read).is_err());
+        assert!(check_permission(&hex("c", 0, 1, "{}"), &tool, Permission::Read).is_err());
+    }
+
+    #[test]
+    fn test_rbac_disabled_uses_reach_only() {
+        let tool = hex("fs", 0, 0, r#"{"rbacConfig": {"enabled": false}}"#);
+
+        assert!(check_permission(&hex("a", 0, 1, "{}"), &tool, Permission::Admin).is_ok());
+        assert!(check_permission(&hex("a", 0, 3, "{}"), &tool, Permission::Read).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Filesystem sandboxing for file commands
+//!
+//! Every file command operates inside the root directory of a filesystem hex.
+//! Paths are resolved against that root, and anything that would land outside
+//! it - through `..` components, absolute paths or symlinks - is rejected.
+
+use crate::commands::CommandError;
+use crate::database::Database;
+use std::path::{Component, Path, PathBuf};
+
+/// The root directory of a filesystem hex
+#[derive(Debug, Clone)]
+pub struct SandboxRoot {
+    /// Root as written in the hex config, lexically normalized
+    configured: PathBuf,
+    /// Root with symlinks resolved
+    canonical: PathBuf,
+}
+
+impl SandboxRoot {
+    /// Create a sandbox rooted at an existing directory
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, CommandError> {
+        let root = root.as_ref();
+        if !root.is_absolute() {
+            return Err(CommandError::PathError(format!(
+                "Filesystem root must be absolute: {}",
+                root.display()
+            )));
+        }
+
+        let canonical = std::fs::canonicalize(root).map_err(|e| {
+            CommandError::PathError(format!("Invalid filesystem root {}: {}", root.display(), e))
+        })?;
+        if !canonical.is_dir() {
+            return Err(CommandError::PathError(format!(
+                "Filesystem root is not a directory: {}",
+                root.display()
+            )));
+        }
+
+        Ok(Self {
+            configured: normalize(root),
+            canonical,
+        })
+    }
+
+    /// Load the sandbox for a filesystem hex from its `rootPath` config
+    pub fn for_hex(db: &Database, filesystem_hex_id: &str) -> Result<Self, CommandError> {
+        let hex = db
+            .get_hex(filesystem_hex_id)
+            .map_err(|e| CommandError::PathError(e.to_string()))?
+            .ok_or_else(|| {
+                CommandError::PathError(format!("Filesystem hex not found: {}", filesystem_hex_id))
+            })?;
+
+        let config: serde_json::Value = serde_json::from_str(&hex.config)
+            .map_err(|e| CommandError::PathError(format!("Invalid hex config: {}", e)))?;
+
+        let root = config
+            .get("config")
+            .and_then(|c| c.get("rootPath"))
+            .and_then(|r| r.as_str())
+            .filter(|r| !r.is_empty())
+            .ok_or_else(|| {
+                CommandError::PathError(format!(
+                    "Filesystem hex has no root path: {}",
+                    filesystem_hex_id
+                ))
+            })?;
+
+        Self::new(root)
+    }
+
+    /// The canonical root directory
+    pub fn root(&self) -> &Path {
+        &self.canonical
+    }
+
+    /// Resolve a path (relative to the root, or absolute) to a location inside the root
+    ///
+    /// The returned path is under the canonical root but keeps its final
+    /// component unresolved, so operations on a symlink act on the link itself.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, CommandError> {
+        let outside = || {
+            CommandError::PathError(format!(
+                "Path is outside the filesystem root {}: {}",
+                self.configured.display(),
+                path
+            ))
+        };
+
+        let requested = Path::new(path);
+        let relative = if requested.is_absolute() {
+            let normalized = normalize(requested);
+            match normalized
+                .strip_prefix(&self.configured)
+                .or_else(|_| normalized.strip_prefix(&self.canonical))
+            {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => return Err(outside()),
+            }
+        } else {
+            requested.to_path_buf()
+        };
+
+        // Resolve `.` and `..` without leaving the root
+        let mut components: Vec<Component> = Vec::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(_) => components.push(component),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    components.pop().ok_or_else(outside)?;
+                }
+                Component::RootDir | Component::Prefix(_) => return Err(outside()),
+            }
+        }
+
+        let resolved: PathBuf = components
+            .iter()
+            .fold(self.canonical.clone(), |acc, c| acc.join(c));
+
+        self.check_symlinks(&resolved, path)?;
+        Ok(resolved)
+    }
+
+    /// Resolve a path to write to, following a final symlink
+    ///
+    /// Writes replace the file at the returned path, so a link to a file inside
+    /// the root is written through rather than replaced by a regular file.
+    pub fn resolve_write_target(&self, path: &str) -> Result<PathBuf, CommandError> {
+        let resolved = self.resolve(path)?;
+        match std::fs::symlink_metadata(&resolved) {
+            // `resolve` already checked that the link lands inside the root
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                Ok(std::fs::canonicalize(&resolved)?)
+            }
+            _ => Ok(resolved),
+        }
+    }
+
+    /// Ensure the deepest existing ancestor of `resolved` is still inside the root
+    fn check_symlinks(&self, resolved: &Path, path: &str) -> Result<(), CommandError> {
+        for ancestor in resolved.ancestors() {
+            match std::fs::canonicalize(ancestor) {
+                Ok(canonical) if canonical.starts_with(&self.canonical) => return Ok(()),
+                Ok(_) => {
+                    return Err(CommandError::PathError(format!(
+                        "Path escapes the filesystem root through a symlink: {}",
+                        path
+                    )))
+                }
+                // A link whose target doesn't exist could still be written through
+                Err(_) if std::fs::symlink_metadata(ancestor).is_ok() => {
+                    return Err(CommandError::PathError(format!(
+                        "Path contains a dangling symlink: {}",
+                        path
+                    )))
+                }
+                Err(_) => continue,
+            }
+        }
+
+        Err(CommandError::PathError(format!(
+            "Filesystem root no longer exists: {}",
+            self.configured.display()
+        )))
+    }
+}
+
+/// Lexically normalize an absolute path, dropping `.` and applying `..`
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn temp_root() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_resolve_relative_and_absolute_paths() {
+        let dir = temp_root();
+        let sandbox = SandboxRoot::new(dir.path()).unwrap();
+
+        let relative = sandbox.resolve("src/main.rs").unwrap();
+        assert_eq!(relative, sandbox.root().join("src/main.rs"));
+
+        let absolute = sandbox.resolve(dir.path().join("src/../src/main.rs").to_str().unwrap()).unwrap();
+        assert_eq!(absolute, relative);
+
+        // Paths that don't exist yet are allowed as long as they stay inside
+        let new_file = sandbox.resolve("src/new/lib.rs").unwrap();
+        assert_eq!(new_file, sandbox.root().join("src/new/lib.rs"));
+    }
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let dir = temp_root();
+        let sandbox = SandboxRoot::new(dir.path()).unwrap();
+
+        assert!(matches!(sandbox.resolve("../outside"), Err(CommandError::PathError(_))));
+        assert!(matches!(sandbox.resolve("src/../../outside"), Err(CommandError::PathError(_))));
+        assert!(matches!(sandbox.resolve("/home"), Err(CommandError::PathError(_))));
+        assert!(matches!(
+            sandbox.resolve(dir.path().join("../outside").to_str().unwrap()),
+            Err(CommandError::PathError(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_rejects_symlink_escapes() {
+        let dir = temp_root();
+        let outside = temp_root();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("escape")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("missing"), dir.path().join("dangling")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("src"), dir.path().join("inside")).unwrap();
+        let sandbox = SandboxRoot::new(dir.path()).unwrap();
+
+        assert!(matches!(sandbox.resolve("escape/src/main.rs"), Err(CommandError::PathError(_))));
+        assert!(matches!(sandbox.resolve("escape/new.txt"), Err(CommandError::PathError(_))));
+        assert!(matches!(sandbox.resolve("dangling"), Err(CommandError::PathError(_))));
+        assert!(sandbox.resolve("inside/main.rs").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_target_follows_final_symlink() {
+        use crate::files::write::{write_atomic, WritePrecondition};
+
+        let dir = temp_root();
+        std::os::unix::fs::symlink(dir.path().join("src/main.rs"), dir.path().join("link.rs")).unwrap();
+        let sandbox = SandboxRoot::new(dir.path()).unwrap();
+
+        let target = sandbox.resolve_write_target("link.rs").unwrap();
+        assert_eq!(target, sandbox.root().join("src/main.rs"));
+        write_atomic(&target, b"fn main() { run() }", &WritePrecondition::default()).unwrap();
+
+        let link = sandbox.root().join("link.rs");
+        assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read(&link).unwrap(), b"fn main() { run() }");
+
+        // Paths that aren't links, including new files, are unchanged
+        assert_eq!(sandbox.resolve_write_target("new.rs").unwrap(), sandbox.root().join("new.rs"));
+    }
+}
@@ -0,0 +1,121 @@
+//! Tauri commands for filesystem watching
+//!
+//! Exposes filesystem hex watches to the frontend via Tauri IPC.
+
+use super::{FilesystemChanges, WatchInfo, WatchOptions, WatcherState, CHANGED_EVENT};
+use crate::database::Database;
+use crate::indexer::commands::IndexerState;
+use crate::indexer::jobs::JobKind;
+use crate::rbac::{self, Permission};
+use crate::sandbox::SandboxRoot;
+use serde::Deserialize;
+use std::path::Path;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+#[derive(Debug, Deserialize)]
+pub struct WatchRequest {
+    pub filesystem_hex_id: String,
+    /// Agent hex making the request, checked against the board's RBAC
+    pub agent_hex_id: Option<String>,
+    #[serde(flatten)]
+    pub options: WatchOptions,
+}
+
+/// Start watching a filesystem hex's root, replacing any existing watch
+#[tauri::command]
+pub fn watcher_start(
+    app: AppHandle,
+    state: State<'_, WatcherState>,
+    db: State<'_, Database>,
+    indexer: State<'_, IndexerState>,
+    request: WatchRequest,
+) -> Result<WatchInfo, String> {
+    rbac::authorize(
+        &db,
+        request.agent_hex_id.as_deref(),
+        &request.filesystem_hex_id,
+        Permission::Read,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let root = SandboxRoot::for_hex(&db, &request.filesystem_hex_id)
+        .map_err(|e| e.to_string())?
+        .root()
+        .to_path_buf();
+    let ignore_dirs = indexer.config.ignore_dirs.clone();
+    let reindex = request.options.reindex;
+    let base = root.clone();
+
+    state
+        .start(
+            &request.filesystem_hex_id,
+            root,
+            &request.options,
+            ignore_dirs,
+            move |changes| {
+                if reindex {
+                    reindex_changes(&app, &base, &changes);
+                }
+                if let Err(e) = app.emit(CHANGED_EVENT, &changes) {
+                    eprintln!("[Watcher] Failed to emit changes: {}", e);
+                }
+            },
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Stop watching a filesystem hex
+#[tauri::command]
+pub fn watcher_stop(
+    state: State<'_, WatcherState>,
+    db: State<'_, Database>,
+    filesystem_hex_id: String,
+    agent_hex_id: Option<String>,
+) -> Result<bool, String> {
+    rbac::authorize(&db, agent_hex_id.as_deref(), &filesystem_hex_id, Permission::Read)
+        .map_err(|e| e.to_string())?;
+    Ok(state.stop(&filesystem_hex_id))
+}
+
+/// List active watches
+#[tauri::command]
+pub fn watcher_list(state: State<'_, WatcherState>) -> Vec<WatchInfo> {
+    state.list()
+}
+
+/// Queue index updates for a batch of changes
+fn reindex_changes(app: &AppHandle, base_path: &Path, changes: &FilesystemChanges) {
+    let indexer = app.state::<IndexerState>();
+    // Nothing to update until the model is loaded; the initial index picks changes up
+    if !indexer.embedder.is_initialized() || !indexer.store.is_initialized() {
+        return;
+    }
+
+    let hex_id = &changes.filesystem_hex_id;
+    let removed = changes
+        .removed
+        .iter()
+        .chain(changes.renamed.iter().map(|r| &r.from));
+    for file_path in removed {
+        let kind = JobKind::RemoveFile {
+            file_path: file_path.clone(),
+        };
+        indexer.jobs.submit(hex_id, kind, None, None);
+    }
+
+    let changed = changes
+        .created
+        .iter()
+        .chain(&changes.modified)
+        .chain(changes.renamed.iter().map(|r| &r.to));
+    for file_path in changed {
+        if !indexer.is_indexable(file_path) || !base_path.join(file_path).is_file() {
+            continue;
+        }
+        let kind = JobKind::IndexFile {
+            base_path: base_path.to_path_buf(),
+            file_path: file_path.clone(),
+        };
+        indexer.jobs.submit(hex_id, kind, None, None);
+    }
+}
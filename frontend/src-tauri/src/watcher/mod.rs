@@ -0,0 +1,363 @@
+//! Filesystem watching for filesystem hexes
+//!
+//! Watches each filesystem hex root recursively and reports debounced batches
+//! of changes, so edits made outside the app - an editor, `git checkout`, a
+//! shell command - reach the frontend and, when enabled, the codebase index.
+
+pub mod commands;
+
+use notify_debouncer_full::notify::event::{ModifyKind, RenameMode};
+use notify_debouncer_full::notify::{Event, EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Tauri event carrying a `FilesystemChanges` batch
+///
+/// Tauri event names can't contain dots; the frontend re-emits these on its
+/// event bus as `filesystem.changed`.
+pub const CHANGED_EVENT: &str = "filesystem:changed";
+
+/// Quiet period before a batch of changes is reported
+const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+#[derive(Error, Debug)]
+pub enum WatcherError {
+    #[error("Failed to watch {0}: {1}")]
+    Watch(String, String),
+}
+
+/// A path that was renamed within the root
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenamedPath {
+    pub from: String,
+    pub to: String,
+}
+
+/// A debounced batch of changes, paths relative to the filesystem hex root
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilesystemChanges {
+    pub filesystem_hex_id: String,
+    pub created: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<RenamedPath>,
+}
+
+impl FilesystemChanges {
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty()
+            && self.modified.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+    }
+}
+
+/// Options for a watch
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchOptions {
+    pub debounce_ms: Option<u64>,
+    /// Re-index changed files in the codebase index
+    #[serde(default)]
+    pub reindex: bool,
+}
+
+/// Public description of a watch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchInfo {
+    pub filesystem_hex_id: String,
+    pub root: String,
+    pub debounce_ms: u64,
+    pub reindex: bool,
+}
+
+struct Watch {
+    info: WatchInfo,
+    // Dropping the debouncer stops the watch
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+}
+
+/// Active watches, one per filesystem hex
+#[derive(Default)]
+pub struct WatcherState {
+    watches: Mutex<HashMap<String, Watch>>,
+}
+
+impl WatcherState {
+    /// Watch `root` for a filesystem hex, replacing any existing watch for it
+    ///
+    /// `on_changes` runs on the watcher thread with each non-empty batch.
+    pub fn start<F>(
+        &self,
+        filesystem_hex_id: &str,
+        root: PathBuf,
+        options: &WatchOptions,
+        ignore_dirs: Vec<String>,
+        on_changes: F,
+    ) -> Result<WatchInfo, WatcherError>
+    where
+        F: Fn(FilesystemChanges) + Send + 'static,
+    {
+        let debounce_ms = options.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS);
+        let watch_error = |e: &dyn std::fmt::Display| {
+            WatcherError::Watch(root.to_string_lossy().to_string(), e.to_string())
+        };
+
+        let hex_id = filesystem_hex_id.to_string();
+        let base = root.clone();
+        let mut debouncer = new_debouncer(
+            Duration::from_millis(debounce_ms),
+            None,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => {
+                    let changes =
+                        collect_changes(&hex_id, &base, events.iter().map(|e| &e.event), &ignore_dirs);
+                    if !changes.is_empty() {
+                        on_changes(changes);
+                    }
+                }
+                Err(errors) => {
+                    for error in errors {
+                        eprintln!("[Watcher] {}: {}", hex_id, error);
+                    }
+                }
+            },
+        )
+        .map_err(|e| watch_error(&e))?;
+        debouncer
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(|e| watch_error(&e))?;
+
+        let info = WatchInfo {
+            filesystem_hex_id: filesystem_hex_id.to_string(),
+            root: root.to_string_lossy().to_string(),
+            debounce_ms,
+            reindex: options.reindex,
+        };
+        self.watches.lock().unwrap().insert(
+            filesystem_hex_id.to_string(),
+            Watch {
+                info: info.clone(),
+                _debouncer: debouncer,
+            },
+        );
+        Ok(info)
+    }
+
+    /// Stop watching a filesystem hex; returns whether it was watched
+    pub fn stop(&self, filesystem_hex_id: &str) -> bool {
+        self.watches.lock().unwrap().remove(filesystem_hex_id).is_some()
+    }
+
+    pub fn list(&self) -> Vec<WatchInfo> {
+        self.watches
+            .lock()
+            .unwrap()
+            .values()
+            .map(|w| w.info.clone())
+            .collect()
+    }
+}
+
+/// Group raw events into created/modified/removed/renamed paths under `root`
+///
+/// Paths inside an ignored directory are dropped, and a path created and
+/// renamed within one batch (such as an atomic write's temp file) is reported
+/// as a change to the rename target.
+pub fn collect_changes<'a>(
+    filesystem_hex_id: &str,
+    root: &Path,
+    events: impl IntoIterator<Item = &'a Event>,
+    ignore_dirs: &[String],
+) -> FilesystemChanges {
+    let relative = |path: &Path| -> Option<String> {
+        let relative = path.strip_prefix(root).ok()?;
+        let ignored = relative.components().any(|c| match c {
+            Component::Normal(name) => ignore_dirs.iter().any(|d| name == d.as_str()),
+            _ => false,
+        });
+        if ignored || relative.as_os_str().is_empty() {
+            None
+        } else {
+            Some(relative.to_string_lossy().to_string())
+        }
+    };
+
+    let mut created = BTreeSet::new();
+    let mut modified = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+    let mut renamed = Vec::new();
+
+    for event in events {
+        let paths: Vec<Option<String>> = event.paths.iter().map(|p| relative(p)).collect();
+        match event.kind {
+            EventKind::Create(_) => created.extend(paths.into_iter().flatten()),
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if paths.len() == 2 => {
+                match (paths[0].clone(), paths[1].clone()) {
+                    (Some(from), Some(to)) => {
+                        if created.remove(&from) {
+                            modified.insert(to);
+                        } else {
+                            renamed.push(RenamedPath { from, to });
+                        }
+                    }
+                    (Some(from), None) => {
+                        removed.insert(from);
+                    }
+                    (None, Some(to)) => {
+                        created.insert(to);
+                    }
+                    (None, None) => {}
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                removed.extend(paths.into_iter().flatten())
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                created.extend(paths.into_iter().flatten())
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                for (path, name) in event.paths.iter().zip(paths) {
+                    let Some(name) = name else { continue };
+                    if path.exists() {
+                        created.insert(name);
+                    } else {
+                        removed.insert(name);
+                    }
+                }
+            }
+            // Permission and timestamp changes don't change content
+            EventKind::Modify(ModifyKind::Metadata(_)) => {}
+            EventKind::Modify(_) => modified.extend(paths.into_iter().flatten()),
+            EventKind::Remove(_) => removed.extend(paths.into_iter().flatten()),
+            _ => {}
+        }
+    }
+
+    // A path created and removed within the batch either came back or never mattered
+    let transient: Vec<String> = created.intersection(&removed).cloned().collect();
+    for path in transient {
+        removed.remove(&path);
+        if !root.join(&path).exists() {
+            created.remove(&path);
+        }
+    }
+    let modified = modified
+        .into_iter()
+        .filter(|p| !created.contains(p) && !removed.contains(p))
+        .collect();
+
+    FilesystemChanges {
+        filesystem_hex_id: filesystem_hex_id.to_string(),
+        created: created.into_iter().collect(),
+        modified,
+        removed: removed.into_iter().collect(),
+        renamed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify_debouncer_full::notify::event::{CreateKind, DataChange, RemoveKind};
+    use std::sync::mpsc;
+    use tempfile::TempDir;
+
+    fn event(kind: EventKind, paths: &[&str]) -> Event {
+        let mut event = Event::new(kind);
+        for path in paths {
+            event = event.add_path(PathBuf::from(path));
+        }
+        event
+    }
+
+    #[test]
+    fn test_collect_changes_groups_and_filters() {
+        let root = Path::new("/project");
+        let ignore_dirs = vec!["node_modules".to_string(), ".git".to_string()];
+        let events = vec![
+            event(EventKind::Create(CreateKind::File), &["/project/src/new.rs"]),
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), &["/project/src/lib.rs"]),
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Content)), &["/project/src/new.rs"]),
+            event(EventKind::Remove(RemoveKind::File), &["/project/old.rs"]),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &["/project/a.rs", "/project/b.rs"],
+            ),
+            event(EventKind::Create(CreateKind::File), &["/project/node_modules/x/index.js"]),
+            event(EventKind::Modify(ModifyKind::Data(DataChange::Any)), &["/project/.git/index"]),
+            event(EventKind::Create(CreateKind::File), &["/elsewhere/file.rs"]),
+        ];
+
+        let changes = collect_changes("fs-1", root, &events, &ignore_dirs);
+
+        assert_eq!(changes.filesystem_hex_id, "fs-1");
+        assert_eq!(changes.created, vec!["src/new.rs"]);
+        assert_eq!(changes.modified, vec!["src/lib.rs"]);
+        assert_eq!(changes.removed, vec!["old.rs"]);
+        assert_eq!(
+            changes.renamed,
+            vec![RenamedPath {
+                from: "a.rs".to_string(),
+                to: "b.rs".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_collect_changes_folds_atomic_writes() {
+        let root = Path::new("/project");
+        let events = vec![
+            event(EventKind::Create(CreateKind::File), &["/project/.main.rs.abc.tmp"]),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &["/project/.main.rs.abc.tmp", "/project/main.rs"],
+            ),
+            // Created and deleted again, and no longer on disk
+            event(EventKind::Create(CreateKind::File), &["/project/scratch.txt"]),
+            event(EventKind::Remove(RemoveKind::File), &["/project/scratch.txt"]),
+        ];
+
+        let changes = collect_changes("fs-1", root, &events, &[]);
+
+        assert_eq!(changes.modified, vec!["main.rs"]);
+        assert!(changes.created.is_empty());
+        assert!(changes.removed.is_empty());
+        assert!(changes.renamed.is_empty());
+    }
+
+    #[test]
+    fn test_watch_reports_changes() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("target")).unwrap();
+        let root = std::fs::canonicalize(dir.path()).unwrap();
+
+        let state = WatcherState::default();
+        let (tx, rx) = mpsc::channel();
+        let tx = Mutex::new(tx);
+        let options = WatchOptions {
+            debounce_ms: Some(100),
+            reindex: false,
+        };
+        state
+            .start("fs-1", root.clone(), &options, vec!["target".to_string()], move |changes| {
+                let _ = tx.lock().unwrap().send(changes);
+            })
+            .unwrap();
+        assert_eq!(state.list().len(), 1);
+
+        std::fs::write(root.join("target/out.o"), "ignored").unwrap();
+        std::fs::write(root.join("hello.txt"), "hi").unwrap();
+
+        let changes = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(changes.created.contains(&"hello.txt".to_string()));
+        assert!(changes.created.iter().all(|p| !p.starts_with("target")));
+
+        assert!(state.stop("fs-1"));
+        assert!(!state.stop("fs-1"));
+    }
+}
@@ -0,0 +1,151 @@
+"""Regenerate the tiny embedding model fixture used by the embedder tests.
+
+The model maps each token id to a fixed 8-dimensional vector and zeroes
+padding positions, which is enough to exercise fastembed's user-defined model
+loading and mean pooling without a network connection. The ONNX protobuf is
+encoded by hand so the script needs nothing beyond the standard library.
+
+    python3 generate.py
+"""
+
+import json
+import math
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+VOCAB = [
+    "[PAD]", "[UNK]", "fn", "main", "let", "return", "struct", "impl",
+    "parse", "file", "search", "index", "code", "test", "value", "error",
+]
+DIM = 8
+
+
+def varint(n):
+    n &= (1 << 64) - 1
+    out = bytearray()
+    while True:
+        byte = n & 0x7F
+        n >>= 7
+        if n:
+            out.append(byte | 0x80)
+        else:
+            out.append(byte)
+            return bytes(out)
+
+
+def field(number, wire_type, payload):
+    return varint(number << 3 | wire_type) + payload
+
+
+def int_field(number, value):
+    return field(number, 0, varint(value))
+
+
+def bytes_field(number, value):
+    if isinstance(value, str):
+        value = value.encode()
+    return field(number, 2, varint(len(value)) + value)
+
+
+FLOAT, INT64 = 1, 7
+
+
+def tensor(name, data_type, dims, raw):
+    return b"".join(
+        [*(int_field(1, d) for d in dims), int_field(2, data_type), bytes_field(8, name), bytes_field(9, raw)]
+    )
+
+
+def value_info(name, elem_type, dims):
+    shape = b"".join(
+        bytes_field(1, bytes_field(2, d) if isinstance(d, str) else int_field(1, d)) for d in dims
+    )
+    tensor_type = int_field(1, elem_type) + bytes_field(2, shape)
+    return bytes_field(1, name) + bytes_field(2, bytes_field(1, tensor_type))
+
+
+def node(op_type, inputs, outputs, attributes=()):
+    return b"".join(
+        [
+            *(bytes_field(1, i) for i in inputs),
+            *(bytes_field(2, o) for o in outputs),
+            bytes_field(3, outputs[0]),
+            bytes_field(4, op_type),
+            *(bytes_field(5, a) for a in attributes),
+        ]
+    )
+
+
+def int_attribute(name, value):
+    # AttributeProto.type INT = 2
+    return bytes_field(1, name) + int_field(3, value) + int_field(20, 2)
+
+
+def model_bytes():
+    weights = [math.sin(1.0 + token * DIM + i) for token in range(len(VOCAB)) for i in range(DIM)]
+    weights[:DIM] = [0.0] * DIM  # [PAD]
+    graph = b"".join(
+        [
+            bytes_field(1, node("Gather", ["embeddings", "input_ids"], ["token_embeddings"], [int_attribute("axis", 0)])),
+            bytes_field(1, node("Cast", ["attention_mask"], ["mask"], [int_attribute("to", FLOAT)])),
+            bytes_field(1, node("Unsqueeze", ["mask", "unsqueeze_axes"], ["mask_3d"])),
+            bytes_field(1, node("Mul", ["token_embeddings", "mask_3d"], ["last_hidden_state"])),
+            bytes_field(2, "tiny-embedding-model"),
+            bytes_field(5, tensor("embeddings", FLOAT, [len(VOCAB), DIM], struct.pack(f"<{len(weights)}f", *weights))),
+            bytes_field(5, tensor("unsqueeze_axes", INT64, [1], struct.pack("<q", -1))),
+            bytes_field(11, value_info("input_ids", INT64, ["batch", "sequence"])),
+            bytes_field(11, value_info("attention_mask", INT64, ["batch", "sequence"])),
+            bytes_field(12, value_info("last_hidden_state", FLOAT, ["batch", "sequence", DIM])),
+        ]
+    )
+    opset = int_field(2, 13)  # default domain, opset 13
+    return int_field(1, 8) + bytes_field(2, "generate.py") + bytes_field(7, graph) + bytes_field(8, opset)
+
+
+def special_token(token_id, content):
+    return {
+        "id": token_id,
+        "content": content,
+        "single_word": False,
+        "lstrip": False,
+        "rstrip": False,
+        "normalized": False,
+        "special": True,
+    }
+
+
+def write_json(name, value):
+    with open(os.path.join(HERE, name), "w") as f:
+        json.dump(value, f, indent=2)
+        f.write("\n")
+
+
+def main():
+    with open(os.path.join(HERE, "model.onnx"), "wb") as f:
+        f.write(model_bytes())
+    write_json(
+        "tokenizer.json",
+        {
+            "version": "1.0",
+            "truncation": None,
+            "padding": None,
+            "added_tokens": [special_token(0, "[PAD]"), special_token(1, "[UNK]")],
+            "normalizer": {"type": "Lowercase"},
+            "pre_tokenizer": {"type": "Whitespace"},
+            "post_processor": None,
+            "decoder": None,
+            "model": {
+                "type": "WordLevel",
+                "vocab": {token: i for i, token in enumerate(VOCAB)},
+                "unk_token": "[UNK]",
+            },
+        },
+    )
+    write_json("config.json", {"model_type": "bert", "hidden_size": DIM, "pad_token_id": 0})
+    write_json("special_tokens_map.json", {"pad_token": "[PAD]", "unk_token": "[UNK]"})
+    write_json("tokenizer_config.json", {"model_max_length": 64, "pad_token": "[PAD]", "unk_token": "[UNK]"})
+
+
+if __name__ == "__main__":
+    main()
@@ -173,6 +173,34 @@ export function HexDetailBar() {
             });
             break;
 
+          case 'indexer.progress': {
+            const stage = event.data?.stage;
+            if (stage === 'discovered') {
+              entityLogs.logs.push({
+                timestamp,
+                level: 'info',
+                message: `[INDEX] Found ${event.data?.filesTotal ?? 0} files to index`,
+              });
+            } else if (stage === 'error') {
+              entityLogs.logs.push({
+                timestamp,
+                level: 'error',
+                message: `[INDEX] ${event.data?.path}: ${event.data?.error}`,
+              });
+            } else if (stage === 'cancelled') {
+              entityLogs.logs.push({ timestamp, level: 'info', message: '[INDEX] Cancelled' });
+            } else if (stage === 'completed') {
+              entityLogs.logs.push({
+                timestamp,
+                level: 'info',
+                message:
+                  `[INDEX] Done: ${event.data?.filesAdded} added, ${event.data?.filesUpdated} updated, ` +
+                  `${event.data?.filesRemoved} removed, ${event.data?.filesSkipped} unchanged`,
+              });
+            }
+            break;
+          }
+
           case 'work.received':
             entityLogs.logs.push({
               timestamp,
@@ -4,6 +4,7 @@
  */
 
 import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import { llmClient } from '../../llm/client';
 import { LLMProviderType } from '../../llm/types';
 import * as tauriDb from '../../services/tauriDatabase';
@@ -19,7 +20,7 @@ type ApiKeyProviderKey = Exclude<ProviderKey, 'ollama'>;
 
 type ApiKeyState = Record<ApiKeyProviderKey, string>;
 
-type SettingsSection = 'llm-providers';
+type SettingsSection = 'llm-providers' | 'codebase-index';
 
 // All providers in display order (Ollama rendered differently inline)
 const ALL_PROVIDERS: ProviderKey[] = ['openai', 'anthropic', 'deepseek', 'gemini', 'cohere', 'mistral', 'ollama', 'grok'];
@@ -65,8 +66,58 @@ const STORAGE_KEYS: Record<ProviderKey, string> = {
 
 const SECTIONS: { id: SettingsSection; label: string }[] = [
   { id: 'llm-providers', label: 'LLM Providers' },
+  { id: 'codebase-index', label: 'Codebase Index' },
 ];
 
+// Embedding models supported by the Rust indexer (see indexer/embedder.rs)
+const EMBEDDING_MODEL_SETTING = 'indexer:embedding-model';
+const DEFAULT_EMBEDDING_MODEL = 'all-minilm-l6-v2';
+const EMBEDDING_MODELS: { id: string; label: string }[] = [
+  { id: 'all-minilm-l6-v2', label: 'All-MiniLM-L6-v2 (384 dims)' },
+  { id: 'bge-small-en-v1.5', label: 'BGE Small EN v1.5 (384 dims)' },
+  { id: 'bge-base-en-v1.5', label: 'BGE Base EN v1.5 (768 dims)' },
+  { id: 'nomic-embed-text-v1.5', label: 'Nomic Embed Text v1.5 (768 dims)' },
+  { id: 'jina-embeddings-v2-base-code', label: 'Jina Embeddings v2 Base Code (768 dims)' },
+];
+
+// Optional indexer settings; cleared fields are deleted so the indexer uses its defaults
+interface IndexerSettings {
+  provider: string;
+  // Offline model loaded from a local directory; overrides the model above when a path is set
+  modelPath: string;
+  modelPooling: string;
+  modelDim: string;
+  // OpenAI-compatible embedding server (Ollama, llama.cpp, ...)
+  httpEndpoint: string;
+  httpModel: string;
+  httpDim: string;
+  httpApiKey: string;
+}
+
+const INDEXER_SETTINGS: Record<keyof IndexerSettings, string> = {
+  provider: 'indexer:embedding-provider',
+  modelPath: 'indexer:model-path',
+  modelPooling: 'indexer:model-pooling',
+  modelDim: 'indexer:model-dim',
+  httpEndpoint: 'indexer:http-endpoint',
+  httpModel: 'indexer:http-model',
+  httpDim: 'indexer:http-dim',
+  httpApiKey: 'indexer:http-api-key',
+};
+
+const INDEXER_SETTING_FIELDS = Object.keys(INDEXER_SETTINGS) as (keyof IndexerSettings)[];
+
+const EMPTY_INDEXER_SETTINGS: IndexerSettings = {
+  provider: '',
+  modelPath: '',
+  modelPooling: '',
+  modelDim: '',
+  httpEndpoint: '',
+  httpModel: '',
+  httpDim: '',
+  httpApiKey: '',
+};
+
 export function SettingsModal({ onClose }: SettingsModalProps) {
   const [activeSection, setActiveSection] = useState<SettingsSection>('llm-providers');
   const [apiKeys, setApiKeys] = useState<ApiKeyState>(() => {
@@ -77,6 +128,12 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
     return initial as ApiKeyState;
   });
   const [ollamaEndpoint, setOllamaEndpoint] = useState<string>(DEFAULT_OLLAMA_ENDPOINT);
+  const [embeddingModel, setEmbeddingModel] = useState<string>(DEFAULT_EMBEDDING_MODEL);
+  const [savedEmbeddingModel, setSavedEmbeddingModel] = useState<string>(DEFAULT_EMBEDDING_MODEL);
+  const [indexerSettings, setIndexerSettings] = useState<IndexerSettings>(EMPTY_INDEXER_SETTINGS);
+  const [savedIndexerSettings, setSavedIndexerSettings] = useState<IndexerSettings>(EMPTY_INDEXER_SETTINGS);
+  // Why the indexer couldn't load the saved embedding settings at startup
+  const [indexerSettingsError, setIndexerSettingsError] = useState<string | null>(null);
   const [isSaving, setIsSaving] = useState(false);
   const [saveMessage, setSaveMessage] = useState<string | null>(null);
 
@@ -95,6 +152,24 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
 
       // Load Ollama endpoint
       setOllamaEndpoint(llmClient.getOllamaBaseUrl() || DEFAULT_OLLAMA_ENDPOINT);
+
+      // Load embedding model
+      const model = (await tauriDb.getSetting(EMBEDDING_MODEL_SETTING)) || DEFAULT_EMBEDDING_MODEL;
+      setEmbeddingModel(model);
+      setSavedEmbeddingModel(model);
+
+      const loadedIndexerSettings = { ...EMPTY_INDEXER_SETTINGS };
+      for (const field of INDEXER_SETTING_FIELDS) {
+        loadedIndexerSettings[field] = (await tauriDb.getSetting(INDEXER_SETTINGS[field])) || '';
+      }
+      setIndexerSettings(loadedIndexerSettings);
+      setSavedIndexerSettings(loadedIndexerSettings);
+
+      try {
+        setIndexerSettingsError(await invoke<string | null>('indexer_settings_error'));
+      } catch (error) {
+        console.error('[Settings] Failed to check indexer settings:', error);
+      }
     }
 
     loadKeys();
@@ -130,7 +205,37 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
         hasChanges = true;
       }
 
-      if (hasChanges) {
+      // The indexer loads its model at startup, so a new model applies after a restart
+      let needsRestart = false;
+      if (embeddingModel !== savedEmbeddingModel) {
+        await tauriDb.setSetting(EMBEDDING_MODEL_SETTING, embeddingModel);
+        setSavedEmbeddingModel(embeddingModel);
+        console.log(`[Settings] Saved embedding model: ${embeddingModel}`);
+        hasChanges = true;
+        needsRestart = true;
+      }
+
+      const trimmedIndexerSettings = { ...EMPTY_INDEXER_SETTINGS };
+      for (const field of INDEXER_SETTING_FIELDS) {
+        const value = indexerSettings[field].trim();
+        trimmedIndexerSettings[field] = value;
+        if (value === savedIndexerSettings[field]) continue;
+        // The backend only returns the API key masked
+        if (field === 'httpApiKey' && value.includes('*')) continue;
+        if (value) {
+          await tauriDb.setSetting(INDEXER_SETTINGS[field], value);
+        } else {
+          await tauriDb.deleteSetting(INDEXER_SETTINGS[field]);
+        }
+        console.log(`[Settings] Saved indexer setting ${INDEXER_SETTINGS[field]}`);
+        hasChanges = true;
+        needsRestart = true;
+      }
+      setSavedIndexerSettings(trimmedIndexerSettings);
+
+      if (needsRestart) {
+        setSaveMessage('Settings saved successfully; restart to switch embedding models');
+      } else if (hasChanges) {
         setSaveMessage('Settings saved successfully');
       } else {
         setSaveMessage('No changes to save');
@@ -147,6 +252,10 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
     setApiKeys(prev => ({ ...prev, [provider]: value }));
   };
 
+  const updateIndexerSetting = (field: keyof IndexerSettings, value: string) => {
+    setIndexerSettings(prev => ({ ...prev, [field]: value }));
+  };
+
   return (
     <div className="fixed inset-0 z-50 flex items-center justify-center">
       {/* Backdrop */}
@@ -237,6 +346,166 @@ export function SettingsModal({ onClose }: SettingsModalProps) {
                 </div>
               </div>
             )}
+
+            {activeSection === 'codebase-index' && (
+              <div className="p-6 flex flex-col">
+                <div className="flex-shrink-0 mb-4">
+                  <h3 className="text-text-primary text-lg font-medium mb-1">Codebase Index</h3>
+                  <p className="text-text-secondary text-sm">
+                    Choose how codebase search embeds code. Built-in models run locally and are downloaded on first use. Changes take effect after a restart, and each filesystem is re-indexed with the new model the next time it is indexed.
+                  </p>
+                  {indexerSettingsError && (
+                    <div className="mt-3 text-sm px-3 py-2 rounded-md bg-accent-warning/10 text-accent-warning">
+                      Codebase search is unavailable until these settings are fixed and the app is restarted: {indexerSettingsError}
+                    </div>
+                  )}
+                </div>
+
+                <div className="max-w-md mb-6">
+                  <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                    Embedding Provider
+                  </label>
+                  <select
+                    value={indexerSettings.provider || 'fastembed'}
+                    onChange={(e) => updateIndexerSetting('provider', e.target.value)}
+                    className="input w-full"
+                  >
+                    <option value="fastembed">Built-in (runs locally)</option>
+                    <option value="http">Embedding server (OpenAI-compatible)</option>
+                  </select>
+                </div>
+
+                {indexerSettings.provider === 'http' ? (
+                  <>
+                    <div className="mb-3">
+                      <h4 className="text-text-primary text-sm font-medium mb-1">Embedding Server</h4>
+                      <p className="text-text-secondary text-sm">
+                        Use an existing server with an OpenAI-compatible /embeddings endpoint, such as Ollama or llama.cpp. The model and its dimensions are required.
+                      </p>
+                    </div>
+
+                    <div className="grid grid-cols-2 gap-x-6 gap-y-4 max-w-2xl">
+                      <div className="col-span-2">
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          Endpoint URL
+                        </label>
+                        <input
+                          type="text"
+                          value={indexerSettings.httpEndpoint}
+                          onChange={(e) => updateIndexerSetting('httpEndpoint', e.target.value)}
+                          placeholder={DEFAULT_OLLAMA_ENDPOINT}
+                          className="input w-full placeholder:text-text-muted/50"
+                        />
+                      </div>
+                      <div>
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          Model
+                        </label>
+                        <input
+                          type="text"
+                          value={indexerSettings.httpModel}
+                          onChange={(e) => updateIndexerSetting('httpModel', e.target.value)}
+                          placeholder="nomic-embed-text"
+                          className="input w-full placeholder:text-text-muted/50"
+                        />
+                      </div>
+                      <div>
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          Dimensions
+                        </label>
+                        <input
+                          type="text"
+                          inputMode="numeric"
+                          value={indexerSettings.httpDim}
+                          onChange={(e) => updateIndexerSetting('httpDim', e.target.value)}
+                          placeholder="768"
+                          className="input w-full placeholder:text-text-muted/50"
+                        />
+                      </div>
+                      <div className="col-span-2">
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          API Key <span className="text-text-muted/60">(optional)</span>
+                        </label>
+                        <input
+                          type="password"
+                          value={indexerSettings.httpApiKey}
+                          onChange={(e) => updateIndexerSetting('httpApiKey', e.target.value)}
+                          placeholder="Bearer token"
+                          className="input w-full placeholder:text-text-muted/50"
+                        />
+                      </div>
+                    </div>
+                  </>
+                ) : (
+                  <>
+                    <div className="max-w-md">
+                      <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                        Embedding Model
+                      </label>
+                      <select
+                        value={embeddingModel}
+                        onChange={(e) => setEmbeddingModel(e.target.value)}
+                        className="input w-full"
+                      >
+                        {EMBEDDING_MODELS.map((model) => (
+                          <option key={model.id} value={model.id}>
+                            {model.label}
+                          </option>
+                        ))}
+                      </select>
+                    </div>
+
+                    <div className="mt-6 mb-3">
+                      <h4 className="text-text-primary text-sm font-medium mb-1">Local Model</h4>
+                      <p className="text-text-secondary text-sm">
+                        For offline machines, load an ONNX model from a directory containing model.onnx, tokenizer.json, config.json, special_tokens_map.json and tokenizer_config.json. When set, it replaces the model above.
+                      </p>
+                    </div>
+
+                    <div className="grid grid-cols-2 gap-x-6 gap-y-4 max-w-2xl">
+                      <div className="col-span-2">
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          Model Directory
+                        </label>
+                        <input
+                          type="text"
+                          value={indexerSettings.modelPath}
+                          onChange={(e) => updateIndexerSetting('modelPath', e.target.value)}
+                          placeholder="/path/to/model"
+                          className="input w-full placeholder:text-text-muted/50"
+                        />
+                      </div>
+                      <div>
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          Pooling
+                        </label>
+                        <select
+                          value={indexerSettings.modelPooling || 'mean'}
+                          onChange={(e) => updateIndexerSetting('modelPooling', e.target.value)}
+                          className="input w-full"
+                        >
+                          <option value="mean">Mean</option>
+                          <option value="cls">CLS</option>
+                        </select>
+                      </div>
+                      <div>
+                        <label className="text-text-muted text-xs block mb-1.5 font-medium">
+                          Dimensions <span className="text-text-muted/60">(optional)</span>
+                        </label>
+                        <input
+                          type="text"
+                          inputMode="numeric"
+                          value={indexerSettings.modelDim}
+                          onChange={(e) => updateIndexerSetting('modelDim', e.target.value)}
+                          placeholder="From config.json"
+                          className="input w-full placeholder:text-text-muted/50"
+                        />
+                      </div>
+                    </div>
+                  </>
+                )}
+              </div>
+            )}
           </div>
         </div>
 
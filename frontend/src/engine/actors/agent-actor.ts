@@ -112,6 +112,11 @@ export class AgentActor extends BaseActor {
         return;
       }
 
+      // Watcher batches also contain every agent write, which already has its own event
+      if (event.data.source === 'watcher') {
+        return;
+      }
+
       // Check if this filesystem is one we have read access to
       if (this.canReadFromFilesystem(filesystemEntityId)) {
         console.log(`[Agent ${this.agentEntity.name}] Filesystem changed event received:`, event.data);
@@ -1214,6 +1219,7 @@ export class AgentActor extends BaseActor {
     // Filesystem read tools - track these for read-before-write enforcement
     const filesystemReadTools = [
       'filesystem_read_file',
+      'filesystem_read_file_range',
       'filesystem_get_file_info',
     ];
     const isFilesystemReadTool = filesystemReadTools.includes(toolName);
@@ -1221,6 +1227,7 @@ export class AgentActor extends BaseActor {
     // Filesystem write tools - emit filesystem.changed events
     const filesystemWriteTools = [
       'filesystem_write_file',
+      'filesystem_apply_patch',
       'filesystem_create_directory',
       'filesystem_delete_file',
       'filesystem_delete_directory',
@@ -1391,6 +1398,7 @@ export class AgentActor extends BaseActor {
   private extractFilesystemPath(toolName: string, params: Record<string, unknown>): string | null {
     switch (toolName) {
       case 'filesystem_write_file':
+      case 'filesystem_apply_patch':
       case 'filesystem_delete_file':
       case 'filesystem_create_directory':
       case 'filesystem_delete_directory':
@@ -1445,6 +1453,7 @@ export class AgentActor extends BaseActor {
 
     switch (toolName) {
       case 'filesystem_write_file':
+      case 'filesystem_apply_patch':
         operation = 'write';
         path = params.path as string;
         break;
@@ -575,14 +575,21 @@ export class ToolActor extends BaseActor {
     
     switch (operation) {
       case 'read':
-        return await invoke('read_file', { path: params.path as string });
+        return await invoke('read_file', {
+          filesystemHexId: this.toolEntity.id,
+          path: params.path as string
+        });
       case 'write':
         return await invoke('write_file', { 
+          filesystemHexId: this.toolEntity.id,
           path: params.path as string, 
           contents: params.contents as string 
         });
       case 'list':
-        return await invoke('list_directory', { path: params.path as string });
+        return await invoke('list_directory', {
+          filesystemHexId: this.toolEntity.id,
+          path: params.path as string
+        });
       default:
         throw new Error(`Unknown filesystem operation: ${operation}`);
     }
@@ -5,6 +5,8 @@
  * 1. On board start - index all filesystem hexes
  * 2. On file changes - incremental updates
  * 3. On filesystem hex config change - re-index with new path
+ * 4. On changes made outside the app - a Rust watcher per filesystem hex
+ *    re-indexes changed files and reports them as `filesystem.changed`
  */
 
 import { EventBus } from './event-bus';
@@ -13,6 +15,7 @@ import { Store, ToolEntity } from '../state/store';
 interface IndexDirectoryRequest {
   filesystem_hex_id: string;
   directory_path: string;
+  job_id?: string;
 }
 
 interface IndexFileRequest {
@@ -24,6 +27,35 @@ interface IndexFileRequest {
 interface IndexResult {
   chunks_indexed: number;
   files_processed: number;
+  files_added: number;
+  files_updated: number;
+  files_removed: number;
+  files_skipped: number;
+  cancelled: boolean;
+}
+
+// Progress events streamed by indexer_index_directory
+type IndexEvent =
+  | { Queued: { job_id: string } }
+  | { Started: { job_id: string } }
+  | { Discovered: { files: number } }
+  | {
+      Progress: {
+        files_done: number;
+        files_total: number;
+        chunks_embedded: number;
+        current_file: string | null;
+      };
+    }
+  | { FileError: { path: string; error: string } }
+  | 'Cancelled';
+
+interface FilesystemChanges {
+  filesystem_hex_id: string;
+  created: string[];
+  modified: string[];
+  removed: string[];
+  renamed: { from: string; to: string }[];
 }
 
 
@@ -33,7 +65,9 @@ export class IndexerService {
   private store: Store;
   private unsubscribeFilesystemChanged?: () => void;
   private unsubscribeEntityUpdated?: () => void;
+  private unlistenWatcher?: () => void;
   private indexedFilesystems: Map<string, string> = new Map(); // entityId -> rootPath
+  private indexingJobs: Map<string, string> = new Map(); // entityId -> jobId
 
   constructor(eventBus: EventBus, store: Store) {
     this.eventBus = eventBus;
@@ -56,6 +90,16 @@ export class IndexerService {
       this.handleEntityUpdated(event);
     });
 
+    // Forward batches from the Rust filesystem watchers onto the event bus
+    try {
+      const { listen } = await import('@tauri-apps/api/event');
+      this.unlistenWatcher = await listen<FilesystemChanges>('filesystem:changed', (event) => {
+        this.handleWatcherChanges(event.payload);
+      });
+    } catch (error) {
+      console.error('[IndexerService] Failed to listen for filesystem changes:', error);
+    }
+
     // Initialize the indexer (downloads model if needed)
     try {
       const { invoke } = await import('@tauri-apps/api/core');
@@ -86,6 +130,18 @@ export class IndexerService {
       this.unsubscribeEntityUpdated = undefined;
     }
 
+    if (this.unlistenWatcher) {
+      this.unlistenWatcher();
+      this.unlistenWatcher = undefined;
+    }
+
+    for (const entityId of this.indexingJobs.keys()) {
+      this.cancelIndexing(entityId);
+    }
+
+    for (const entityId of this.indexedFilesystems.keys()) {
+      this.stopWatcher(entityId);
+    }
     this.indexedFilesystems.clear();
   }
 
@@ -129,29 +185,166 @@ export class IndexerService {
 
     console.log(`[IndexerService] Indexing ${entity.name} at ${rootPath}...`);
 
+    let jobId = `index-${entity.id}-${Date.now()}`;
+    this.indexingJobs.set(entity.id, jobId);
+
     try {
-      const { invoke } = await import('@tauri-apps/api/core');
+      const { invoke, Channel } = await import('@tauri-apps/api/core');
       
       const request: IndexDirectoryRequest = {
         filesystem_hex_id: entity.id,
         directory_path: rootPath,
+        job_id: jobId,
       };
 
-      const result = await invoke<IndexResult>('indexer_index_directory', { request });
-      
+      const onEvent = new Channel<IndexEvent>();
+      onEvent.onmessage = (event) => {
+        // A rescan already queued for this hex is joined rather than repeated
+        if (typeof event === 'object' && 'Queued' in event) {
+          jobId = event.Queued.job_id;
+          this.indexingJobs.set(entity.id, jobId);
+        }
+        this.handleIndexEvent(entity.id, jobId, event);
+      };
+
+      const result = await invoke<IndexResult>('indexer_index_directory', { request, onEvent });
+
+      if (result.cancelled) {
+        console.log(`[IndexerService] Indexing ${entity.name} cancelled`);
+        this.emitIndexProgress(entity.id, { jobId, stage: 'cancelled' });
+        return;
+      }
+
       this.indexedFilesystems.set(entity.id, rootPath);
+      this.emitIndexProgress(entity.id, {
+        jobId,
+        stage: 'completed',
+        filesAdded: result.files_added,
+        filesUpdated: result.files_updated,
+        filesRemoved: result.files_removed,
+        filesSkipped: result.files_skipped,
+        chunksEmbedded: result.chunks_indexed,
+      });
       console.log(
-        `[IndexerService] Indexed ${entity.name}: ${result.files_processed} files, ${result.chunks_indexed} chunks`
+        `[IndexerService] Indexed ${entity.name}: ${result.files_added} added, ${result.files_updated} updated, ` +
+          `${result.files_removed} removed, ${result.files_skipped} unchanged (${result.chunks_indexed} chunks)`
       );
+
+      // Keep the index current when files change outside the app
+      await invoke('watcher_start', { request: { filesystem_hex_id: entity.id, reindex: true } });
     } catch (error) {
       console.error(`[IndexerService] Failed to index ${entity.name}:`, error);
+    } finally {
+      if (this.indexingJobs.get(entity.id) === jobId) {
+        this.indexingJobs.delete(entity.id);
+      }
+    }
+  }
+
+  /**
+   * Cancel a filesystem hex's running index job, if any
+   */
+  private async cancelIndexing(entityId: string): Promise<void> {
+    const jobId = this.indexingJobs.get(entityId);
+    if (!jobId) {
+      return;
     }
+
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke<boolean>('indexer_cancel', { jobId });
+    } catch (error) {
+      console.error(`[IndexerService] Failed to cancel indexing for ${entityId}:`, error);
+    }
+  }
+
+  /**
+   * Forward a streamed index event onto the event bus as indexer.progress
+   */
+  private handleIndexEvent(entityId: string, jobId: string, event: IndexEvent): void {
+    if (event === 'Cancelled') {
+      // Reported once the command returns
+      return;
+    }
+    if ('Queued' in event) {
+      this.emitIndexProgress(entityId, { jobId, stage: 'queued' });
+    } else if ('Started' in event) {
+      this.emitIndexProgress(entityId, { jobId, stage: 'started' });
+    } else if ('Discovered' in event) {
+      this.emitIndexProgress(entityId, { jobId, stage: 'discovered', filesTotal: event.Discovered.files });
+    } else if ('Progress' in event) {
+      const progress = event.Progress;
+      this.emitIndexProgress(entityId, {
+        jobId,
+        stage: 'progress',
+        filesDone: progress.files_done,
+        filesTotal: progress.files_total,
+        chunksEmbedded: progress.chunks_embedded,
+        currentFile: progress.current_file,
+      });
+    } else if ('FileError' in event) {
+      console.warn(`[IndexerService] Failed to index ${event.FileError.path}: ${event.FileError.error}`);
+      this.emitIndexProgress(entityId, {
+        jobId,
+        stage: 'error',
+        path: event.FileError.path,
+        error: event.FileError.error,
+      });
+    }
+  }
+
+  private emitIndexProgress(entityId: string, data: Record<string, unknown>): void {
+    this.eventBus.emit({
+      type: 'indexer.progress',
+      hexId: entityId,
+      boardId: this.store.getState().board?.id ?? 'local',
+      data,
+      timestamp: new Date(),
+    });
+  }
+
+  /**
+   * Stop the Rust watcher for a filesystem hex
+   */
+  private async stopWatcher(entityId: string): Promise<void> {
+    try {
+      const { invoke } = await import('@tauri-apps/api/core');
+      await invoke('watcher_stop', { filesystemHexId: entityId });
+    } catch (error) {
+      console.error(`[IndexerService] Failed to stop watcher for ${entityId}:`, error);
+    }
+  }
+
+  /**
+   * Re-emit a watcher batch as a filesystem.changed event
+   */
+  private handleWatcherChanges(changes: FilesystemChanges): void {
+    console.log(`[IndexerService] Filesystem ${changes.filesystem_hex_id} changed on disk:`, changes);
+    this.eventBus.emit({
+      type: 'filesystem.changed',
+      hexId: changes.filesystem_hex_id,
+      boardId: this.store.getState().board?.id ?? 'local',
+      data: {
+        operation: 'external',
+        source: 'watcher',
+        created: changes.created,
+        modified: changes.modified,
+        removed: changes.removed,
+        renamed: changes.renamed,
+      },
+      timestamp: new Date(),
+    });
   }
 
   /**
    * Handle filesystem.changed event for incremental index updates
    */
   private async handleFilesystemChanged(event: { hexId: string; data: Record<string, unknown> }): Promise<void> {
+    // The watcher has already re-indexed these in Rust
+    if (event.data.source === 'watcher') {
+      return;
+    }
+
     const filesystemHexId = event.hexId;
     const { operation, path: filePath } = event.data as {
       operation: string;
@@ -254,6 +447,9 @@ export class IndexerService {
       try {
         const { invoke } = await import('@tauri-apps/api/core');
 
+        // Stop indexing the old path
+        await this.cancelIndexing(entityId);
+
         // Clear old index
         if (oldRootPath) {
           await invoke('indexer_clear_filesystem', { filesystem_hex_id: entityId });
@@ -262,6 +458,7 @@ export class IndexerService {
 
         // Remove from tracked filesystems
         this.indexedFilesystems.delete(entityId);
+        await this.stopWatcher(entityId);
 
         // Re-index with new path
         if (newRootPath && newRootPath.trim() !== '') {
@@ -271,10 +271,10 @@ export function buildToolWorkflowInstructions(toolNames: string[]): string {
 
   // Check for filesystem capabilities
   const hasFilesystemRead = toolNames.some(n =>
-    n.includes('read_file') || n.includes('list_directory') || n.includes('file_exists')
+    n.includes('read_file') || n.includes('list_directory') || n.includes('list_tree') || n.includes('grep') || n.includes('file_exists')
   );
   const hasFilesystemWrite = toolNames.some(n =>
-    n.includes('write_file') || n.includes('create_directory') || n.includes('delete_file')
+    n.includes('write_file') || n.includes('apply_patch') || n.includes('create_directory') || n.includes('delete_file')
   );
 
   // Build workflow based on available tools
@@ -158,8 +158,16 @@ export function getAgentTools(agentId: string, state: AppState): ToolContext {
       // Create execute function that applies tool entity config
       const executeWithConfig = async (params: Record<string, unknown>): Promise<ToolResult> => {
         // Merge tool entity config with call params (call params take precedence)
-        // Include _sourceHexKey so tasklist tools can find the correct ToolActor
-        const mergedParams = { ...config, ...params, _sourceHexKey: resource.hexKey };
+        // Include _sourceHexKey so tasklist tools can find the correct ToolActor,
+        // _sourceEntityId so filesystem tools are confined to this hex's root,
+        // and _agentEntityId so the backend can check the board's RBAC itself
+        const mergedParams = {
+          ...config,
+          ...params,
+          _sourceHexKey: resource.hexKey,
+          _sourceEntityId: resource.entityId,
+          _agentEntityId: agentId,
+        };
         return zodTool.execute(mergedParams);
       };
 
@@ -427,19 +435,22 @@ const TOOL_PERMISSION_MAP: Record<string, Record<string, Permission>> = {
   filesystem: {
     // Read operations
     read_file: 'read',
+    read_file_range: 'read',
     list_directory: 'read',
+    list_tree: 'read',
     search_files: 'read',
+    grep: 'read',
     file_exists: 'read',
     get_file_info: 'read',
+    codebase_search: 'read',
     // Write operations
     write_file: 'write',
+    apply_patch: 'write',
     create_directory: 'write',
     delete_file: 'write',
     delete_directory: 'write',
     copy_file: 'write',
     move_file: 'write',
-    // Execute operations (compute-intensive)
-    codebase_search: 'execute',
   },
   shell: {
     run_command: 'execute',
@@ -32,20 +32,59 @@ const readFileSchema = z.object({
   path: z.string().describe('Path to the file to read'),
 });
 
+const readFileRangeSchema = z.object({
+  path: z.string().describe('Path to the file to read'),
+  startLine: z.number().optional().describe('First line to read (1-based)'),
+  endLine: z.number().optional().describe('Last line to read (inclusive)'),
+  offset: z.number().optional().describe('Byte offset to start reading from (when not reading by line)'),
+  length: z.number().optional().describe('Number of bytes to read from offset'),
+  binary: z.boolean().optional().describe('Return the raw bytes as base64'),
+});
+
 const writeFileSchema = z.object({
   path: z.string().describe('Path to the file to write'),
   content: z.string().describe('Content to write to the file'),
+  expectedHash: z.string().optional().describe('Only write if the file still has this SHA-256 hash (from get_file_info or a previous write)'),
+});
+
+const applyPatchSchema = z.object({
+  path: z.string().describe('Path to the file to patch'),
+  patch: z.string().describe('A unified diff for this file, or one or more <<<<<<< SEARCH / ======= / >>>>>>> REPLACE blocks'),
+  expectedHash: z.string().optional().describe('Only patch if the file still has this SHA-256 hash'),
+  allowPartial: z.boolean().optional().describe('Write the file even if some hunks fail to apply'),
 });
 
 const listDirectorySchema = z.object({
   path: z.string().describe('Directory path to list'),
 });
 
+const listTreeSchema = z.object({
+  path: z.string().optional().default('.').describe('Directory to list'),
+  maxDepth: z.number().optional().describe('Maximum depth to descend (1 = direct children only)'),
+  include: z.array(z.string()).optional().describe('Only list files matching these globs (e.g. "*.rs")'),
+  exclude: z.array(z.string()).optional().describe('Skip files and directories matching these globs'),
+  includeIgnored: z.boolean().optional().describe('Also list files excluded by .gitignore/.ignore'),
+  includeHidden: z.boolean().optional().describe('Also list dotfiles and dot-directories'),
+  maxEntries: z.number().optional().describe('Maximum number of entries to return (default 1000)'),
+});
+
 const searchFilesSchema = z.object({
   pattern: z.string().describe('Search pattern (glob-like, e.g., "*.ts" or "test*")'),
   path: z.string().optional().default('.').describe('Directory to search in'),
 });
 
+const grepSchema = z.object({
+  query: z.string().describe('Text or regular expression to search file contents for'),
+  path: z.string().optional().default('.').describe('Directory to search in'),
+  regex: z.boolean().optional().describe('Treat the query as a regular expression'),
+  caseSensitive: z.boolean().optional().describe('Match case exactly (default true)'),
+  wholeWord: z.boolean().optional().describe('Only match whole words'),
+  include: z.array(z.string()).optional().describe('Only search files matching these globs (e.g. "*.ts")'),
+  exclude: z.array(z.string()).optional().describe('Skip files and directories matching these globs'),
+  contextLines: z.number().optional().describe('Lines of context around each match (default 2)'),
+  maxResults: z.number().optional().describe('Maximum number of matching lines (default 200)'),
+});
+
 const deleteFileSchema = z.object({
   path: z.string().describe('Path to the file to delete'),
 });
@@ -74,11 +113,19 @@ const fileExistsSchema = z.object({
 
 const getFileInfoSchema = z.object({
   path: z.string().describe('Path to get metadata for'),
+  includeHash: z.boolean().optional().describe('Include the SHA-256 hash of the file contents'),
 });
 
 const codebaseSearchSchema = z.object({
-  query: z.string().describe('Natural language query to search the codebase'),
+  query: z.string().describe('Natural language query, symbol name or error code to search the codebase for'),
   limit: z.number().optional().default(10).describe('Maximum number of results to return'),
+  mode: z.enum(['vector', 'keyword', 'hybrid']).optional().default('hybrid').describe('vector ranks by meaning, keyword by exact words and identifiers, hybrid fuses both'),
+  keywordWeight: z.number().min(0).max(1).optional().describe('Share of a hybrid ranking given to keyword matches (default 0.5)'),
+  languages: z.array(z.string()).optional().describe('Only search these languages (e.g. ["python"], ["typescript", "tsx"])'),
+  include: z.array(z.string()).optional().describe('Only search files matching these globs (e.g. ["*_test.go", "tests/**"])'),
+  exclude: z.array(z.string()).optional().describe('Skip files and directories matching these globs'),
+  kinds: z.array(z.enum(['function', 'class', 'impl', 'struct', 'enum', 'trait', 'interface', 'type', 'module', 'constant', 'variable', 'macro'])).optional().describe('Only return these kinds of code unit'),
+  minSimilarity: z.number().min(-1).max(1).optional().describe('Drop semantic matches whose cosine similarity to the query is below this (e.g. 0.3)'),
 });
 
 // Configuration schema for the filesystem plugin
@@ -147,9 +194,13 @@ export class FilesystemPlugin implements ToolPlugin {
   getTools(): ToolDefinition[] {
     return [
       { name: 'read_file', description: 'Read the contents of a file', parameters: zodToJsonSchema(readFileSchema) },
+      { name: 'read_file_range', description: 'Read a line or byte range of a file, or a binary file as base64', parameters: zodToJsonSchema(readFileRangeSchema) },
       { name: 'write_file', description: 'Write content to a file', parameters: zodToJsonSchema(writeFileSchema) },
+      { name: 'apply_patch', description: 'Apply a unified diff or search/replace blocks to a file', parameters: zodToJsonSchema(applyPatchSchema) },
       { name: 'list_directory', description: 'List files and directories', parameters: zodToJsonSchema(listDirectorySchema) },
+      { name: 'list_tree', description: 'List a directory tree recursively, respecting .gitignore', parameters: zodToJsonSchema(listTreeSchema) },
       { name: 'search_files', description: 'Search for files matching a pattern', parameters: zodToJsonSchema(searchFilesSchema) },
+      { name: 'grep', description: 'Search file contents for text or a regex', parameters: zodToJsonSchema(grepSchema) },
       { name: 'delete_file', description: 'Delete a file', parameters: zodToJsonSchema(deleteFileSchema) },
       { name: 'delete_directory', description: 'Delete a directory recursively', parameters: zodToJsonSchema(deleteDirectorySchema) },
       { name: 'copy_file', description: 'Copy a file to a new location', parameters: zodToJsonSchema(copyFileSchema) },
@@ -157,16 +208,20 @@ export class FilesystemPlugin implements ToolPlugin {
       { name: 'create_directory', description: 'Create a directory', parameters: zodToJsonSchema(createDirectorySchema) },
       { name: 'file_exists', description: 'Check if a file or directory exists', parameters: zodToJsonSchema(fileExistsSchema) },
       { name: 'get_file_info', description: 'Get metadata about a file or directory', parameters: zodToJsonSchema(getFileInfoSchema) },
-      { name: 'codebase_search', description: 'Semantic search across the indexed codebase', parameters: zodToJsonSchema(codebaseSearchSchema) },
+      { name: 'codebase_search', description: 'Semantic and keyword search across the indexed codebase', parameters: zodToJsonSchema(codebaseSearchSchema) },
     ];
   }
 
   getZodTools(): ZodToolDefinition[] {
     return [
       { name: 'read_file', description: 'Read the contents of a file', schema: readFileSchema, execute: async (p) => this.execute('read_file', p, {} as ExecutionContext) },
+      { name: 'read_file_range', description: 'Read a line or byte range of a file, or a binary file as base64', schema: readFileRangeSchema, execute: async (p) => this.execute('read_file_range', p, {} as ExecutionContext) },
       { name: 'write_file', description: 'Write content to a file', schema: writeFileSchema, execute: async (p) => this.execute('write_file', p, {} as ExecutionContext) },
+      { name: 'apply_patch', description: 'Apply a unified diff or search/replace blocks to a file', schema: applyPatchSchema, execute: async (p) => this.execute('apply_patch', p, {} as ExecutionContext) },
       { name: 'list_directory', description: 'List files and directories', schema: listDirectorySchema, execute: async (p) => this.execute('list_directory', p, {} as ExecutionContext) },
+      { name: 'list_tree', description: 'List a directory tree recursively, respecting .gitignore', schema: listTreeSchema, execute: async (p) => this.execute('list_tree', p, {} as ExecutionContext) },
       { name: 'search_files', description: 'Search for files matching a pattern', schema: searchFilesSchema, execute: async (p) => this.execute('search_files', p, {} as ExecutionContext) },
+      { name: 'grep', description: 'Search file contents for text or a regex', schema: grepSchema, execute: async (p) => this.execute('grep', p, {} as ExecutionContext) },
       { name: 'delete_file', description: 'Delete a file', schema: deleteFileSchema, execute: async (p) => this.execute('delete_file', p, {} as ExecutionContext) },
       { name: 'delete_directory', description: 'Delete a directory recursively', schema: deleteDirectorySchema, execute: async (p) => this.execute('delete_directory', p, {} as ExecutionContext) },
       { name: 'copy_file', description: 'Copy a file to a new location', schema: copyFileSchema, execute: async (p) => this.execute('copy_file', p, {} as ExecutionContext) },
@@ -174,29 +229,42 @@ export class FilesystemPlugin implements ToolPlugin {
       { name: 'create_directory', description: 'Create a directory', schema: createDirectorySchema, execute: async (p) => this.execute('create_directory', p, {} as ExecutionContext) },
       { name: 'file_exists', description: 'Check if a file or directory exists', schema: fileExistsSchema, execute: async (p) => this.execute('file_exists', p, {} as ExecutionContext) },
       { name: 'get_file_info', description: 'Get metadata about a file or directory', schema: getFileInfoSchema, execute: async (p) => this.execute('get_file_info', p, {} as ExecutionContext) },
-      { name: 'codebase_search', description: 'Semantic search across the indexed codebase', schema: codebaseSearchSchema, execute: async (p) => this.execute('codebase_search', p, {} as ExecutionContext) },
+      { name: 'codebase_search', description: 'Semantic and keyword search across the indexed codebase', schema: codebaseSearchSchema, execute: async (p) => this.execute('codebase_search', p, {} as ExecutionContext) },
     ];
   }
 
   async execute(toolName: string, params: Record<string, unknown>, _context: ExecutionContext): Promise<ToolResult> {
     const start = Date.now();
     const workspaceRoot = (params.workspacePath as string) || (params.rootPath as string) || this.config.rootPath || SAFE_DEFAULT_WORKING_DIR;
+    // The backend confines every file operation to this hex's root
+    const filesystemHexId = (params._sourceEntityId as string) || (params.filesystemHexId as string);
+    const agentHexId = params._agentEntityId as string | undefined;
 
     try {
       let result: unknown;
       switch (toolName) {
-        case 'read_file': result = await this.readFile(params.path as string, workspaceRoot); break;
-        case 'write_file': result = await this.writeFile(params.path as string, params.content as string, workspaceRoot); break;
-        case 'list_directory': result = await this.listDirectory(params.path as string, workspaceRoot); break;
-        case 'search_files': result = await this.searchFiles(params.pattern as string, (params.path as string) || '.', workspaceRoot); break;
-        case 'delete_file': result = await this.deleteFile(params.path as string, workspaceRoot); break;
-        case 'delete_directory': result = await this.deleteDirectory(params.path as string, workspaceRoot); break;
-        case 'copy_file': result = await this.copyFile(params.source as string, params.destination as string, workspaceRoot); break;
-        case 'move_file': result = await this.moveFile(params.source as string, params.destination as string, workspaceRoot); break;
-        case 'create_directory': result = await this.createDirectory(params.path as string, workspaceRoot); break;
-        case 'file_exists': result = await this.fileExists(params.path as string, workspaceRoot); break;
-        case 'get_file_info': result = await this.getFileInfo(params.path as string, workspaceRoot); break;
-        case 'codebase_search': result = await this.codebaseSearch(params.query as string, params.filesystemHexId as string | undefined, (params.limit as number) || 10); break;
+        case 'read_file': result = await this.readFile(params.path as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'read_file_range': result = await this.readFileRange(params, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'write_file': result = await this.writeFile(params.path as string, params.content as string, workspaceRoot, filesystemHexId, agentHexId, params.expectedHash as string | undefined); break;
+        case 'apply_patch': result = await this.applyPatch(params, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'list_directory': result = await this.listDirectory(params.path as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'list_tree': result = await this.listTree(params, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'search_files': result = await this.searchFiles(params.pattern as string, (params.path as string) || '.', workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'grep': result = await this.grep(params, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'delete_file': result = await this.deleteFile(params.path as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'delete_directory': result = await this.deleteDirectory(params.path as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'copy_file': result = await this.copyFile(params.source as string, params.destination as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'move_file': result = await this.moveFile(params.source as string, params.destination as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'create_directory': result = await this.createDirectory(params.path as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'file_exists': result = await this.fileExists(params.path as string, workspaceRoot, filesystemHexId, agentHexId); break;
+        case 'get_file_info': result = await this.getFileInfo(params.path as string, workspaceRoot, filesystemHexId, agentHexId, params.includeHash as boolean | undefined); break;
+        case 'codebase_search': result = await this.codebaseSearch(params.query as string, filesystemHexId, (params.limit as number) || 10, agentHexId, (params.mode as SearchMode | undefined) ?? 'hybrid', params.keywordWeight as number | undefined, {
+          languages: (params.languages as string[] | undefined) ?? [],
+          include: (params.include as string[] | undefined) ?? [],
+          exclude: (params.exclude as string[] | undefined) ?? [],
+          kinds: (params.kinds as string[] | undefined) ?? [],
+          min_similarity: params.minSimilarity as number | undefined,
+        }); break;
         default: return { success: false, error: `Unknown tool: ${toolName}`, duration: Date.now() - start };
       }
       return { success: true, result, duration: Date.now() - start };
@@ -214,100 +282,167 @@ export class FilesystemPlugin implements ToolPlugin {
     return path;
   }
 
-  private async readFile(path: string, workspaceRoot?: string): Promise<string> {
+  private async readFile(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<string> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
     console.log(`[FilesystemPlugin] Reading file: ${resolvedPath}`);
-    return invoke<string>('read_file', { path: resolvedPath });
+    return invoke<string>('read_file', { filesystemHexId, agentHexId, path: resolvedPath });
+  }
+
+  private async readFileRange(params: Record<string, unknown>, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<FileRangeContent> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    const resolvedPath = this.resolvePath(params.path as string, workspaceRoot);
+    let range: FileRange | undefined;
+    if (params.startLine !== undefined) {
+      range = { lines: { start: params.startLine as number, end: params.endLine as number | undefined } };
+    } else if (params.offset !== undefined || params.length !== undefined) {
+      range = { bytes: { offset: (params.offset as number) || 0, length: params.length as number | undefined } };
+    }
+    const mode = params.binary ? 'base64' : 'text';
+    return invoke<FileRangeContent>('read_file_range', { filesystemHexId, agentHexId, path: resolvedPath, range, mode });
   }
 
-  private async writeFile(path: string, content: string, workspaceRoot?: string): Promise<{ written: number }> {
+  private async writeFile(path: string, content: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string, expectedHash?: string): Promise<{ written: number; hash: string }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
     console.log(`[FilesystemPlugin] Writing file: ${resolvedPath} (${content.length} chars)`);
-    await invoke('write_file', { path: resolvedPath, contents: content });
-    return { written: content.length };
+    const precondition: WritePrecondition | undefined = expectedHash ? { expected_hash: expectedHash } : undefined;
+    const result = await invoke<WriteResult>('write_file', { filesystemHexId, agentHexId, path: resolvedPath, contents: content, precondition });
+    return { written: content.length, hash: result.hash };
   }
 
-  private async listDirectory(path: string, workspaceRoot?: string): Promise<{ entries: { name: string; type: 'file' | 'directory'; size: number }[] }> {
+  private async applyPatch(params: Record<string, unknown>, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<PatchResult> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    const resolvedPath = this.resolvePath(params.path as string, workspaceRoot);
+    const precondition: WritePrecondition | undefined = params.expectedHash ? { expected_hash: params.expectedHash as string } : undefined;
+    const result = await invoke<PatchResult>('apply_patch', { filesystemHexId, agentHexId, path: resolvedPath, patch: params.patch as string, precondition, allowPartial: params.allowPartial as boolean | undefined });
+    if (!result.written) {
+      const failed = result.hunks.filter(h => !h.applied).map(h => `hunk ${h.hunk}: ${h.error}`);
+      throw new Error(`Patch not applied to ${resolvedPath}: ${failed.join('; ')}`);
+    }
+    return result;
+  }
+
+  private async listDirectory(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ entries: { name: string; type: 'file' | 'directory'; size: number }[] }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
-    const dirEntries = await invoke<DirEntry[]>('list_directory', { path: resolvedPath });
+    const dirEntries = await invoke<DirEntry[]>('list_directory', { filesystemHexId, agentHexId, path: resolvedPath });
     return { entries: dirEntries.map(e => ({ name: e.name, type: e.is_dir ? 'directory' as const : 'file' as const, size: e.size })) };
   }
 
-  private async searchFiles(pattern: string, basePath: string, workspaceRoot?: string): Promise<{ matches: string[] }> {
+  private async listTree(params: Record<string, unknown>, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ root: string; truncated: boolean; entries: { path: string; type: 'file' | 'directory'; size: number; modified: number | null; truncated?: boolean }[] }> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    const resolvedPath = this.resolvePath((params.path as string) || '.', workspaceRoot);
+    const options: TreeOptions = {
+      max_depth: params.maxDepth as number | undefined,
+      include: (params.include as string[] | undefined) ?? [],
+      exclude: (params.exclude as string[] | undefined) ?? [],
+      respect_gitignore: !params.includeIgnored,
+      include_hidden: Boolean(params.includeHidden),
+      max_entries: params.maxEntries as number | undefined,
+    };
+    const listing = await invoke<TreeListing>('list_tree', { filesystemHexId, agentHexId, path: resolvedPath, options });
+    const prefix = listing.root.endsWith('/') ? listing.root : `${listing.root}/`;
+    return {
+      root: listing.root,
+      truncated: listing.truncated,
+      entries: listing.entries.map(e => ({
+        path: e.path.startsWith(prefix) ? e.path.slice(prefix.length) : e.path,
+        type: e.is_dir ? 'directory' as const : 'file' as const,
+        size: e.size,
+        modified: e.modified,
+        ...(e.truncated ? { truncated: true } : {}),
+      })),
+    };
+  }
+
+  private async searchFiles(pattern: string, basePath: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ matches: string[] }> {
     const matches: string[] = [];
-    await this.searchRecursive(basePath, pattern, matches, 0, workspaceRoot);
+    await this.searchRecursive(basePath, pattern, matches, 0, workspaceRoot, filesystemHexId, agentHexId);
     return { matches };
   }
 
-  private async searchRecursive(path: string, pattern: string, matches: string[], depth: number, workspaceRoot?: string): Promise<void> {
+  private async searchRecursive(path: string, pattern: string, matches: string[], depth: number, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<void> {
     if (depth > 10) return;
-    const { entries } = await this.listDirectory(path, workspaceRoot);
+    const { entries } = await this.listDirectory(path, workspaceRoot, filesystemHexId, agentHexId);
     const regex = new RegExp(pattern.replace(/\*/g, '.*').replace(/\?/g, '.'));
     for (const entry of entries) {
       const fullPath = path === '.' ? entry.name : `${path}/${entry.name}`;
       if (entry.type === 'file' && regex.test(entry.name)) matches.push(fullPath);
-      else if (entry.type === 'directory' && !entry.name.startsWith('.')) await this.searchRecursive(fullPath, pattern, matches, depth + 1, workspaceRoot);
+      else if (entry.type === 'directory' && !entry.name.startsWith('.')) await this.searchRecursive(fullPath, pattern, matches, depth + 1, workspaceRoot, filesystemHexId, agentHexId);
     }
   }
 
-  private async deleteFile(path: string, workspaceRoot?: string): Promise<{ deleted: string }> {
+  private async grep(params: Record<string, unknown>, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<SearchFilesResult> {
+    const { invoke } = await import('@tauri-apps/api/core');
+    const resolvedPath = this.resolvePath((params.path as string) || '.', workspaceRoot);
+    const options: ContentSearchOptions = {
+      regex: Boolean(params.regex),
+      case_sensitive: params.caseSensitive !== false,
+      whole_word: Boolean(params.wholeWord),
+      context_lines: (params.contextLines as number | undefined) ?? 2,
+      include: (params.include as string[] | undefined) ?? [],
+      exclude: (params.exclude as string[] | undefined) ?? [],
+      max_results: params.maxResults as number | undefined,
+    };
+    return invoke<SearchFilesResult>('search_files', { filesystemHexId, agentHexId, path: resolvedPath, query: params.query as string, options });
+  }
+
+  private async deleteFile(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ deleted: string }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
-    await invoke('delete_file', { path: resolvedPath });
+    await invoke('delete_file', { filesystemHexId, agentHexId, path: resolvedPath });
     return { deleted: resolvedPath };
   }
 
-  private async deleteDirectory(path: string, workspaceRoot?: string): Promise<{ deleted: string }> {
+  private async deleteDirectory(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ deleted: string }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
-    await invoke('delete_directory', { path: resolvedPath });
+    await invoke('delete_directory', { filesystemHexId, agentHexId, path: resolvedPath });
     return { deleted: resolvedPath };
   }
 
-  private async copyFile(source: string, destination: string, workspaceRoot?: string): Promise<{ source: string; destination: string; bytesCopied: number }> {
+  private async copyFile(source: string, destination: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ source: string; destination: string; bytesCopied: number }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedSource = this.resolvePath(source, workspaceRoot);
     const resolvedDest = this.resolvePath(destination, workspaceRoot);
-    const bytesCopied = await invoke<number>('copy_file', { source: resolvedSource, destination: resolvedDest });
+    const bytesCopied = await invoke<number>('copy_file', { filesystemHexId, agentHexId, source: resolvedSource, destination: resolvedDest });
     return { source: resolvedSource, destination: resolvedDest, bytesCopied };
   }
 
-  private async moveFile(source: string, destination: string, workspaceRoot?: string): Promise<{ source: string; destination: string }> {
+  private async moveFile(source: string, destination: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ source: string; destination: string }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedSource = this.resolvePath(source, workspaceRoot);
     const resolvedDest = this.resolvePath(destination, workspaceRoot);
-    await invoke('move_file', { source: resolvedSource, destination: resolvedDest });
+    await invoke('move_file', { filesystemHexId, agentHexId, source: resolvedSource, destination: resolvedDest });
     return { source: resolvedSource, destination: resolvedDest };
   }
 
-  private async createDirectory(path: string, workspaceRoot?: string): Promise<{ created: string }> {
+  private async createDirectory(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ created: string }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
-    await invoke('create_directory', { path: resolvedPath });
+    await invoke('create_directory', { filesystemHexId, agentHexId, path: resolvedPath });
     return { created: resolvedPath };
   }
 
-  private async fileExists(path: string, workspaceRoot?: string): Promise<{ path: string; exists: boolean }> {
+  private async fileExists(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string): Promise<{ path: string; exists: boolean }> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
-    const exists = await invoke<boolean>('file_exists', { path: resolvedPath });
+    const exists = await invoke<boolean>('file_exists', { filesystemHexId, agentHexId, path: resolvedPath });
     return { path: resolvedPath, exists };
   }
 
-  private async getFileInfo(path: string, workspaceRoot?: string): Promise<FileInfo> {
+  private async getFileInfo(path: string, workspaceRoot: string, filesystemHexId: string, agentHexId?: string, includeHash?: boolean): Promise<FileInfo> {
     const { invoke } = await import('@tauri-apps/api/core');
     const resolvedPath = this.resolvePath(path, workspaceRoot);
-    return invoke<FileInfo>('get_file_info', { path: resolvedPath });
+    return invoke<FileInfo>('get_file_info', { filesystemHexId, agentHexId, path: resolvedPath, includeHash });
   }
 
-  private async codebaseSearch(query: string, filesystemHexId?: string, limit: number = 10): Promise<CodebaseSearchResult> {
+  private async codebaseSearch(query: string, filesystemHexId?: string, limit: number = 10, agentHexId?: string, mode: SearchMode = 'hybrid', keywordWeight?: number, filters?: SearchFilters): Promise<CodebaseSearchResult> {
     const { invoke } = await import('@tauri-apps/api/core');
     const isReady = await invoke<boolean>('indexer_is_ready');
     if (!isReady) await invoke('indexer_initialize');
-    const request: SearchRequest = { query, filesystem_hex_ids: filesystemHexId ? [filesystemHexId] : [], limit };
+    const request: SearchRequest = { query, filesystem_hex_ids: filesystemHexId ? [filesystemHexId] : [], limit, mode, keyword_weight: keywordWeight, filters, agent_hex_id: agentHexId };
     const results = await invoke<SearchResultItem[]>('indexer_search', { request });
     return {
       query,
@@ -317,17 +452,43 @@ export class FilesystemPlugin implements ToolPlugin {
         startLine: r.chunk.start_line,
         endLine: r.chunk.end_line,
         content: r.chunk.content,
+        kind: r.chunk.kind ?? null,
+        symbol: qualifiedSymbol(r.chunk),
+        signature: r.chunk.signature ?? null,
         distance: r.distance,
-        relevance: 1 - r.distance,
+        relevance: r.distance === null ? null : 1 - r.distance,
+        score: r.score,
       })),
     };
   }
 }
 
+/** Name a chunk's symbol the way its language would, e.g. `Database::add_board_usage` */
+function qualifiedSymbol(chunk: CodeChunk): string | null {
+  if (!chunk.symbol) return null;
+  if (!chunk.parent_symbol) return chunk.symbol;
+  const separator = ['rust', 'cpp'].includes(chunk.language ?? '') ? '::' : '.';
+  return `${chunk.parent_symbol}${separator}${chunk.symbol}`;
+}
+
 // Type definitions
-interface FileInfo { path: string; exists: boolean; is_file: boolean; is_dir: boolean; size: number; modified: number | null; created: number | null; readonly: boolean; }
-interface SearchRequest { query: string; filesystem_hex_ids: string[]; limit?: number; }
-interface CodeChunk { id: string; filesystem_hex_id: string; file_path: string; start_line: number; end_line: number; content: string; language?: string; }
-interface SearchResultItem { chunk: CodeChunk; distance: number; }
-interface CodebaseSearchResult { query: string; resultCount: number; results: { filePath: string; startLine: number; endLine: number; content: string; distance: number; relevance: number; }[]; }
+interface FileInfo { path: string; exists: boolean; is_file: boolean; is_dir: boolean; size: number; modified: number | null; modified_ms: number | null; created: number | null; readonly: boolean; hash: string | null; }
+interface TreeOptions { max_depth?: number; include: string[]; exclude: string[]; respect_gitignore: boolean; include_hidden: boolean; max_entries?: number; }
+interface TreeEntry { name: string; path: string; depth: number; is_dir: boolean; is_file: boolean; size: number; modified: number | null; truncated: boolean; }
+interface TreeListing { root: string; entries: TreeEntry[]; truncated: boolean; }
+interface ContentSearchOptions { regex: boolean; case_sensitive: boolean; whole_word: boolean; context_lines: number; include: string[]; exclude: string[]; max_results?: number; }
+interface SearchMatch { path: string; line: number; column: number; text: string; context_before: string[]; context_after: string[]; }
+interface SearchFilesResult { matches: SearchMatch[]; files_searched: number; truncated: boolean; }
+interface WritePrecondition { expected_hash?: string; expected_mtime_ms?: number; }
+interface WriteResult { path: string; bytes_written: number; hash: string; modified_ms: number | null; }
+interface HunkResult { hunk: number; applied: boolean; line: number | null; fuzzy: boolean; error: string | null; }
+interface PatchResult { path: string; written: boolean; hunks: HunkResult[]; hash: string | null; }
+type FileRange = { lines: { start: number; end?: number } } | { bytes: { offset: number; length?: number } };
+interface FileRangeContent { path: string; encoding: 'utf-8' | 'utf-16le' | 'utf-16be' | 'latin-1' | 'binary'; mode: 'text' | 'base64'; content: string; offset: number; length: number; start_line: number | null; end_line: number | null; total_bytes: number; eof: boolean; truncated: boolean; }
+type SearchMode = 'vector' | 'keyword' | 'hybrid';
+interface SearchFilters { languages: string[]; include: string[]; exclude: string[]; kinds: string[]; min_similarity?: number; }
+interface SearchRequest { query: string; filesystem_hex_ids: string[]; limit?: number; mode?: SearchMode; keyword_weight?: number; filters?: SearchFilters; agent_hex_id?: string; }
+interface CodeChunk { id: string; filesystem_hex_id: string; file_path: string; start_line: number; end_line: number; content: string; language?: string; kind?: string; symbol?: string; node_kind?: string; parent_symbol?: string; signature?: string; }
+interface SearchResultItem { chunk: CodeChunk; distance: number | null; score: number; }
+interface CodebaseSearchResult { query: string; resultCount: number; results: { filePath: string; startLine: number; endLine: number; content: string; kind: string | null; symbol: string | null; signature: string | null; distance: number | null; relevance: number | null; score: number; }[]; }
 
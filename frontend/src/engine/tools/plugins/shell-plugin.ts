@@ -27,6 +27,12 @@ interface ShellResult {
   stderr: string;
 }
 
+// Per-call options for the Tauri shell command
+interface ShellOptions {
+  shell_hex_id?: string;
+  agent_hex_id?: string;
+}
+
 // Zod schemas for shell tools
 const executeSchema = z.object({
   command: z.string().describe('Shell command to execute'),
@@ -154,12 +160,17 @@ export class ShellPlugin implements ToolPlugin {
   async execute(toolName: string, params: Record<string, unknown>, _context: ExecutionContext): Promise<ToolResult> {
     const start = Date.now();
     const workingDir = (params.cwd as string) || (params.workingDirectory as string) || this.config.workingDirectory;
+    // Lets the backend apply this hex's env config and check the board's RBAC
+    const options: ShellOptions = {
+      shell_hex_id: params._sourceEntityId as string | undefined,
+      agent_hex_id: params._agentEntityId as string | undefined,
+    };
 
     try {
       let result: unknown;
       switch (toolName) {
-        case 'execute': result = await this.executeCommand(params.command as string, workingDir); break;
-        case 'execute_script': result = await this.executeScript(params.script as string, (params.interpreter as string) || 'bash', workingDir); break;
+        case 'execute': result = await this.executeCommand(params.command as string, workingDir, options); break;
+        case 'execute_script': result = await this.executeScript(params.script as string, (params.interpreter as string) || 'bash', workingDir, options); break;
         default: return { success: false, error: `Unknown tool: ${toolName}`, duration: Date.now() - start };
       }
       return { success: true, result, duration: Date.now() - start };
@@ -169,7 +180,7 @@ export class ShellPlugin implements ToolPlugin {
   }
 
   // === Private helper methods ===
-  private async executeCommand(command: string, cwd?: string): Promise<{ stdout: string; stderr: string; exitCode: number }> {
+  private async executeCommand(command: string, cwd?: string, options?: ShellOptions): Promise<{ stdout: string; stderr: string; exitCode: number }> {
     // Validate command against allowlist if configured
     if (this.config.allowedCommands && this.config.allowedCommands.length > 0) {
       const baseCommand = command.split(' ')[0];
@@ -192,7 +203,7 @@ export class ShellPlugin implements ToolPlugin {
       else if ('Stderr' in event) streamedOutput.stderr.push(event.Stderr);
     };
 
-    const result = await invoke<ShellResult>('execute_shell', { command, cwd: workingDir, onEvent });
+    const result = await invoke<ShellResult>('execute_shell', { command, cwd: workingDir, options, onEvent });
 
     const lastEntry = this.commandHistory[this.commandHistory.length - 1];
     if (lastEntry && lastEntry.command === command) {
@@ -202,7 +213,7 @@ export class ShellPlugin implements ToolPlugin {
     return { stdout: result.stdout, stderr: result.stderr, exitCode: result.exit_code };
   }
 
-  private async executeScript(script: string, interpreter: string, cwd?: string): Promise<{ stdout: string; stderr: string; exitCode: number }> {
+  private async executeScript(script: string, interpreter: string, cwd?: string, options?: ShellOptions): Promise<{ stdout: string; stderr: string; exitCode: number }> {
     console.log(`[ShellPlugin] Executing script with ${interpreter} (${script.length} chars) in ${cwd || 'default'}`);
 
     let command: string;
@@ -221,7 +232,7 @@ export class ShellPlugin implements ToolPlugin {
         command = `${interpreter} -c ${this.escapeShellArg(script)}`;
     }
 
-    return this.executeCommand(command, cwd);
+    return this.executeCommand(command, cwd, options);
   }
 
   private escapeShellArg(arg: string): string {
@@ -118,6 +118,8 @@ export interface EngineEvent {
     | 'shell.command.exit'
     // Filesystem change events
     | 'filesystem.changed'
+    // Codebase indexing progress
+    | 'indexer.progress'
     // User message injection
     | 'user.message';
   hexId: string;